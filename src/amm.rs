@@ -0,0 +1,191 @@
+//! Constant product pool hosted by the program for graduated curves.
+//!
+//! A pool trades the token of a graduated curve against its quote with
+//! `token_reserve * quote_reserve = k`. Swaps pay [`POOL_FEE_BPS`] of what they put
+//! in, which stays in the pool and accrues to liquidity providers. Like the curves,
+//! the pool rounds in its own favour: up for what a trader pays, down for what a
+//! trader receives.
+
+use pinocchio::program_error::ProgramError;
+
+use crate::{
+    error::BondingCurveError,
+    fees::BPS_DENOMINATOR,
+    math::{self, Rounding},
+};
+
+/// Fee on every swap, in basis points of the amount put in.
+pub const POOL_FEE_BPS: u16 = 25;
+
+/// Side of the pool a swap puts in.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SwapDirection {
+    /// Quote in, tokens out.
+    QuoteToToken = 0,
+
+    /// Tokens in, quote out.
+    TokenToQuote = 1,
+}
+
+impl TryFrom<u8> for SwapDirection {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::QuoteToToken),
+            1 => Ok(Self::TokenToQuote),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+/// Reserves and liquidity of a pool.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Pool {
+    pub token_reserve: u64,
+    pub quote_reserve: u64,
+    pub lp_supply: u64,
+}
+
+impl Pool {
+    /// A pool seeded with `token_reserve` and `quote_reserve`, minting the geometric
+    /// mean of the two as its first liquidity.
+    pub fn seed(token_reserve: u64, quote_reserve: u64) -> Result<Self, ProgramError> {
+        let lp_supply = math::to_u64(math::sqrt(token_reserve as u128 * quote_reserve as u128))?;
+        if lp_supply == 0 {
+            return Err(BondingCurveError::InsufficientReserve.into());
+        }
+
+        Ok(Self {
+            token_reserve,
+            quote_reserve,
+            lp_supply,
+        })
+    }
+
+    /// Amount a swap of `amount_in` in `direction` receives, after the fee.
+    pub fn swap_out(&self, direction: SwapDirection, amount_in: u64) -> Result<u64, ProgramError> {
+        let (reserve_in, reserve_out) = match direction {
+            SwapDirection::QuoteToToken => (self.quote_reserve, self.token_reserve),
+            SwapDirection::TokenToQuote => (self.token_reserve, self.quote_reserve),
+        };
+        let fee = math::mul_div_u64(
+            amount_in,
+            POOL_FEE_BPS as u64,
+            BPS_DENOMINATOR,
+            Rounding::Up,
+        )?;
+        let net_in = amount_in - fee;
+
+        math::mul_div_u64(
+            reserve_out,
+            net_in,
+            reserve_in
+                .checked_add(net_in)
+                .ok_or(BondingCurveError::MathOverflow)?,
+            Rounding::Down,
+        )
+    }
+
+    /// Applies a swap of `amount_in` that received `amount_out`.
+    pub fn swap(
+        &mut self,
+        direction: SwapDirection,
+        amount_in: u64,
+        amount_out: u64,
+    ) -> Result<(), ProgramError> {
+        let (reserve_in, reserve_out) = match direction {
+            SwapDirection::QuoteToToken => (&mut self.quote_reserve, &mut self.token_reserve),
+            SwapDirection::TokenToQuote => (&mut self.token_reserve, &mut self.quote_reserve),
+        };
+        *reserve_in = reserve_in
+            .checked_add(amount_in)
+            .ok_or(BondingCurveError::MathOverflow)?;
+        *reserve_out = reserve_out
+            .checked_sub(amount_out)
+            .ok_or(BondingCurveError::InsufficientReserve)?;
+        Ok(())
+    }
+
+    /// Largest deposit within `max_token_in` and `max_quote_in` at the pool ratio, as
+    /// the tokens and quote it takes and the liquidity it mints.
+    pub fn deposit(
+        &self,
+        max_token_in: u64,
+        max_quote_in: u64,
+    ) -> Result<(u64, u64, u64), ProgramError> {
+        let lp_out = math::mul_div_u64(
+            max_token_in,
+            self.lp_supply,
+            self.token_reserve,
+            Rounding::Down,
+        )?
+        .min(math::mul_div_u64(
+            max_quote_in,
+            self.lp_supply,
+            self.quote_reserve,
+            Rounding::Down,
+        )?);
+        let token_in = math::mul_div_u64(lp_out, self.token_reserve, self.lp_supply, Rounding::Up)?;
+        let quote_in = math::mul_div_u64(lp_out, self.quote_reserve, self.lp_supply, Rounding::Up)?;
+        Ok((token_in, quote_in, lp_out))
+    }
+
+    /// Tokens and quote `lp_in` of liquidity withdraws.
+    pub fn withdrawal(&self, lp_in: u64) -> Result<(u64, u64), ProgramError> {
+        if lp_in > self.lp_supply {
+            return Err(BondingCurveError::InsufficientReserve.into());
+        }
+        let token_out =
+            math::mul_div_u64(lp_in, self.token_reserve, self.lp_supply, Rounding::Down)?;
+        let quote_out =
+            math::mul_div_u64(lp_in, self.quote_reserve, self.lp_supply, Rounding::Down)?;
+        Ok((token_out, quote_out))
+    }
+
+    /// Adds `token_in` and `quote_in` to the reserves and `lp` to the supply.
+    pub fn add_liquidity(
+        &mut self,
+        token_in: u64,
+        quote_in: u64,
+        lp: u64,
+    ) -> Result<(), ProgramError> {
+        self.token_reserve = self
+            .token_reserve
+            .checked_add(token_in)
+            .ok_or(BondingCurveError::MathOverflow)?;
+        self.quote_reserve = self
+            .quote_reserve
+            .checked_add(quote_in)
+            .ok_or(BondingCurveError::MathOverflow)?;
+        self.lp_supply = self
+            .lp_supply
+            .checked_add(lp)
+            .ok_or(BondingCurveError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Takes `token_out` and `quote_out` out of the reserves and `lp` out of the
+    /// supply.
+    pub fn remove_liquidity(
+        &mut self,
+        token_out: u64,
+        quote_out: u64,
+        lp: u64,
+    ) -> Result<(), ProgramError> {
+        self.token_reserve = self
+            .token_reserve
+            .checked_sub(token_out)
+            .ok_or(BondingCurveError::InsufficientReserve)?;
+        self.quote_reserve = self
+            .quote_reserve
+            .checked_sub(quote_out)
+            .ok_or(BondingCurveError::InsufficientReserve)?;
+        self.lp_supply = self
+            .lp_supply
+            .checked_sub(lp)
+            .ok_or(BondingCurveError::InsufficientReserve)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,473 @@
+//! Pricing curves.
+//!
+//! Every curve prices trades from the same state: the virtual reserves the curve
+//! started with, the current virtual token reserve (so the amount sold so far) and,
+//! for the linear and exponential curves, a growth scale. The collateral reserve
+//! always grows by what buyers pay and shrinks by what sellers receive.
+
+use pinocchio::program_error::ProgramError;
+
+use crate::{
+    error::BondingCurveError,
+    math::{self, mul_div, Rounding, ONE},
+    state::BondingCurveState,
+};
+
+/// Fixed-point scale of spot prices: prices are quote units per token in Q64.64.
+pub const PRICE_SCALE: u128 = ONE;
+
+/// Largest `tokens / growth_scale` ratio an exponential curve may reach.
+pub const MAX_EXPONENT: u64 = 40;
+
+/// Costs are rounded up and refunds down, so rounding never drains the pool.
+pub trait Curve {
+    /// Amount of collateral a buyer pays for `amount` tokens.
+    fn cost_to_buy(&self, amount: u64) -> Result<u64, ProgramError>;
+
+    /// Amount of collateral a seller receives for `amount` tokens.
+    fn refund_for_sell(&self, amount: u64) -> Result<u64, ProgramError>;
+
+    /// Largest amount of tokens whose cost does not exceed `quote_in`.
+    fn amount_for_quote_in(&self, quote_in: u64) -> Result<u64, ProgramError>;
+
+    /// Smallest amount of tokens whose refund covers `quote_out`.
+    fn amount_for_quote_out(&self, quote_out: u64) -> Result<u64, ProgramError>;
+
+    /// Marginal price of the next token, in quote units per token scaled by
+    /// [`PRICE_SCALE`].
+    fn spot_price(&self) -> Result<u128, ProgramError>;
+}
+
+/// Shape of the pricing curve, selected at `Initialize`.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CurveType {
+    /// `vtoken_reserve * vsol_reserve = k`.
+    ConstantProduct = 0,
+
+    /// The price grows by the starting price every `growth_scale` tokens sold.
+    Linear = 1,
+
+    /// The price grows by a factor of `e` every `growth_scale` tokens sold.
+    Exponential = 2,
+}
+
+impl TryFrom<u8> for CurveType {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::ConstantProduct),
+            1 => Ok(Self::Linear),
+            2 => Ok(Self::Exponential),
+            _ => Err(BondingCurveError::InvalidCurveParameters.into()),
+        }
+    }
+}
+
+/// Constant product curve over the current virtual reserves.
+pub struct ConstantProduct {
+    pub vtoken_reserve: u64,
+    pub vsol_reserve: u64,
+}
+
+impl Curve for ConstantProduct {
+    // `vsol * vtoken = (vsol + cost) * (vtoken - amount)` solved for the cost, and
+    // likewise for the refund
+    fn cost_to_buy(&self, amount: u64) -> Result<u64, ProgramError> {
+        // The reserve can never be fully drained
+        let new_vtoken_reserve = self
+            .vtoken_reserve
+            .checked_sub(amount)
+            .filter(|reserve| *reserve > 0)
+            .ok_or(BondingCurveError::InsufficientReserve)?;
+        math::mul_div_u64(self.vsol_reserve, amount, new_vtoken_reserve, Rounding::Up)
+    }
+
+    fn refund_for_sell(&self, amount: u64) -> Result<u64, ProgramError> {
+        let new_vtoken_reserve = self.vtoken_reserve as u128 + amount as u128;
+        math::to_u64(mul_div(
+            self.vsol_reserve as u128,
+            amount as u128,
+            new_vtoken_reserve,
+            Rounding::Down,
+        )?)
+    }
+
+    // The same invariant solved for the amount; both are exact, so the amounts need
+    // no settling against the forward pricing
+    fn amount_for_quote_in(&self, quote_in: u64) -> Result<u64, ProgramError> {
+        math::to_u64(mul_div(
+            self.vtoken_reserve as u128,
+            quote_in as u128,
+            self.vsol_reserve as u128 + quote_in as u128,
+            Rounding::Down,
+        )?)
+    }
+
+    fn amount_for_quote_out(&self, quote_out: u64) -> Result<u64, ProgramError> {
+        let remaining = self
+            .vsol_reserve
+            .checked_sub(quote_out)
+            .filter(|reserve| *reserve > 0)
+            .ok_or(BondingCurveError::InsufficientReserve)?;
+        math::mul_div_u64(quote_out, self.vtoken_reserve, remaining, Rounding::Up)
+    }
+
+    fn spot_price(&self) -> Result<u128, ProgramError> {
+        if self.vtoken_reserve == 0 {
+            return Err(BondingCurveError::InsufficientReserve.into());
+        }
+        mul_div(
+            self.vsol_reserve as u128,
+            PRICE_SCALE,
+            self.vtoken_reserve as u128,
+            Rounding::Down,
+        )
+    }
+}
+
+/// Linear curve: `price(sold) = start_price * (1 + sold / growth_scale)`, where the
+/// start price is `initial_vsol_reserve / initial_vtoken_reserve`.
+pub struct Linear {
+    pub initial_vtoken_reserve: u64,
+    pub initial_vsol_reserve: u64,
+    pub sold: u64,
+    pub growth_scale: u64,
+}
+
+impl Linear {
+    /// Area under the price line between `sold` and `sold + amount` when `forward`,
+    /// or between `sold - amount` and `sold` otherwise:
+    /// `V0 * amount * (2 * scale + 2 * sold ± amount) / (2 * T0 * scale)`.
+    fn area(&self, amount: u64, forward: bool, rounding: Rounding) -> Result<u64, ProgramError> {
+        let base = 2 * (self.growth_scale as u128 + self.sold as u128);
+        let width = if forward {
+            base + amount as u128
+        } else {
+            base - amount as u128
+        };
+
+        // Rounding both divisions the same way rounds the whole quotient that way
+        let doubled = mul_div(
+            self.initial_vsol_reserve as u128 * amount as u128,
+            width,
+            self.initial_vtoken_reserve as u128 * self.growth_scale as u128,
+            rounding,
+        )?;
+        math::to_u64(math::div(doubled, 2, rounding)?)
+    }
+
+    /// `2 * quote * T0 * scale / V0`, the constant term of the quadratic solved for
+    /// the amount that `quote` pays for.
+    fn quadratic_term(&self, quote: u64, rounding: Rounding) -> Result<u128, ProgramError> {
+        mul_div(
+            quote as u128 * self.initial_vtoken_reserve as u128,
+            2 * self.growth_scale as u128,
+            self.initial_vsol_reserve as u128,
+            rounding,
+        )
+    }
+
+    /// `(scale + sold)^2`.
+    fn offset_squared(&self) -> Result<u128, ProgramError> {
+        let offset = self.growth_scale as u128 + self.sold as u128;
+        offset
+            .checked_mul(offset)
+            .ok_or(BondingCurveError::MathOverflow.into())
+    }
+}
+
+impl Curve for Linear {
+    fn cost_to_buy(&self, amount: u64) -> Result<u64, ProgramError> {
+        check_buy(self.initial_vtoken_reserve, self.sold, amount)?;
+        self.area(amount, true, Rounding::Up)
+    }
+
+    fn refund_for_sell(&self, amount: u64) -> Result<u64, ProgramError> {
+        check_sell(self.sold, amount)?;
+        self.area(amount, false, Rounding::Down)
+    }
+
+    // `amount = sqrt((scale + sold)^2 + 2 * quote * T0 * scale / V0) - (scale + sold)`
+    fn amount_for_quote_in(&self, quote_in: u64) -> Result<u64, ProgramError> {
+        let radicand = self
+            .offset_squared()?
+            .checked_add(self.quadratic_term(quote_in, Rounding::Down)?)
+            .ok_or(BondingCurveError::MathOverflow)?;
+        let offset = self.growth_scale as u128 + self.sold as u128;
+        let estimate = (math::sqrt(radicand) - offset).min(u64::MAX as u128) as u64;
+
+        fit_quote_in(
+            self,
+            estimate,
+            self.initial_vtoken_reserve - self.sold,
+            quote_in,
+        )
+    }
+
+    // `amount = (scale + sold) - sqrt((scale + sold)^2 - 2 * quote * T0 * scale / V0)`
+    fn amount_for_quote_out(&self, quote_out: u64) -> Result<u64, ProgramError> {
+        let radicand = self
+            .offset_squared()?
+            .checked_sub(self.quadratic_term(quote_out, Rounding::Up)?)
+            .ok_or(BondingCurveError::InsufficientReserve)?;
+        let offset = self.growth_scale as u128 + self.sold as u128;
+        let estimate = (offset - math::sqrt(radicand)).min(u64::MAX as u128) as u64;
+
+        fit_quote_out(self, estimate, self.sold, quote_out)
+    }
+
+    fn spot_price(&self) -> Result<u128, ProgramError> {
+        mul_div(
+            self.initial_vsol_reserve as u128 * PRICE_SCALE,
+            self.growth_scale as u128 + self.sold as u128,
+            self.initial_vtoken_reserve as u128 * self.growth_scale as u128,
+            Rounding::Down,
+        )
+    }
+}
+
+/// Exponential curve: `price(sold) = start_price * e^(sold / growth_scale)`, where
+/// the start price is `initial_vsol_reserve / initial_vtoken_reserve`.
+pub struct Exponential {
+    pub initial_vtoken_reserve: u64,
+    pub initial_vsol_reserve: u64,
+    pub sold: u64,
+    pub growth_scale: u64,
+}
+
+impl Exponential {
+    /// `e^(sold / growth_scale)` in Q64.64.
+    fn growth(&self, sold: u64, rounding: Rounding) -> Result<u128, ProgramError> {
+        let exponent = math::div((sold as u128) << 64, self.growth_scale as u128, rounding)?;
+        math::exp(exponent, rounding)
+    }
+
+    /// `V0 * scale * (e^(to / scale) - e^(from / scale)) / T0`.
+    fn area(&self, from: u64, to: u64, rounding: Rounding) -> Result<u64, ProgramError> {
+        // The bounds on the two growths would otherwise leave a unit of area
+        if from == to {
+            return Ok(0);
+        }
+
+        let delta = self
+            .growth(to, rounding)?
+            .saturating_sub(self.growth(from, rounding.reverse())?);
+
+        math::to_u64(mul_div(
+            delta,
+            self.initial_vsol_reserve as u128 * self.growth_scale as u128,
+            (self.initial_vtoken_reserve as u128) << 64,
+            rounding,
+        )?)
+    }
+
+    /// `quote * T0 / (V0 * scale)` in Q64.64, the change in growth that `quote` pays
+    /// for.
+    fn growth_for_quote(&self, quote: u64) -> Result<u128, ProgramError> {
+        mul_div(
+            quote as u128 * self.initial_vtoken_reserve as u128,
+            ONE,
+            self.initial_vsol_reserve as u128 * self.growth_scale as u128,
+            Rounding::Down,
+        )
+    }
+
+    /// Number of tokens sold once growth has reached `growth`, `scale * ln(growth)`.
+    fn sold_at_growth(&self, growth: u128) -> Result<u64, ProgramError> {
+        let sold = mul_div(
+            math::ln(growth)?,
+            self.growth_scale as u128,
+            ONE,
+            Rounding::Down,
+        )?;
+        Ok(sold.min(u64::MAX as u128) as u64)
+    }
+}
+
+impl Curve for Exponential {
+    fn cost_to_buy(&self, amount: u64) -> Result<u64, ProgramError> {
+        check_buy(self.initial_vtoken_reserve, self.sold, amount)?;
+        self.area(self.sold, self.sold + amount, Rounding::Up)
+    }
+
+    fn refund_for_sell(&self, amount: u64) -> Result<u64, ProgramError> {
+        check_sell(self.sold, amount)?;
+        self.area(self.sold - amount, self.sold, Rounding::Down)
+    }
+
+    // `amount = scale * ln(e^(sold / scale) + quote * T0 / (V0 * scale)) - sold`
+    fn amount_for_quote_in(&self, quote_in: u64) -> Result<u64, ProgramError> {
+        let growth = self
+            .growth(self.sold, Rounding::Down)?
+            .checked_add(self.growth_for_quote(quote_in)?)
+            .ok_or(BondingCurveError::MathOverflow)?;
+        let estimate = self.sold_at_growth(growth)?.saturating_sub(self.sold);
+
+        fit_quote_in(
+            self,
+            estimate,
+            self.initial_vtoken_reserve - self.sold,
+            quote_in,
+        )
+    }
+
+    // `amount = sold - scale * ln(e^(sold / scale) - quote * T0 / (V0 * scale))`
+    fn amount_for_quote_out(&self, quote_out: u64) -> Result<u64, ProgramError> {
+        let growth = self
+            .growth(self.sold, Rounding::Down)?
+            .checked_sub(self.growth_for_quote(quote_out)?)
+            .filter(|growth| *growth >= ONE)
+            .ok_or(BondingCurveError::InsufficientReserve)?;
+        let estimate = self.sold.saturating_sub(self.sold_at_growth(growth)?);
+
+        fit_quote_out(self, estimate, self.sold, quote_out)
+    }
+
+    fn spot_price(&self) -> Result<u128, ProgramError> {
+        mul_div(
+            self.growth(self.sold, Rounding::Down)?,
+            self.initial_vsol_reserve as u128,
+            self.initial_vtoken_reserve as u128,
+            Rounding::Down,
+        )
+    }
+}
+
+/// The curve configured for a bonding curve state account.
+pub enum BondingCurve {
+    ConstantProduct(ConstantProduct),
+    Linear(Linear),
+    Exponential(Exponential),
+}
+
+impl BondingCurve {
+    pub fn from_state(state: &BondingCurveState) -> Result<Self, ProgramError> {
+        let sold = state
+            .initial_vtoken_reserve()
+            .checked_sub(state.vtoken_reserve_amount())
+            .ok_or(BondingCurveError::MathOverflow)?;
+
+        Ok(match state.curve_type()? {
+            CurveType::ConstantProduct => Self::ConstantProduct(ConstantProduct {
+                vtoken_reserve: state.vtoken_reserve_amount(),
+                vsol_reserve: state.vsol_reserve_amount(),
+            }),
+            CurveType::Linear => Self::Linear(Linear {
+                initial_vtoken_reserve: state.initial_vtoken_reserve(),
+                initial_vsol_reserve: state.initial_vsol_reserve(),
+                sold,
+                growth_scale: state.growth_scale(),
+            }),
+            CurveType::Exponential => Self::Exponential(Exponential {
+                initial_vtoken_reserve: state.initial_vtoken_reserve(),
+                initial_vsol_reserve: state.initial_vsol_reserve(),
+                sold,
+                growth_scale: state.growth_scale(),
+            }),
+        })
+    }
+
+    fn as_curve(&self) -> &dyn Curve {
+        match self {
+            Self::ConstantProduct(curve) => curve,
+            Self::Linear(curve) => curve,
+            Self::Exponential(curve) => curve,
+        }
+    }
+}
+
+impl Curve for BondingCurve {
+    fn cost_to_buy(&self, amount: u64) -> Result<u64, ProgramError> {
+        self.as_curve().cost_to_buy(amount)
+    }
+
+    fn refund_for_sell(&self, amount: u64) -> Result<u64, ProgramError> {
+        self.as_curve().refund_for_sell(amount)
+    }
+
+    fn amount_for_quote_in(&self, quote_in: u64) -> Result<u64, ProgramError> {
+        self.as_curve().amount_for_quote_in(quote_in)
+    }
+
+    fn amount_for_quote_out(&self, quote_out: u64) -> Result<u64, ProgramError> {
+        self.as_curve().amount_for_quote_out(quote_out)
+    }
+
+    fn spot_price(&self) -> Result<u128, ProgramError> {
+        self.as_curve().spot_price()
+    }
+}
+
+/// Buys may take at most the remaining virtual token reserve.
+fn check_buy(initial_vtoken_reserve: u64, sold: u64, amount: u64) -> Result<(), ProgramError> {
+    match sold.checked_add(amount) {
+        Some(total) if total <= initial_vtoken_reserve => Ok(()),
+        _ => Err(BondingCurveError::InsufficientReserve.into()),
+    }
+}
+
+/// Sells may return at most the tokens sold so far.
+fn check_sell(sold: u64, amount: u64) -> Result<(), ProgramError> {
+    if amount > sold {
+        return Err(BondingCurveError::InsufficientReserve.into());
+    }
+    Ok(())
+}
+
+/// Settles an estimated inverse on the largest amount up to `max` whose cost fits in
+/// `quote_in`.
+///
+/// The closed-form inverses are only exact up to rounding, so the amount is checked
+/// against the forward pricing, which is what the trade is charged with.
+fn fit_quote_in(
+    curve: &dyn Curve,
+    estimate: u64,
+    max: u64,
+    quote_in: u64,
+) -> Result<u64, ProgramError> {
+    let fits = |amount| curve.cost_to_buy(amount).is_ok_and(|cost| cost <= quote_in);
+
+    let mut amount = estimate.min(max);
+    while amount > 0 && !fits(amount) {
+        amount -= 1;
+    }
+    while amount < max && fits(amount + 1) {
+        amount += 1;
+    }
+
+    // The remaining reserve cannot absorb the whole quote
+    if amount == max && curve.cost_to_buy(max)? < quote_in {
+        return Err(BondingCurveError::InsufficientReserve.into());
+    }
+    Ok(amount)
+}
+
+/// Settles an estimated inverse on the smallest amount up to `max` whose refund
+/// covers `quote_out`, see [`fit_quote_in`].
+fn fit_quote_out(
+    curve: &dyn Curve,
+    estimate: u64,
+    max: u64,
+    quote_out: u64,
+) -> Result<u64, ProgramError> {
+    let covers = |amount| {
+        curve
+            .refund_for_sell(amount)
+            .is_ok_and(|refund| refund >= quote_out)
+    };
+
+    if !covers(max) {
+        return Err(BondingCurveError::InsufficientReserve.into());
+    }
+
+    let mut amount = estimate.min(max);
+    while amount < max && !covers(amount) {
+        amount += 1;
+    }
+    while amount > 0 && covers(amount - 1) {
+        amount -= 1;
+    }
+    Ok(amount)
+}
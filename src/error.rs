@@ -0,0 +1,141 @@
+use pinocchio::program_error::ProgramError;
+
+/// Errors that may be returned by the bonding curve program.
+///
+/// Each variant is surfaced to clients as `ProgramError::Custom` with the
+/// variant's discriminant as the error code.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BondingCurveError {
+    /// The account discriminator does not match the expected account type.
+    InvalidAccountDiscriminator = 0,
+
+    /// The account layout version is not supported by this program.
+    UnsupportedAccountVersion = 1,
+
+    /// A mint account does not match the mint recorded in the curve state.
+    InvalidMint = 2,
+
+    /// An account does not match the address derived by the program.
+    InvalidPda = 3,
+
+    /// The curve reserves cannot cover the requested amount.
+    InsufficientReserve = 4,
+
+    /// An arithmetic operation overflowed.
+    MathOverflow = 5,
+
+    /// The price moved past the limit set by the user.
+    SlippageExceeded = 6,
+
+    /// The curve has not reached its migration threshold.
+    NotMigratable = 7,
+
+    /// The curve parameters passed to `Initialize` are invalid.
+    InvalidCurveParameters = 8,
+
+    /// The signer does not hold the authority the instruction requires.
+    Unauthorized = 9,
+
+    /// The program is paused by its admin.
+    ProgramPaused = 10,
+
+    /// The curve is paused by the admin.
+    CurvePaused = 11,
+
+    /// The token mint is not controlled by the curve alone.
+    InvalidMintAuthority = 12,
+
+    /// The curve has sold its migration allocation, or has migrated, and no longer
+    /// trades.
+    CurveNotTrading = 13,
+
+    /// The curve has already migrated.
+    AlreadyMigrated = 14,
+
+    /// An account passed for the migration target is not the one the target expects.
+    InvalidMigrationAccount = 15,
+
+    /// The collateral of the curve cannot pay the migration fee.
+    InsufficientCollateral = 16,
+
+    /// The liquidity of the migrated curve is locked until its unlock timestamp.
+    LpLocked = 17,
+
+    /// The curve has not migrated yet.
+    NotMigrated = 18,
+
+    /// The curve did not lock its liquidity or vest its unsold tokens when it
+    /// migrated, so there is nothing of the kind to claim.
+    NothingToClaim = 19,
+
+    /// A trade names its own trader as referrer.
+    InvalidReferrer = 20,
+}
+
+impl BondingCurveError {
+    /// Return the error for a `ProgramError::Custom` code, if it is one of ours.
+    pub fn from_code(code: u32) -> Option<Self> {
+        Some(match code {
+            0 => Self::InvalidAccountDiscriminator,
+            1 => Self::UnsupportedAccountVersion,
+            2 => Self::InvalidMint,
+            3 => Self::InvalidPda,
+            4 => Self::InsufficientReserve,
+            5 => Self::MathOverflow,
+            6 => Self::SlippageExceeded,
+            7 => Self::NotMigratable,
+            8 => Self::InvalidCurveParameters,
+            9 => Self::Unauthorized,
+            10 => Self::ProgramPaused,
+            11 => Self::CurvePaused,
+            12 => Self::InvalidMintAuthority,
+            13 => Self::CurveNotTrading,
+            14 => Self::AlreadyMigrated,
+            15 => Self::InvalidMigrationAccount,
+            16 => Self::InsufficientCollateral,
+            17 => Self::LpLocked,
+            18 => Self::NotMigrated,
+            19 => Self::NothingToClaim,
+            20 => Self::InvalidReferrer,
+            _ => return None,
+        })
+    }
+
+    /// Human readable description of the error, for use by off-chain clients.
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::InvalidAccountDiscriminator => "Account is not of the expected type",
+            Self::UnsupportedAccountVersion => "Account layout version is not supported",
+            Self::InvalidMint => "Mint does not match the bonding curve",
+            Self::InvalidPda => "Account does not match the derived program address",
+            Self::InsufficientReserve => "Insufficient reserve for the requested amount",
+            Self::MathOverflow => "Math overflow",
+            Self::SlippageExceeded => "Price moved past the slippage limit",
+            Self::NotMigratable => "Bonding curve has not reached its migration threshold",
+            Self::InvalidCurveParameters => "Invalid bonding curve parameters",
+            Self::Unauthorized => "Signer is not authorized for this instruction",
+            Self::ProgramPaused => "Program is paused",
+            Self::CurvePaused => "Bonding curve is paused",
+            Self::InvalidMintAuthority => "Mint authority is not the bonding curve",
+            Self::CurveNotTrading => "Bonding curve is not trading",
+            Self::AlreadyMigrated => "Bonding curve has already migrated",
+            Self::InvalidMigrationAccount => "Account does not match the migration target",
+            Self::InsufficientCollateral => "Collateral does not cover the migration fee",
+            Self::LpLocked => "Liquidity is locked until its unlock timestamp",
+            Self::NotMigrated => "Bonding curve has not migrated",
+            Self::NothingToClaim => "Bonding curve has nothing of the kind to claim",
+            Self::InvalidReferrer => "Trader cannot be its own referrer",
+        }
+    }
+}
+
+/// Return the message for a `ProgramError::Custom` code returned by this program.
+pub fn error_message(code: u32) -> Option<&'static str> {
+    BondingCurveError::from_code(code).map(|error| error.message())
+}
+
+impl From<BondingCurveError> for ProgramError {
+    fn from(error: BondingCurveError) -> Self {
+        ProgramError::Custom(error as u32)
+    }
+}
@@ -0,0 +1,105 @@
+//! Trading fees.
+//!
+//! Fees are charged in the quote asset on top of what the curve prices, so they never
+//! enter the curve reserves. Each fee is split between the protocol and the creator of
+//! the curve; the protocol keeps the rounding remainder. When a trade names a referrer,
+//! the referrer is paid a share of the fee out of the protocol's part.
+
+use pinocchio::program_error::ProgramError;
+
+use crate::{
+    error::BondingCurveError,
+    math::{self, Rounding},
+};
+
+/// Basis points in a whole.
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Highest trading fee a curve may charge, 10%.
+pub const MAX_FEE_BPS: u16 = 1_000;
+
+/// Fee settings of a curve.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Fees {
+    /// Fee charged on every trade, in basis points of the quote traded.
+    pub fee_bps: u16,
+
+    /// Share of each fee paid to the curve creator, in basis points of the fee.
+    pub creator_share_bps: u16,
+}
+
+impl Fees {
+    pub fn validate(&self) -> Result<(), ProgramError> {
+        if self.fee_bps > MAX_FEE_BPS || self.creator_share_bps as u64 > BPS_DENOMINATOR {
+            return Err(BondingCurveError::InvalidCurveParameters.into());
+        }
+        Ok(())
+    }
+
+    /// Protocol's share of every trade before referrals, in basis points of the quote
+    /// traded, rounded down.
+    pub fn protocol_fee_bps(&self) -> u16 {
+        (self.fee_bps as u64 * (BPS_DENOMINATOR - self.creator_share_bps as u64)
+            / BPS_DENOMINATOR) as u16
+    }
+
+    /// Fee on a trade of `quote` priced by the curve, rounded up.
+    pub fn fee_on(&self, quote: u64) -> Result<u64, ProgramError> {
+        math::mul_div_u64(quote, self.fee_bps as u64, BPS_DENOMINATOR, Rounding::Up)
+    }
+
+    /// Splits the `quote_in` a buyer spends into the quote the curve prices and the
+    /// fee on it. The fee takes the rounding remainder.
+    pub fn split_quote_in(&self, quote_in: u64) -> Result<(u64, u64), ProgramError> {
+        let net = math::mul_div_u64(
+            quote_in,
+            BPS_DENOMINATOR,
+            BPS_DENOMINATOR + self.fee_bps as u64,
+            Rounding::Down,
+        )?;
+        Ok((net, quote_in - net))
+    }
+
+    /// Quote the curve has to refund for a seller to receive `quote_out` after the fee,
+    /// and that fee. The fee takes the rounding remainder.
+    pub fn gross_for_quote_out(&self, quote_out: u64) -> Result<(u64, u64), ProgramError> {
+        let gross = math::mul_div_u64(
+            quote_out,
+            BPS_DENOMINATOR,
+            BPS_DENOMINATOR - self.fee_bps as u64,
+            Rounding::Up,
+        )?;
+        Ok((gross, gross - quote_out))
+    }
+
+    /// Splits `fee` into the protocol's and the creator's shares.
+    pub fn split(&self, fee: u64) -> Result<(u64, u64), ProgramError> {
+        let creator = math::mul_div_u64(
+            fee,
+            self.creator_share_bps as u64,
+            BPS_DENOMINATOR,
+            Rounding::Down,
+        )?;
+        Ok((fee - creator, creator))
+    }
+
+    /// Splits `fee` into the protocol's, the creator's and the referrer's shares.
+    ///
+    /// The referrer is paid `referral_share_bps` of the fee out of the protocol's
+    /// share, and never more than that share.
+    pub fn split_with_referral(
+        &self,
+        fee: u64,
+        referral_share_bps: u16,
+    ) -> Result<(u64, u64, u64), ProgramError> {
+        let (protocol, creator) = self.split(fee)?;
+        let referrer = math::mul_div_u64(
+            fee,
+            referral_share_bps as u64,
+            BPS_DENOMINATOR,
+            Rounding::Down,
+        )?
+        .min(protocol);
+        Ok((protocol - referrer, creator, referrer))
+    }
+}
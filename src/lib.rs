@@ -1,101 +1,86 @@
+pub mod amm;
+pub mod curve;
+pub mod error;
+pub mod fees;
+pub mod math;
+pub mod migration;
+pub mod pda;
+pub mod state;
+pub mod sysvars;
+
 #[cfg(test)]
 mod tests;
 
 use pinocchio::{
-    account_info::AccountInfo, entrypoint, instruction::{ Seed, Signer}, msg,  program_error::ProgramError, pubkey:: Pubkey, ProgramResult
+    account_info::{AccountInfo, Ref, RefMut}, entrypoint, instruction::{ Seed, Signer}, msg,  program_error::ProgramError, pubkey:: Pubkey, ProgramResult
+};
+use curve::{BondingCurve, Curve, CurveType, MAX_EXPONENT};
+use pinocchio_system::instructions::{self as system, CreateAccount};
+use pinocchio_token::{
+    instructions::{
+        AuthorityType, Burn, InitilizeAccount3, InitilizeMint2, MintTo, SetAuthority, Transfer,
+    },
+    state::{Mint, TokenAccount},
+};
+use amm::{Pool, SwapDirection};
+use error::BondingCurveError;
+use fees::Fees;
+use migration::{Liquidity, LpPolicy, MigrationTarget, PoolCreation, UnsoldTokenPolicy};
+use state::{
+    BondingCurveState, CurveStatus, GlobalConfig, PoolState, ProgramAccount, QuoteMode,
+    ReferrerStats, SupplyMode,
 };
-use pinocchio_token::instructions::{Burn, InitilizeAccount3, MintTo, Transfer};
-use solana_nostd_sha256::hashv;
 
 
 pinocchio::entrypoint!(process_instruction);
 
-/*
-pub struct BondingCurveState {
-    pub is_initialized: bool,
-    pub vtoken_reserve: u64,    // Virtual reserve of the token
-    pub vtoken_mint: [u8; 32],
-    pub vsol_reserve: u64,      // Virtual reserve of the collateral (SOL)
-    pub vsol_mint: [u8; 32],
-    pub total_supply: u64,      // Total supply of tokens
-    pub allocation_at_migration: u64,
-}
- */
+// Default curve parameters, borrowed from Moonshot
+// https://docs.moonshot.cc/developers/bonding-curve-solana
+pub const INITIAL_VTOKEN: u64 = 1_073_000_000;
+pub const INITIAL_VSOL: u64 = 30; // Equivalent to 0.00000002795 SOL initial price per token
+pub const TOTAL_SUPPLY: u64 = 1_000_000_000;
+pub const ALLOCATION_AT_MIGRATION: u64 = 800_000_000; // Approximately 80% of total supp
 
-const MIGRATION_FEE_RAYDIUM: u64 = 6; // In SOL
-// const MIGRATION_FEE_METEORA: u64 = 3; // In SOL
+/// Decimals of every token mint created by the program.
+pub const TOKEN_DECIMALS: u8 = 6;
 
+/// Mint of wrapped SOL, the only SPL quote whose collateral is in lamports.
+pub const NATIVE_MINT: Pubkey =
+    five8_const::decode_32_const("So11111111111111111111111111111111111111112");
 
-// Constants borrowed from Moonshot
-// https://docs.moonshot.cc/developers/bonding-curve-solana
-const INITIAL_VTOKEN: u64 = 1_073_000_000;
-const INITIAL_VSOL: u64 = 30; // Equivalent to 0.00000002795 SOL initial price per token
-const TOTAL_SUPPLY: u64 = 1_000_000_000;
-const ALLOCATION_AT_MIGRATION: u64 = 800_000_000; // Approximately 80% of total supp
+/// The upgradeable BPF loader, which owns the program data account of the program.
+pub const BPF_LOADER_UPGRADEABLE_ID: Pubkey =
+    five8_const::decode_32_const("BPFLoaderUpgradeab1e11111111111111111111111");
 
-pub const RAND: &[u8; 11] = b"random_seed";
 
 
 // change program id here
 pub const ID: [u8; 32] =
-    five8_const::decode_32_const("111111111111111111111111111111111111111");
-
+    five8_const::decode_32_const("Cewh1X3Q2ikTp7Fc4dnSkWRUBV6beVdzZakYKXEKCHRj");
 
-pub struct BondingCurveState (*const u8);
-
-impl BondingCurveState {
-    pub const LEN: usize = 1 + 8 + 32 + 8 + 32 + 8 + 8;
-
-    #[inline(always)]
-    pub fn from_account_info_unchecked(account_info: &AccountInfo) -> Self {
-        unsafe { Self(account_info.borrow_data_unchecked().as_ptr()) }
-    }
-
-    pub fn from_account_info(account_info: &AccountInfo) -> Self {
-        assert_eq!(account_info.data_len(), Self::LEN);
-        assert_eq!(account_info.owner(), &ID);
-        Self::from_account_info_unchecked(account_info)
-    }
-
-    pub fn is_initialized(&self) -> bool {
-        unsafe { core::ptr::read_unaligned(self.0 as *const u8) != 0 }
-    }
-
-    pub fn vtoken_reserve_amount(&self) -> u64 {
-        unsafe { core::ptr::read_unaligned(self.0.add(1) as *const u64) }
-    }
-
-    pub fn vtoken_mint(&self) -> [u8; 32] {
-        let mut mint = [0u8; 32];
-        mint.copy_from_slice(unsafe { core::slice::from_raw_parts(self.0.add(9), 32) });
-        mint
-    }
-
-    pub fn vsol_reserve_amount(&self) -> u64 {
-        unsafe { core::ptr::read_unaligned(self.0.add(9) as *const u64) }
-    }
-
-    pub fn vsol_mint(&self) -> [u8; 32] {
-        let mut mint = [0u8; 32];
-        mint.copy_from_slice(unsafe { core::slice::from_raw_parts(self.0.add(41), 32) });
-        mint
-    }
-
-    pub fn total_supply(&self) -> u64 {
-        unsafe { core::ptr::read_unaligned(self.0.add(17) as *const u64) }
-    }
-    
-    pub fn allocation_at_migration(&self) -> u64 {
-        unsafe { core::ptr::read_unaligned(self.0.add(25) as *const u64) }
-    }
-
-}
 
 pub enum BondingCurveInstruction {
     Initialize,
     Buy,
     Sell,
     Migrate,
+    BuyExactIn,
+    SellExactOut,
+    InitializeConfig,
+    UpdateConfig,
+    ProposeAdmin,
+    AcceptAdmin,
+    CancelAdminProposal,
+    SetPaused,
+    SetCurvePaused,
+    ClaimProtocolFees,
+    ClaimCreatorFees,
+    InitializeReferrerStats,
+    Swap,
+    AddLiquidity,
+    RemoveLiquidity,
+    ClaimLockedLp,
+    ClaimVestedTokens,
 }
 
 impl TryFrom<&u8> for BondingCurveInstruction {
@@ -107,6 +92,23 @@ impl TryFrom<&u8> for BondingCurveInstruction {
             1 => Ok(Self::Buy),
             2 => Ok(Self::Sell),
             3 => Ok(Self::Migrate),
+            4 => Ok(Self::BuyExactIn),
+            5 => Ok(Self::SellExactOut),
+            6 => Ok(Self::InitializeConfig),
+            7 => Ok(Self::UpdateConfig),
+            8 => Ok(Self::ProposeAdmin),
+            9 => Ok(Self::AcceptAdmin),
+            10 => Ok(Self::CancelAdminProposal),
+            11 => Ok(Self::SetPaused),
+            12 => Ok(Self::SetCurvePaused),
+            13 => Ok(Self::ClaimProtocolFees),
+            14 => Ok(Self::ClaimCreatorFees),
+            15 => Ok(Self::InitializeReferrerStats),
+            16 => Ok(Self::Swap),
+            17 => Ok(Self::AddLiquidity),
+            18 => Ok(Self::RemoveLiquidity),
+            19 => Ok(Self::ClaimLockedLp),
+            20 => Ok(Self::ClaimVestedTokens),
             _ => Err(ProgramError::InvalidInstructionData),
         }
     }
@@ -126,253 +128,2492 @@ fn process_instruction(
         BondingCurveInstruction::Initialize => initialize(accounts, data),
         BondingCurveInstruction::Buy => buy(accounts, data),
         BondingCurveInstruction::Sell => sell(accounts, data),
-        BondingCurveInstruction::Migrate => migrate(accounts),
+        BondingCurveInstruction::Migrate => migrate(accounts, data),
+        BondingCurveInstruction::BuyExactIn => buy_exact_in(accounts, data),
+        BondingCurveInstruction::SellExactOut => sell_exact_out(accounts, data),
+        BondingCurveInstruction::InitializeConfig => initialize_config(accounts, data),
+        BondingCurveInstruction::UpdateConfig => update_config(accounts, data),
+        BondingCurveInstruction::ProposeAdmin => propose_admin(accounts, data),
+        BondingCurveInstruction::AcceptAdmin => accept_admin(accounts),
+        BondingCurveInstruction::CancelAdminProposal => cancel_admin_proposal(accounts),
+        BondingCurveInstruction::SetPaused => set_paused(accounts, data),
+        BondingCurveInstruction::SetCurvePaused => set_curve_paused(accounts, data),
+        BondingCurveInstruction::ClaimProtocolFees => claim_protocol_fees(accounts),
+        BondingCurveInstruction::ClaimCreatorFees => claim_creator_fees(accounts),
+        BondingCurveInstruction::InitializeReferrerStats => initialize_referrer_stats(accounts, data),
+        BondingCurveInstruction::Swap => swap(accounts, data),
+        BondingCurveInstruction::AddLiquidity => add_liquidity(accounts, data),
+        BondingCurveInstruction::RemoveLiquidity => remove_liquidity(accounts, data),
+        BondingCurveInstruction::ClaimLockedLp => claim_locked_lp(accounts),
+        BondingCurveInstruction::ClaimVestedTokens => claim_vested_tokens(accounts),
     }
 }
 
 
 
-pub fn initialize(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
-    let [state_account, vtoken_mint, state_token_account, vsol_mint, admin] = accounts else {
+/// Curve parameters encoded after the bump in the `Initialize` instruction data.
+///
+/// ### Layout (little-endian):
+///   - `[0..8]`: initial virtual token reserve
+///   - `[8..16]`: initial virtual collateral reserve
+///   - `[16..24]`: total supply
+///   - `[24..32]`: allocation at migration
+///   - `[32]`: curve type, see [`CurveType`]
+///   - `[33..41]`: growth scale of the linear and exponential curves
+///   - `[41]`: quote mode, see [`QuoteMode`]
+///   - `[42]`: bump of the quote vault, only used by native curves
+///   - `[43..45]`: trading fee in basis points
+///   - `[45..47]`: creator share of the trading fee in basis points
+///   - `[47]`: supply mode, see [`SupplyMode`]
+///   - `[48]`: bump of the token vault, only used by pre-minted curves
+///   - `[49]`: migration target, see [`MigrationTarget`]
+///   - `[50]`: LP policy, see [`LpPolicy`]
+///   - `[51..59]`: unix timestamp from which locked liquidity can be claimed
+///   - `[59]`: unsold token policy, see [`UnsoldTokenPolicy`]
+///   - `[60..68]`: seconds over which vested tokens are released
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InitializeArgs {
+    pub initial_vtoken_reserve: u64,
+    pub initial_vsol_reserve: u64,
+    pub total_supply: u64,
+    pub allocation_at_migration: u64,
+    pub curve_type: CurveType,
+    pub growth_scale: u64,
+    pub quote_mode: QuoteMode,
+    pub quote_vault_bump: u8,
+    pub fees: Fees,
+    pub supply_mode: SupplyMode,
+    pub token_vault_bump: u8,
+    pub migration_target: MigrationTarget,
+    pub lp_policy: LpPolicy,
+    pub lp_unlock_timestamp: i64,
+    pub unsold_token_policy: UnsoldTokenPolicy,
+    pub vesting_duration: u64,
+}
+
+impl InitializeArgs {
+    pub const LEN: usize = 68;
+
+    pub fn try_from_bytes(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        Ok(Self {
+            initial_vtoken_reserve: read_u64(data, 0)?,
+            initial_vsol_reserve: read_u64(data, 8)?,
+            total_supply: read_u64(data, 16)?,
+            allocation_at_migration: read_u64(data, 24)?,
+            curve_type: CurveType::try_from(data[32])?,
+            growth_scale: read_u64(data, 33)?,
+            quote_mode: QuoteMode::try_from(data[41])?,
+            quote_vault_bump: data[42],
+            fees: Fees {
+                fee_bps: u16::from_le_bytes([data[43], data[44]]),
+                creator_share_bps: u16::from_le_bytes([data[45], data[46]]),
+            },
+            supply_mode: SupplyMode::try_from(data[47])?,
+            token_vault_bump: data[48],
+            migration_target: MigrationTarget::try_from(data[49])?,
+            lp_policy: LpPolicy::try_from(data[50])?,
+            lp_unlock_timestamp: read_u64(data, 51)? as i64,
+            unsold_token_policy: UnsoldTokenPolicy::try_from(data[59])?,
+            vesting_duration: read_u64(data, 60)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut data = [0; Self::LEN];
+        data[0..8].copy_from_slice(&self.initial_vtoken_reserve.to_le_bytes());
+        data[8..16].copy_from_slice(&self.initial_vsol_reserve.to_le_bytes());
+        data[16..24].copy_from_slice(&self.total_supply.to_le_bytes());
+        data[24..32].copy_from_slice(&self.allocation_at_migration.to_le_bytes());
+        data[32] = self.curve_type as u8;
+        data[33..41].copy_from_slice(&self.growth_scale.to_le_bytes());
+        data[41] = self.quote_mode as u8;
+        data[42] = self.quote_vault_bump;
+        data[43..45].copy_from_slice(&self.fees.fee_bps.to_le_bytes());
+        data[45..47].copy_from_slice(&self.fees.creator_share_bps.to_le_bytes());
+        data[47] = self.supply_mode as u8;
+        data[48] = self.token_vault_bump;
+        data[49] = self.migration_target as u8;
+        data[50] = self.lp_policy as u8;
+        data[51..59].copy_from_slice(&self.lp_unlock_timestamp.to_le_bytes());
+        data[59] = self.unsold_token_policy as u8;
+        data[60..68].copy_from_slice(&self.vesting_duration.to_le_bytes());
+        data
+    }
+
+    /// Rejects parameters a curve could not trade with: zero values, a migration
+    /// allocation above the supply or the virtual token reserve, growth scales that
+    /// are missing or would push an exponential curve past [`MAX_EXPONENT`], fees
+    /// above [`fees::MAX_FEE_BPS`], external migration targets for curves quoted in
+    /// native SOL, which those targets cannot take, unlock timestamps for liquidity
+    /// that is burned rather than locked, vesting durations for tokens that are not
+    /// vested and vested tokens without one, which would all be released at once.
+    pub fn validate(&self) -> Result<(), ProgramError> {
+        self.fees.validate()?;
+
+        let invalid = self.initial_vtoken_reserve == 0
+            || self.initial_vsol_reserve == 0
+            || self.total_supply == 0
+            || self.allocation_at_migration == 0
+            || self.allocation_at_migration > self.total_supply
+            || self.allocation_at_migration >= self.initial_vtoken_reserve
+            || match self.curve_type {
+                CurveType::ConstantProduct => false,
+                CurveType::Linear => self.growth_scale == 0,
+                CurveType::Exponential => {
+                    self.growth_scale == 0
+                        || self.initial_vtoken_reserve / self.growth_scale >= MAX_EXPONENT
+                }
+            }
+            || (self.migration_target.is_external() && self.quote_mode == QuoteMode::Native)
+            || (self.lp_policy == LpPolicy::Burn && self.lp_unlock_timestamp != 0)
+            || match self.unsold_token_policy {
+                UnsoldTokenPolicy::Vest => self.vesting_duration == 0,
+                UnsoldTokenPolicy::SeedPool | UnsoldTokenPolicy::Burn => self.vesting_duration != 0,
+            };
+
+        if invalid {
+            return Err(BondingCurveError::InvalidCurveParameters.into());
+        }
+        Ok(())
+    }
+}
+
+impl Default for InitializeArgs {
+    fn default() -> Self {
+        Self {
+            initial_vtoken_reserve: INITIAL_VTOKEN,
+            initial_vsol_reserve: INITIAL_VSOL,
+            total_supply: TOTAL_SUPPLY,
+            allocation_at_migration: ALLOCATION_AT_MIGRATION,
+            curve_type: CurveType::ConstantProduct,
+            growth_scale: 0,
+            quote_mode: QuoteMode::SplToken,
+            quote_vault_bump: 0,
+            fees: Fees::default(),
+            supply_mode: SupplyMode::MintOnBuy,
+            token_vault_bump: 0,
+            migration_target: MigrationTarget::InHouse,
+            lp_policy: LpPolicy::Burn,
+            lp_unlock_timestamp: 0,
+            unsold_token_policy: UnsoldTokenPolicy::SeedPool,
+            vesting_duration: 0,
+        }
+    }
+}
+
+/// Config parameters encoded in the `InitializeConfig` and `UpdateConfig` instruction
+/// data.
+///
+/// ### Layout (little-endian):
+///   - `[0..32]`: fee recipient
+///   - `[32..40]`: migration fee in lamports
+///   - `[40]`: paused flag, `0` or `1`
+///   - `[41..109]`: default curve parameters, see [`InitializeArgs`]
+///   - `[109..111]`: referrer share of the trading fee in basis points
+///   - `[111..119]`: migration bounty in lamports, paid out of the migration fee
+///   - `[119..121]`: minimum protocol share of the trading fee of new curves, in basis
+///     points of the quote traded
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ConfigArgs {
+    pub fee_recipient: Pubkey,
+    pub migration_fee: u64,
+    pub paused: bool,
+    pub default_args: InitializeArgs,
+    pub referral_fee_share_bps: u16,
+    pub migration_bounty: u64,
+    pub min_protocol_fee_bps: u16,
+}
+
+impl ConfigArgs {
+    pub const LEN: usize = 53 + InitializeArgs::LEN;
+
+    pub fn try_from_bytes(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        Ok(Self {
+            fee_recipient: data[0..32].try_into().unwrap(),
+            migration_fee: read_u64(data, 32)?,
+            paused: read_flag(&data[40..41])?,
+            default_args: InitializeArgs::try_from_bytes(&data[41..109])?,
+            referral_fee_share_bps: u16::from_le_bytes([data[109], data[110]]),
+            migration_bounty: read_u64(data, 111)?,
+            min_protocol_fee_bps: u16::from_le_bytes([data[119], data[120]]),
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut data = [0; Self::LEN];
+        data[0..32].copy_from_slice(&self.fee_recipient);
+        data[32..40].copy_from_slice(&self.migration_fee.to_le_bytes());
+        data[40] = self.paused as u8;
+        data[41..109].copy_from_slice(&self.default_args.to_bytes());
+        data[109..111].copy_from_slice(&self.referral_fee_share_bps.to_le_bytes());
+        data[111..119].copy_from_slice(&self.migration_bounty.to_le_bytes());
+        data[119..121].copy_from_slice(&self.min_protocol_fee_bps.to_le_bytes());
+        data
+    }
+
+    /// Rejects default curve parameters a curve could not be created with, including
+    /// default fees below the minimum protocol share, a minimum above
+    /// [`fees::MAX_FEE_BPS`], a referral share above the whole fee and a migration fee
+    /// that does not cover the bounty and what the default target costs the caller, see
+    /// [`initialize`]. Curves created with the defaults are quoted in an SPL token, mint
+    /// on buy, seed their pool with all their unsold tokens and burn its liquidity, so
+    /// the defaults must too.
+    pub fn validate(&self) -> Result<(), ProgramError> {
+        self.default_args.validate()?;
+        if self.default_args.quote_mode != QuoteMode::SplToken
+            || self.default_args.supply_mode != SupplyMode::MintOnBuy
+            || self.default_args.lp_policy != LpPolicy::Burn
+            || self.default_args.unsold_token_policy != UnsoldTokenPolicy::SeedPool
+            || self.min_protocol_fee_bps > fees::MAX_FEE_BPS
+            || self.default_args.fees.protocol_fee_bps() < self.min_protocol_fee_bps
+            || self.referral_fee_share_bps as u64 > fees::BPS_DENOMINATOR
+            || self.migration_bounty.saturating_add(self.default_args.migration_target.fee())
+                > self.migration_fee
+        {
+            return Err(BondingCurveError::InvalidCurveParameters.into());
+        }
+        Ok(())
+    }
+
+    fn apply(&self, config: &mut GlobalConfig) {
+        config.set_fee_recipient(&self.fee_recipient);
+        config.set_migration_fee(self.migration_fee);
+        config.set_paused(self.paused);
+        config.set_default_args(&self.default_args);
+        config.set_referral_fee_share_bps(self.referral_fee_share_bps);
+        config.set_migration_bounty(self.migration_bounty);
+        config.set_min_protocol_fee_bps(self.min_protocol_fee_bps);
+    }
+}
+
+/// Creates the global config and makes the signer its admin. Only the upgrade
+/// authority of the program may sign, so nobody can take the config over by creating
+/// it first.
+///
+/// The config is a singleton PDA at its canonical bump, so this only succeeds once.
+///
+/// Accounts: the config, the upgrade authority (signer), the system program and the
+/// program data account of the program.
+///
+/// Instruction data: the canonical config bump followed by the [`ConfigArgs`].
+pub fn initialize_config(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [config_account, admin, _system_program, program_data, ..] = accounts else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
-    assert!(admin.is_signer());
+    if !admin.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    check_upgrade_authority(program_data, admin.key())?;
 
-    // Parse bump byte and any remaining data
-    let (bump, _data) = data
+    let (bump, data) = data
         .split_first()
         .ok_or(ProgramError::InvalidInstructionData)?;
 
-    // Directly manipulate the state data with unsafe
-    unsafe {
-        let data_ptr = state_account.borrow_mut_data_unchecked().as_mut_ptr();
+    let args = ConfigArgs::try_from_bytes(data)?;
+    args.validate()?;
+
+    let (address, canonical_bump) = pda::find_config_address()?;
+    if *bump != canonical_bump {
+        return Err(BondingCurveError::InvalidPda.into());
+    }
+    pda::check(config_account, address)?;
+
+    let bump_seed = [*bump];
+    let seeds = [Seed::from(pda::CONFIG_SEED), Seed::from(&bump_seed)];
+
+    CreateAccount {
+        from: admin,
+        to: config_account,
+        lamports: sysvars::rent()?.minimum_balance(GlobalConfig::LEN),
+        space: GlobalConfig::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
 
-        // Mark the state account as initialized
-        *data_ptr = 1;  // initialized flag at offset 0
+    let mut config = GlobalConfig::init(config_account)?;
+    config.set_bump(*bump);
+    config.set_admin(admin.key());
+    args.apply(&mut config);
+
+    Ok(())
+}
+
+/// Checks `authority` is the upgrade authority the upgradeable loader records in the
+/// program data account. A program deployed as immutable has none.
+fn check_upgrade_authority(program_data: &AccountInfo, authority: &Pubkey) -> ProgramResult {
+    if program_data.owner() != &BPF_LOADER_UPGRADEABLE_ID {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+    pda::check(program_data, pda::program_data_address()?)?;
+
+    // `UpgradeableLoaderState::ProgramData`: the `u32` variant, the `u64` slot of the
+    // last deployment, then the optional upgrade authority
+    let data = program_data.try_borrow_data()?;
+    let upgrade_authority = match data.get(..45) {
+        Some(metadata) if metadata[..4] == 3u32.to_le_bytes() && metadata[12] == 1 => {
+            &metadata[13..]
+        }
+        _ => return Err(BondingCurveError::Unauthorized.into()),
+    };
+    if upgrade_authority != authority {
+        return Err(BondingCurveError::Unauthorized.into());
+    }
+    Ok(())
+}
 
-        // Set initial vtoken amount at offset 1
-        *(data_ptr.add(1) as *mut u64) = INITIAL_VTOKEN;
+/// Replaces the config parameters. Only the config admin may call it.
+///
+/// Instruction data: the [`ConfigArgs`].
+pub fn update_config(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [config_account, admin, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
 
-        // Set vtoken_mint address at offset 9
-        *(data_ptr.add(9) as *mut [u8; 32]) = *vtoken_mint.key();
+    let args = ConfigArgs::try_from_bytes(data)?;
+    args.validate()?;
 
-        // Set initial vsol amount at offset 41
-        *(data_ptr.add(41) as *mut u64) = INITIAL_VSOL;
+    let mut config = load_config_as_admin(config_account, admin)?;
+    args.apply(&mut config);
 
-        // Set vsol_mint address at offset 49
-        *(data_ptr.add(49) as *mut [u8; 32]) = *vsol_mint.key();
+    Ok(())
+}
 
-        // Set total supply at offset 81
-        *(data_ptr.add(81) as *mut u64) = TOTAL_SUPPLY;
+/// Proposes a new config admin, replacing any pending proposal. The admin only changes
+/// once the proposed key accepts, so a mistyped key cannot lock the config.
+///
+/// Instruction data: the proposed admin.
+pub fn propose_admin(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [config_account, admin, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
 
-        // Set allocation at migration at offset 89
-        *(data_ptr.add(89) as *mut u64) = ALLOCATION_AT_MIGRATION;
+    let pending_admin: &Pubkey = data
+        .try_into()
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    if pending_admin == &[0; 32] {
+        return Err(ProgramError::InvalidInstructionData);
     }
 
-    let binding = bump.to_le_bytes();
-    let seeds = [Seed::from(state_account.key().as_ref()), Seed::from(&binding)];
-    let signer = [Signer::from(&seeds)];
+    let mut config = load_config_as_admin(config_account, admin)?;
+    config.set_pending_admin(Some(pending_admin));
 
-    // Initialize state token account with derived authority
-    InitilizeAccount3 {
-        token: state_token_account,
-        owner: state_account.key(),
+    Ok(())
+}
+
+/// Makes the proposed admin, which must sign, the config admin.
+pub fn accept_admin(accounts: &[AccountInfo]) -> ProgramResult {
+    let [config_account, pending_admin, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !pending_admin.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let mut config = load_config_mut(config_account)?;
+    if config.pending_admin() != Some(pending_admin.key()) {
+        return Err(BondingCurveError::Unauthorized.into());
+    }
+    config.set_admin(pending_admin.key());
+    config.set_pending_admin(None);
+
+    Ok(())
+}
+
+/// Withdraws a pending admin proposal. Only the current admin may call it.
+pub fn cancel_admin_proposal(accounts: &[AccountInfo]) -> ProgramResult {
+    let [config_account, admin, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let mut config = load_config_as_admin(config_account, admin)?;
+    config.set_pending_admin(None);
+
+    Ok(())
+}
+
+/// Pauses or resumes trading and migration on every curve. Only the config admin may
+/// call it.
+///
+/// Instruction data: `1` to pause, `0` to resume.
+pub fn set_paused(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [config_account, admin, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let paused = read_flag(data)?;
+    load_config_as_admin(config_account, admin)?.set_paused(paused);
+
+    Ok(())
+}
+
+/// Pauses or resumes trading and migration on a single curve. Only the config admin
+/// may call it.
+///
+/// Instruction data: `1` to pause, `0` to resume.
+pub fn set_curve_paused(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [config_account, admin, state_account, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let paused = read_flag(data)?;
+    load_config_as_admin(config_account, admin)?;
+    let mut state = BondingCurveState::load_mut(state_account)?;
+    check_curve(state_account, &state)?;
+    state.set_paused(paused);
+
+    Ok(())
+}
+
+/// Initializes a curve.
+///
+/// The token mint is a new account that must sign. It is created with
+/// [`TOKEN_DECIMALS`], the state as its only mint authority and no freeze authority.
+/// Pre-minted curves pass their token vault last: the whole supply is minted into it
+/// and the mint authority revoked.
+///
+/// The fourth account is the quote mint of an SPL quoted curve, or the quote vault of
+/// a native one. The system program creates the state, the fee vault and the vault
+/// holding the quote, see [`pda`].
+///
+/// The curve keeps the config migration fee and bounty it is created with. The fee is
+/// paid in lamports out of the collateral, so while the config charges one, curves
+/// must be quoted in native SOL or wrapped SOL. It must cover the bounty and refund
+/// whoever migrates the curve what its target costs, see [`MigrationTarget::fee`], so
+/// curves only migrate to an external DEX under a config charging at least that.
+///
+/// Instruction data: the state, vault and fee vault bumps, optionally followed by the
+/// [`InitializeArgs`]. Without them the curve starts from the config defaults. Either
+/// way its fees must leave the protocol the config minimum share.
+pub fn initialize(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [state_account, vtoken_mint, state_token_account, quote_account, admin, config_account, fee_vault, _system_program, remaining @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !admin.is_signer() || !vtoken_mint.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Parse the bump bytes and the curve parameters
+    let [bump, vault_bump, fee_vault_bump, data @ ..] = data else {
+        return Err(ProgramError::InvalidInstructionData);
+    };
+
+    let config = load_config(config_account)?;
+    let args = if data.is_empty() {
+        config.default_args()?
+    } else {
+        InitializeArgs::try_from_bytes(data)?
+    };
+    args.validate()?;
+    if args.fees.protocol_fee_bps() < config.min_protocol_fee_bps() {
+        return Err(BondingCurveError::InvalidCurveParameters.into());
+    }
+    let (migration_fee, migration_bounty) = (config.migration_fee(), config.migration_bounty());
+    if migration_fee > 0
+        && args.quote_mode == QuoteMode::SplToken
+        && quote_account.key() != &NATIVE_MINT
+    {
+        return Err(BondingCurveError::InvalidCurveParameters.into());
+    }
+    let caller_fee = args
+        .migration_target
+        .fee()
+        .checked_add(migration_bounty)
+        .ok_or(BondingCurveError::MathOverflow)?;
+    if caller_fee > migration_fee {
+        return Err(BondingCurveError::InvalidCurveParameters.into());
+    }
+    // A lock that is already over would hand the liquidity to the creator at migration
+    if args.lp_policy == LpPolicy::Lock
+        && args.lp_unlock_timestamp <= sysvars::clock()?.unix_timestamp
+    {
+        return Err(BondingCurveError::InvalidCurveParameters.into());
+    }
+
+    let state_key = state_account.key();
+    pda::check(state_account, pda::curve_address(vtoken_mint.key(), *bump)?)?;
+    pda::check(state_token_account, pda::vault_address(state_key, *vault_bump)?)?;
+    if args.quote_mode == QuoteMode::Native {
+        pda::check(quote_account, pda::quote_vault_address(state_key, args.quote_vault_bump)?)?;
+    }
+    pda::check(fee_vault, pda::fee_vault_address(state_key, *fee_vault_bump)?)?;
+    let token_vault = match args.supply_mode {
+        SupplyMode::MintOnBuy => None,
+        SupplyMode::PreMinted => {
+            let [token_vault, ..] = remaining else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+            pda::check(token_vault, pda::token_vault_address(state_key, args.token_vault_bump)?)?;
+            Some(token_vault)
+        }
+    };
+
+    let bump = [*bump];
+    let curve_seeds = pda::curve_seeds(vtoken_mint.key(), &bump);
+    CreateAccount {
+        from: admin,
+        to: state_account,
+        lamports: sysvars::rent()?.minimum_balance(BondingCurveState::LEN),
+        space: BondingCurveState::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&curve_seeds)])?;
+
+    // Only the curve can ever mint the token, and nobody can freeze it
+    CreateAccount {
+        from: admin,
+        to: vtoken_mint,
+        lamports: sysvars::rent()?.minimum_balance(Mint::LEN),
+        space: Mint::LEN as u64,
+        owner: &pinocchio_token::ID,
+    }
+    .invoke()?;
+    InitilizeMint2 {
         mint: vtoken_mint,
+        decimals: TOKEN_DECIMALS,
+        mint_authority: state_key,
+        freeze_authority: None,
+    }
+    .invoke()?;
+
+    {
+        let mut state = BondingCurveState::init(state_account)?;
+        state.set_vtoken_reserve_amount(args.initial_vtoken_reserve);
+        state.set_vtoken_mint(vtoken_mint.key());
+        state.set_vsol_reserve_amount(args.initial_vsol_reserve);
+        if args.quote_mode == QuoteMode::SplToken {
+            state.set_vsol_mint(quote_account.key());
+        }
+        state.set_total_supply(args.total_supply);
+        state.set_allocation_at_migration(args.allocation_at_migration);
+        state.set_initial_vtoken_reserve(args.initial_vtoken_reserve);
+        state.set_initial_vsol_reserve(args.initial_vsol_reserve);
+        state.set_curve_type(args.curve_type);
+        state.set_growth_scale(args.growth_scale);
+        state.set_quote_mode(args.quote_mode);
+        state.set_quote_vault_bump(args.quote_vault_bump);
+        state.set_fees(args.fees);
+        state.set_creator(admin.key());
+        state.set_fee_vault_bump(*fee_vault_bump);
+        state.set_bump(bump[0]);
+        state.set_vault_bump(*vault_bump);
+        state.set_supply_mode(args.supply_mode);
+        state.set_token_vault_bump(args.token_vault_bump);
+        state.set_migration_target(args.migration_target);
+        state.set_lp_policy(args.lp_policy);
+        state.set_lp_unlock_timestamp(args.lp_unlock_timestamp);
+        state.set_unsold_token_policy(args.unsold_token_policy);
+        state.set_vesting_duration(args.vesting_duration);
+        state.set_migration_fee(migration_fee);
+        state.set_migration_bounty(migration_bounty);
+    }
+
+    if args.quote_mode == QuoteMode::Native {
+        let bump = [args.quote_vault_bump];
+        let seeds = [
+            Seed::from(pda::QUOTE_VAULT_SEED),
+            Seed::from(state_key.as_ref()),
+            Seed::from(&bump),
+        ];
+
+        // The vault holds no data, so its rent-exempt minimum never changes
+        CreateAccount {
+            from: admin,
+            to: quote_account,
+            lamports: sysvars::rent()?.minimum_balance(0),
+            space: 0,
+            owner: &ID,
+        }
+        .invoke_signed(&[Signer::from(&seeds)])?;
+    }
+
+    // Fees are held in lamports by native curves, and in the quote token otherwise
+    let fee_vault_bump = [*fee_vault_bump];
+    let seeds = [
+        Seed::from(pda::FEE_VAULT_SEED),
+        Seed::from(state_key.as_ref()),
+        Seed::from(&fee_vault_bump),
+    ];
+    let (space, owner) = match args.quote_mode {
+        QuoteMode::SplToken => (TokenAccount::LEN, &pinocchio_token::ID),
+        QuoteMode::Native => (0, &ID),
+    };
+    CreateAccount {
+        from: admin,
+        to: fee_vault,
+        lamports: sysvars::rent()?.minimum_balance(space),
+        space: space as u64,
+        owner,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    if args.quote_mode == QuoteMode::SplToken {
+        // The vault is its own authority, so claims are signed with its seeds
+        InitilizeAccount3 {
+            token: fee_vault,
+            owner: fee_vault.key(),
+            mint: quote_account,
+        }
+        .invoke()?;
+    }
+
+    if args.quote_mode == QuoteMode::SplToken {
+        // The vault collects the quote paid for tokens, owned by the state so that
+        // only the program moves it
+        let vault_bump = [*vault_bump];
+        let seeds = [
+            Seed::from(pda::VAULT_SEED),
+            Seed::from(state_key.as_ref()),
+            Seed::from(&vault_bump),
+        ];
+        CreateAccount {
+            from: admin,
+            to: state_token_account,
+            lamports: sysvars::rent()?.minimum_balance(TokenAccount::LEN),
+            space: TokenAccount::LEN as u64,
+            owner: &pinocchio_token::ID,
+        }
+        .invoke_signed(&[Signer::from(&seeds)])?;
+
+        InitilizeAccount3 {
+            token: state_token_account,
+            owner: state_key,
+            mint: quote_account,
+        }
+        .invoke()?;
+    }
+
+    if let Some(token_vault) = token_vault {
+        let token_vault_bump = [args.token_vault_bump];
+        let seeds = [
+            Seed::from(pda::TOKEN_VAULT_SEED),
+            Seed::from(state_key.as_ref()),
+            Seed::from(&token_vault_bump),
+        ];
+        CreateAccount {
+            from: admin,
+            to: token_vault,
+            lamports: sysvars::rent()?.minimum_balance(TokenAccount::LEN),
+            space: TokenAccount::LEN as u64,
+            owner: &pinocchio_token::ID,
+        }
+        .invoke_signed(&[Signer::from(&seeds)])?;
+        InitilizeAccount3 {
+            token: token_vault,
+            owner: state_key,
+            mint: vtoken_mint,
+        }
+        .invoke()?;
+
+        // Mint the whole supply, then give up minting for good
+        MintTo {
+            mint: vtoken_mint,
+            token: token_vault,
+            mint_authority: state_account,
+            amount: args.total_supply,
+        }
+        .invoke_signed(&[Signer::from(&curve_seeds)])?;
+        SetAuthority {
+            account: vtoken_mint,
+            authority: state_account,
+            authority_type: AuthorityType::MintTokens,
+            new_authority: None,
+        }
+        .invoke_signed(&[Signer::from(&curve_seeds)])?;
     }
-    .invoke_signed(&signer)?;
 
     Ok(())
 }
 
+/// Buys an exact amount of tokens, or what is left of the migration allocation if
+/// that is less.
+///
+/// Instruction data: the token amount and the most quote the buyer is willing to pay
+/// for it including the fee, both as little-endian `u64`s.
 pub fn buy(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
-    let [state_account, buyer, buyer_ata, buying_mint, state_token_account, state_mint, _token_program] =
+    let amount = read_u64(data, 0)?;
+    let max_quote_in = read_u64(data, 8)?;
+
+    process_buy(accounts, |curve, fees, remaining_allocation| {
+        let amount = amount.min(remaining_allocation);
+        let cost = curve.cost_to_buy(amount)?;
+        let fee = fees.fee_on(cost)?;
+        if cost.checked_add(fee).is_none_or(|total| total > max_quote_in) {
+            return Err(BondingCurveError::SlippageExceeded.into());
+        }
+        Ok((amount, cost, fee))
+    })
+}
+
+/// Spends an exact amount of quote, fee included, on tokens. If that buys more than
+/// is left of the migration allocation, only the rest of the allocation is bought and
+/// paid for.
+///
+/// Instruction data: the quote amount and the fewest tokens the buyer accepts for it,
+/// both as little-endian `u64`s.
+pub fn buy_exact_in(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let quote_in = read_u64(data, 0)?;
+    let min_tokens_out = read_u64(data, 8)?;
+
+    process_buy(accounts, |curve, fees, remaining_allocation| {
+        let (quote, fee) = fees.split_quote_in(quote_in)?;
+        // Priced against the rest of the allocation first: the curve cannot price a quote
+        // worth more than its whole reserve
+        let allocation_cost = curve.cost_to_buy(remaining_allocation)?;
+        let (amount, quote, fee) = if quote >= allocation_cost {
+            (remaining_allocation, allocation_cost, fees.fee_on(allocation_cost)?)
+        } else {
+            (curve.amount_for_quote_in(quote)?, quote, fee)
+        };
+        // The limit applies to the tokens delivered, after the cap
+        if amount < min_tokens_out {
+            return Err(BondingCurveError::SlippageExceeded.into());
+        }
+        Ok((amount, quote, fee))
+    })
+}
+
+/// Shared by the buy instructions: `trade` prices the trade on the curve, given what
+/// is left of the migration allocation, and returns the token amount bought, the quote
+/// paid to the curve for it and the fee paid on top. The buy that sells the rest of the
+/// allocation completes the curve.
+///
+/// Accounts: the curve state, the buyer (signer), the buyer's token account, the
+/// token mint, the quote mint (or the native quote vault), the token program, the
+/// config and the fee vault. SPL quoted curves then pass their quote vault and the
+/// buyer's quote token account, native curves the system program. Pre-minted curves
+/// pass their token vault next, and the trade may name a referrer by passing its
+/// account and its [`ReferrerStats`] last, see [`trailing_accounts`].
+fn process_buy(
+    accounts: &[AccountInfo],
+    trade: impl FnOnce(&BondingCurve, &Fees, u64) -> Result<(u64, u64, u64), ProgramError>,
+) -> ProgramResult {
+    let [state_account, buyer, buyer_token_account, buying_mint, quote_account, _token_program, config_account, fee_vault, remaining @ ..] =
         accounts
     else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
-    assert!(buyer.is_signer());
-    
-    let (bump, data) = data
-        .split_first()
-        .ok_or(pinocchio::program_error::ProgramError::InvalidInstructionData)?;
+    if !buyer.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
 
-    let pda = hashv(&[
-        state_account.key().as_ref(),
-        &[*bump],
-        ID.as_ref(),
-        RAND,
-    ]);
+    let referral_fee_share_bps = load_active_config(config_account)?.referral_fee_share_bps();
 
-    assert!(pda == *state_token_account.key());
+    let (amount, sol_cost, fee, referral, spl_quote, token_vault, bump) = {
+        let mut state = BondingCurveState::load_mut(state_account)?;
+        check_curve(state_account, &state)?;
+        check_not_paused(&state)?;
+        check_trading(&state)?;
 
-    let bonding_curve_state = BondingCurveState::from_account_info(state_account);
+        if buying_mint.key() != state.vtoken_mint() {
+            return Err(BondingCurveError::InvalidMint.into());
+        }
+        check_mint(buying_mint, state_account.key(), &state)?;
+        check_token_account(buyer_token_account, state.vtoken_mint())?;
+        check_quote_account(state_account.key(), &state, quote_account)?;
+        check_fee_vault(state_account.key(), &state, fee_vault)?;
+        let TrailingAccounts {
+            spl_quote,
+            token_vault,
+            referral,
+        } = trailing_accounts(state_account.key(), &state, remaining)?;
+
+        let vtoken_reserve = state.vtoken_reserve_amount();
+        let vsol_reserve = state.vsol_reserve_amount();
+
+        // Price the trade based on the bonding curve
+        let (amount, sol_cost, fee) = trade(
+            &BondingCurve::from_state(&state)?,
+            &state.fees(),
+            state.remaining_allocation(),
+        )?;
+
+        // Tokens leave the virtual reserve and the collateral paid for them enters it,
+        // the fee is accrued separately
+        state.set_vtoken_reserve_amount(
+            vtoken_reserve
+                .checked_sub(amount)
+                .ok_or(BondingCurveError::InsufficientReserve)?,
+        );
+        state.set_vsol_reserve_amount(
+            vsol_reserve
+                .checked_add(sol_cost)
+                .ok_or(BondingCurveError::MathOverflow)?,
+        );
+        if state.remaining_allocation() == 0 {
+            state.set_status(CurveStatus::Complete);
+        }
+        let referral = record_referral(&mut state, fee, buyer, referral, referral_fee_share_bps)?;
 
-    if !bonding_curve_state.is_initialized() {
-        return Err(ProgramError::UninitializedAccount);
+        (amount, sol_cost, fee, referral, spl_quote, token_vault, state.bump())
+    };
+    let referral_fee = referral.map_or(0, |(_, referral_fee)| referral_fee);
+
+    match spl_quote {
+        // Transfer the quote from the buyer's quote account to the quote vault, the fee
+        // to the fee vault and the referrer's share of it to the referrer
+        Some((quote_vault, buyer_quote_account)) => {
+            Transfer {
+                from: buyer_quote_account,
+                to: quote_vault,
+                authority: buyer,
+                amount: sol_cost,
+            }
+            .invoke()?;
+            Transfer {
+                from: buyer_quote_account,
+                to: fee_vault,
+                authority: buyer,
+                amount: fee - referral_fee,
+            }
+            .invoke()?;
+            if let Some((referrer, referral_fee)) = referral {
+                Transfer {
+                    from: buyer_quote_account,
+                    to: referrer,
+                    authority: buyer,
+                    amount: referral_fee,
+                }
+                .invoke()?;
+            }
+        }
+        None => {
+            let [_system_program, ..] = remaining else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+
+            system::Transfer {
+                from: buyer,
+                to: quote_account,
+                lamports: sol_cost,
+            }
+            .invoke()?;
+            system::Transfer {
+                from: buyer,
+                to: fee_vault,
+                lamports: fee - referral_fee,
+            }
+            .invoke()?;
+            if let Some((referrer, referral_fee)) = referral {
+                system::Transfer {
+                    from: buyer,
+                    to: referrer,
+                    lamports: referral_fee,
+                }
+                .invoke()?;
+            }
+        }
     }
 
-    assert!(buyer_ata.owner() == _token_program.key());
-    assert!(state_token_account.owner() == _token_program.key());
+    // Deliver the purchased tokens to the buyer's token account, minted or out of the
+    // token vault
+    let bump = [bump];
+    let curve_seeds = pda::curve_seeds(buying_mint.key(), &bump);
+    let signer = [Signer::from(&curve_seeds)];
+    match token_vault {
+        None => MintTo {
+            mint: buying_mint,
+            token: buyer_token_account,
+            mint_authority: state_account,
+            amount,
+        }
+        .invoke_signed(&signer)?,
+        Some(token_vault) => Transfer {
+            from: token_vault,
+            to: buyer_token_account,
+            authority: state_account,
+            amount,
+        }
+        .invoke_signed(&signer)?,
+    }
 
-    assert!(buying_mint.key() == &bonding_curve_state.vtoken_mint());
-    assert!(state_mint.key() == &bonding_curve_state.vsol_mint());
+    Ok(())
+}
 
-    let amount = unsafe { *(data.as_ptr() as *const u64) };
+/// Migrates a complete curve to its [`MigrationTarget`]. A curve migrates once, and
+/// anyone can migrate it: every account the liquidity or the fee ends up in is derived
+/// by the program or checked against the config and the caller, and the DEX checks
+/// the accounts of its own pool.
+///
+/// The target receives the tokens the curve did not sell and the collateral it
+/// collected, less the migration fee the curve was created with, see [`initialize`].
+/// Out of the fee, the caller is refunded what the target costs, see
+/// [`MigrationTarget::fee`], and paid the migration bounty, so migrating never costs
+/// it more than the accounts it creates; the rest goes to the config fee recipient.
+///
+/// The in-house target seeds the curve's constant product pool, see [`amm`]. External
+/// targets are handed the liquidity through the curve's migration authority, which
+/// creates the pool on the DEX, see [`migration`]. The liquidity minted for the seed
+/// is then burned or moved to the curve's LP lock, as the curve's [`LpPolicy`] says.
+/// Unsold tokens the curve's [`UnsoldTokenPolicy`] keeps out of the pool are burned or
+/// moved to the curve's vesting account in the same instruction, and a curve that
+/// mints on buy then gives up its mint authority.
+///
+/// Accounts: the curve state, its quote vault, the quote mint (or the native quote
+/// vault), the token mint, the payer of the new accounts (signer), the token and
+/// system programs, the config, the account the migration fee is paid to, the account
+/// the caller is refunded and paid the bounty at, then the accounts of the target.
+/// Curves that lock their liquidity then pass their LP lock, curves that vest their
+/// unsold tokens their vesting account, and pre-minted curves pass their token vault
+/// last.
+///   - In-house: the pool, its token vault, its quote vault, its LP mint and its LP
+///     vault, see [`pda`].
+///   - External: the migration authority, its token and quote accounts, the DEX
+///     program and the accounts of its pool-creation instruction, in the order the
+///     DEX takes them.
+///
+/// Instruction data, in-house: the bumps of the pool, its token vault, its quote
+/// vault, its LP mint and its LP vault. External: the bumps of the migration
+/// authority, its token account and its quote account. Curves that lock their
+/// liquidity append the bump of their LP lock, then curves that vest their unsold
+/// tokens the bump of their vesting account.
+pub fn migrate(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [state_account, state_token_account, quote_account, vtoken_mint, payer, _token_program, _system_program, config_account, fee_recipient, bounty_account, remaining @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
 
-    // Calculate the price for the desired token amount based on the bonding curve
-    let total_sol_cost = calculate_cost(
-        bonding_curve_state.vtoken_reserve_amount(),
-        bonding_curve_state.vsol_reserve_amount(),
-        amount,
+    if !payer.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let fee_recipient_key = *load_active_config(config_account)?.fee_recipient();
+
+    let (migration, target, target_accounts, data, protocol_fee, caller_fee) = {
+        let mut state = BondingCurveState::load_mut(state_account)?;
+        check_curve(state_account, &state)?;
+        check_not_paused(&state)?;
+        match state.status()? {
+            CurveStatus::Trading => return Err(BondingCurveError::NotMigratable.into()),
+            CurveStatus::Complete => {}
+            CurveStatus::Migrated => return Err(BondingCurveError::AlreadyMigrated.into()),
+        }
+
+        if vtoken_mint.key() != state.vtoken_mint() {
+            return Err(BondingCurveError::InvalidMint.into());
+        }
+        let quote_mode = check_quote_account(state_account.key(), &state, quote_account)?;
+        pda::check(
+            state_token_account,
+            pda::vault_address(state_account.key(), state.vault_bump())?,
+        )?;
+        let target = state.migration_target()?;
+        // The caller pays the target out of its own wallet, and the curve refunds it
+        // along with the bounty
+        let migration_fee = state.migration_fee();
+        let caller_fee = target
+            .fee()
+            .checked_add(state.migration_bounty())
+            .ok_or(BondingCurveError::MathOverflow)?;
+        let protocol_fee = migration_fee
+            .checked_sub(caller_fee)
+            .ok_or(BondingCurveError::MathOverflow)?;
+        let (target_accounts, remaining) = remaining
+            .split_at_checked(target.accounts_len())
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let unsold_token_policy = state.unsold_token_policy()?;
+        let (vesting_bump, data) = match unsold_token_policy {
+            UnsoldTokenPolicy::SeedPool | UnsoldTokenPolicy::Burn => (None, data),
+            UnsoldTokenPolicy::Vest => {
+                let (vesting_bump, data) =
+                    data.split_last().ok_or(ProgramError::InvalidInstructionData)?;
+                (Some(*vesting_bump), data)
+            }
+        };
+        let (lp_lock, target_data, remaining) = match state.lp_policy()? {
+            LpPolicy::Burn => (None, data, remaining),
+            LpPolicy::Lock => {
+                let [lp_lock, remaining @ ..] = remaining else {
+                    return Err(ProgramError::NotEnoughAccountKeys);
+                };
+                let (lp_lock_bump, data) =
+                    data.split_last().ok_or(ProgramError::InvalidInstructionData)?;
+                pda::check(lp_lock, pda::lp_lock_address(state_account.key(), *lp_lock_bump)?)?;
+                state.set_lp_lock_bump(*lp_lock_bump);
+                (Some(lp_lock), data, remaining)
+            }
+        };
+        let (vesting, remaining) = match vesting_bump {
+            None => (None, remaining),
+            Some(vesting_bump) => {
+                let [vesting, remaining @ ..] = remaining else {
+                    return Err(ProgramError::NotEnoughAccountKeys);
+                };
+                pda::check(vesting, pda::vesting_address(state_account.key(), vesting_bump)?)?;
+                state.set_vesting_bump(vesting_bump);
+                (Some(vesting), remaining)
+            }
+        };
+        let token_vault = match state.supply_mode()? {
+            SupplyMode::MintOnBuy => None,
+            SupplyMode::PreMinted => {
+                let [token_vault, ..] = remaining else {
+                    return Err(ProgramError::NotEnoughAccountKeys);
+                };
+                let address =
+                    pda::token_vault_address(state_account.key(), state.token_vault_bump())?;
+                pda::check(token_vault, address)?;
+                Some(token_vault)
+            }
+        };
+
+        let collateral = state
+            .vsol_reserve_amount()
+            .checked_sub(state.initial_vsol_reserve())
+            .ok_or(BondingCurveError::InsufficientReserve)?;
+        if protocol_fee > 0 {
+            check_lamports_account(fee_recipient, &fee_recipient_key, &state)?;
+        }
+        if caller_fee > 0 {
+            check_lamports_account(bounty_account, payer.key(), &state)?;
+        }
+        let quote = collateral
+            .checked_sub(migration_fee)
+            .ok_or(BondingCurveError::InsufficientCollateral)?;
+        msg!(
+            "Collateral of {}: {} lamports migration fee ({} to the caller), {} to the target.",
+            collateral,
+            migration_fee,
+            caller_fee,
+            quote
+        );
+        let unsold = state
+            .total_supply()
+            .checked_sub(state.tokens_sold())
+            .ok_or(BondingCurveError::InsufficientReserve)?;
+        let tokens = match unsold_token_policy {
+            UnsoldTokenPolicy::SeedPool => unsold,
+            UnsoldTokenPolicy::Burn | UnsoldTokenPolicy::Vest => {
+                // Opening the pool at the final price of the curve
+                let price = BondingCurve::from_state(&state)?.spot_price()?;
+                let at_price = math::mul_div(
+                    quote as u128,
+                    curve::PRICE_SCALE,
+                    price,
+                    math::Rounding::Down,
+                )?;
+                at_price.min(unsold as u128) as u64
+            }
+        };
+        let surplus = unsold - tokens;
+        msg!(
+            "Unsold tokens: {}, {} to the target, {} burned or vested.",
+            unsold,
+            tokens,
+            surplus
+        );
+        if vesting.is_some() {
+            state.set_vesting_amount(surplus);
+            state.set_vesting_start(sysvars::clock()?.unix_timestamp);
+        }
+
+        state.set_status(CurveStatus::Migrated);
+        let migration = Migration {
+            state_account,
+            state_token_account,
+            quote_account,
+            vtoken_mint,
+            payer,
+            token_vault,
+            lp_lock,
+            vesting,
+            quote_mode,
+            bump: state.bump(),
+            lp_lock_bump: state.lp_lock_bump(),
+            vesting_bump: state.vesting_bump(),
+            tokens,
+            surplus,
+            quote,
+        };
+        (
+            migration,
+            target,
+            target_accounts,
+            target_data,
+            protocol_fee,
+            caller_fee,
+        )
+    };
+
+    if protocol_fee > 0 {
+        migration.transfer_quote(fee_recipient, protocol_fee)?;
+    }
+    if caller_fee > 0 {
+        migration.transfer_quote(bounty_account, caller_fee)?;
+    }
+    migration.dispose_surplus()?;
+
+    match target {
+        MigrationTarget::InHouse => migrate_in_house(&migration, target_accounts, data),
+        MigrationTarget::RaydiumCpmm => {
+            migrate_externally(&migration, target, target_accounts, data, migration::raydium_cpmm)
+        }
+        MigrationTarget::MeteoraDamm => {
+            migrate_externally(&migration, target, target_accounts, data, migration::meteora_damm)
+        }
+    }?;
+    migration.revoke_mint_authority()
+}
+
+/// A complete curve handing its liquidity over to its migration target.
+struct Migration<'a> {
+    state_account: &'a AccountInfo,
+    state_token_account: &'a AccountInfo,
+    quote_account: &'a AccountInfo,
+    vtoken_mint: &'a AccountInfo,
+    payer: &'a AccountInfo,
+    token_vault: Option<&'a AccountInfo>,
+    /// The LP lock of a curve that locks its liquidity; the liquidity of the others is
+    /// burned.
+    lp_lock: Option<&'a AccountInfo>,
+    /// The vesting account of a curve that vests its surplus tokens; the surplus of the
+    /// others is burned.
+    vesting: Option<&'a AccountInfo>,
+    quote_mode: QuoteMode,
+    bump: u8,
+    lp_lock_bump: u8,
+    vesting_bump: u8,
+    /// Unsold tokens that go to the target.
+    tokens: u64,
+    /// Unsold tokens kept out of the pool, see [`UnsoldTokenPolicy`].
+    surplus: u64,
+    /// Collateral the curve collected, less the migration fee.
+    quote: u64,
+}
+
+impl Migration<'_> {
+    /// Moves `amount` of the collateral to `to`, signed by the curve.
+    fn transfer_quote(&self, to: &AccountInfo, amount: u64) -> ProgramResult {
+        match self.quote_mode {
+            QuoteMode::SplToken => {
+                let bump = [self.bump];
+                let curve_seeds = pda::curve_seeds(self.vtoken_mint.key(), &bump);
+                Transfer {
+                    from: self.state_token_account,
+                    to,
+                    authority: self.state_account,
+                    amount,
+                }
+                .invoke_signed(&[Signer::from(&curve_seeds)])
+            }
+            QuoteMode::Native => withdraw_lamports(self.quote_account, to, amount),
+        }
+    }
+
+    /// Moves the tokens that go to the target to `token_account` and the collateral
+    /// to `quote_account`, signed by the curve.
+    fn hand_over(&self, token_account: &AccountInfo, quote_account: &AccountInfo) -> ProgramResult {
+        self.transfer_quote(quote_account, self.quote)?;
+        self.transfer_tokens(token_account, self.tokens)
+    }
+
+    /// Mints `amount` of tokens to `to`, or moves them out of the token vault of a
+    /// pre-minted curve, signed by the curve.
+    fn transfer_tokens(&self, to: &AccountInfo, amount: u64) -> ProgramResult {
+        let bump = [self.bump];
+        let curve_seeds = pda::curve_seeds(self.vtoken_mint.key(), &bump);
+        let signer = [Signer::from(&curve_seeds)];
+        match self.token_vault {
+            None => MintTo {
+                mint: self.vtoken_mint,
+                token: to,
+                mint_authority: self.state_account,
+                amount,
+            }
+            .invoke_signed(&signer),
+            Some(token_vault) => Transfer {
+                from: token_vault,
+                to,
+                authority: self.state_account,
+                amount,
+            }
+            .invoke_signed(&signer),
+        }
+    }
+
+    /// Moves the surplus tokens to the vesting account, or burns them. Curves that mint
+    /// on buy burn theirs by never minting them, see [`Self::revoke_mint_authority`].
+    fn dispose_surplus(&self) -> ProgramResult {
+        match (self.vesting, self.token_vault) {
+            (Some(vesting), _) => {
+                create_token_account(
+                    self.payer,
+                    vesting,
+                    pda::VESTING_SEED,
+                    self.state_account.key(),
+                    self.vesting_bump,
+                    self.vtoken_mint,
+                )?;
+                self.transfer_tokens(vesting, self.surplus)
+            }
+            (None, Some(token_vault)) if self.surplus > 0 => {
+                let bump = [self.bump];
+                let curve_seeds = pda::curve_seeds(self.vtoken_mint.key(), &bump);
+                Burn {
+                    token: token_vault,
+                    mint: self.vtoken_mint,
+                    authority: self.state_account,
+                    amount: self.surplus,
+                }
+                .invoke_signed(&[Signer::from(&curve_seeds)])
+            }
+            (None, _) => Ok(()),
+        }
+    }
+
+    /// Gives up the mint authority of a curve that mints on buy once the migration
+    /// minted its last tokens, so the tokens it never minted are gone for good and the
+    /// supply cannot grow after it graduates. Pre-minted curves gave it up at
+    /// `Initialize`.
+    fn revoke_mint_authority(&self) -> ProgramResult {
+        if self.token_vault.is_some() {
+            return Ok(());
+        }
+        let bump = [self.bump];
+        let curve_seeds = pda::curve_seeds(self.vtoken_mint.key(), &bump);
+        SetAuthority {
+            account: self.vtoken_mint,
+            authority: self.state_account,
+            authority_type: AuthorityType::MintTokens,
+            new_authority: None,
+        }
+        .invoke_signed(&[Signer::from(&curve_seeds)])
+    }
+
+    /// Burns the `amount` of seed liquidity in `lp_account`, held by `owner`, or moves
+    /// it to the LP lock, which the curve owns.
+    fn dispose_lp(
+        &self,
+        lp_mint: &AccountInfo,
+        lp_account: &AccountInfo,
+        owner: &AccountInfo,
+        owner_signer: &[Signer],
+        amount: u64,
+    ) -> ProgramResult {
+        match self.lp_lock {
+            None => Burn {
+                token: lp_account,
+                mint: lp_mint,
+                authority: owner,
+                amount,
+            }
+            .invoke_signed(owner_signer),
+            Some(lp_lock) => {
+                create_token_account(
+                    self.payer,
+                    lp_lock,
+                    pda::LP_LOCK_SEED,
+                    self.state_account.key(),
+                    self.lp_lock_bump,
+                    lp_mint,
+                )?;
+                Transfer {
+                    from: lp_account,
+                    to: lp_lock,
+                    authority: owner,
+                    amount,
+                }
+                .invoke_signed(owner_signer)
+            }
+        }
+    }
+}
+
+/// Checks that `account` is where a curve pays lamports of its migration fee to
+/// `recipient`: the recipient itself for native curves, or a wrapped SOL token account
+/// it owns for curves quoted in wrapped SOL, the only other curves that pay the fee.
+fn check_lamports_account(
+    account: &AccountInfo,
+    recipient: &Pubkey,
+    state: &BondingCurveState,
+) -> ProgramResult {
+    let valid = match state.quote_mode()? {
+        QuoteMode::Native => account.key() == recipient,
+        QuoteMode::SplToken => {
+            let token_account = TokenAccount::from_account_info(account)?;
+            token_account.owner() == recipient && token_account.mint() == &NATIVE_MINT
+        }
+    };
+
+    if !valid {
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok(())
+}
+
+/// Seeds the curve's constant product pool, see [`migrate`].
+fn migrate_in_house(migration: &Migration, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [pool_account, pool_token_vault, pool_quote_vault, lp_mint, lp_vault] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    let [pool_bump, token_vault_bump, quote_vault_bump, lp_mint_bump, lp_vault_bump] = *data else {
+        return Err(ProgramError::InvalidInstructionData);
+    };
+
+    let Migration {
+        state_account,
+        quote_account,
+        vtoken_mint,
+        payer,
+        quote_mode,
+        ..
+    } = *migration;
+    let state_key = state_account.key();
+    let pool_key = pool_account.key();
+    pda::check(pool_account, pda::pool_address(state_key, pool_bump)?)?;
+    pda::check(pool_token_vault, pda::pool_token_vault_address(pool_key, token_vault_bump)?)?;
+    pda::check(pool_quote_vault, pda::pool_quote_vault_address(pool_key, quote_vault_bump)?)?;
+    pda::check(lp_mint, pda::lp_mint_address(pool_key, lp_mint_bump)?)?;
+    pda::check(lp_vault, pda::lp_vault_address(pool_key, lp_vault_bump)?)?;
+
+    let pool = Pool::seed(migration.tokens, migration.quote)?;
+    msg!(
+        "Seeding pool with {} tokens, {} quote and {} liquidity.",
+        pool.token_reserve,
+        pool.quote_reserve,
+        pool.lp_supply
     );
 
-    // Update the state with the new reserves
-    if state_account.data_len() != 0 {
-        unsafe {
-            let data_ptr = state_account.borrow_mut_data_unchecked().as_mut_ptr();
-            *(data_ptr.add(32) as *mut [u8; 8]) = (bonding_curve_state.vtoken_reserve_amount() + amount).to_le_bytes();
-            *(data_ptr.add(40) as *mut [u8; 8]) = (bonding_curve_state.vsol_reserve_amount() + total_sol_cost).to_le_bytes();
+    let pool_bump = [pool_bump];
+    let pool_seeds = pda::pool_seeds(state_key, &pool_bump);
+    CreateAccount {
+        from: payer,
+        to: pool_account,
+        lamports: sysvars::rent()?.minimum_balance(PoolState::LEN),
+        space: PoolState::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&pool_seeds)])?;
+
+    {
+        let mut pool_state = PoolState::init(pool_account)?;
+        pool_state.set_bump(pool_bump[0]);
+        pool_state.set_curve(state_key);
+        pool_state.set_token_mint(vtoken_mint.key());
+        if quote_mode == QuoteMode::SplToken {
+            pool_state.set_quote_mint(quote_account.key());
         }
-    } else {
-        return Err(ProgramError::UninitializedAccount);
+        pool_state.set_quote_mode(quote_mode);
+        pool_state.set_token_vault_bump(token_vault_bump);
+        pool_state.set_quote_vault_bump(quote_vault_bump);
+        pool_state.set_lp_mint_bump(lp_mint_bump);
+        pool_state.set_lp_vault_bump(lp_vault_bump);
+        pool_state.set_pool(&pool);
     }
 
-    // Transfer SOL from the buyer's account to the state token account
-    Transfer {
-        from: buyer_ata,
-        to: state_token_account,
-        authority: buyer,
-        amount: total_sol_cost,
+    // The pool owns its vaults and is the only authority of its LP mint
+    create_token_account(
+        payer,
+        pool_token_vault,
+        pda::POOL_TOKEN_VAULT_SEED,
+        pool_key,
+        token_vault_bump,
+        vtoken_mint,
+    )?;
+    match quote_mode {
+        QuoteMode::SplToken => create_token_account(
+            payer,
+            pool_quote_vault,
+            pda::POOL_QUOTE_VAULT_SEED,
+            pool_key,
+            quote_vault_bump,
+            quote_account,
+        )?,
+        QuoteMode::Native => {
+            let quote_vault_bump = [quote_vault_bump];
+            let seeds = [
+                Seed::from(pda::POOL_QUOTE_VAULT_SEED),
+                Seed::from(pool_key.as_ref()),
+                Seed::from(&quote_vault_bump),
+            ];
+            CreateAccount {
+                from: payer,
+                to: pool_quote_vault,
+                lamports: sysvars::rent()?.minimum_balance(0),
+                space: 0,
+                owner: &ID,
+            }
+            .invoke_signed(&[Signer::from(&seeds)])?;
+        }
     }
-    .invoke()?;
 
+    let lp_mint_bump = [lp_mint_bump];
+    let seeds = [
+        Seed::from(pda::LP_MINT_SEED),
+        Seed::from(pool_key.as_ref()),
+        Seed::from(&lp_mint_bump),
+    ];
+    CreateAccount {
+        from: payer,
+        to: lp_mint,
+        lamports: sysvars::rent()?.minimum_balance(Mint::LEN),
+        space: Mint::LEN as u64,
+        owner: &pinocchio_token::ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+    InitilizeMint2 {
+        mint: lp_mint,
+        decimals: TOKEN_DECIMALS,
+        mint_authority: pool_key,
+        freeze_authority: None,
+    }
+    .invoke()?;
+    create_token_account(payer, lp_vault, pda::LP_VAULT_SEED, pool_key, lp_vault_bump, lp_mint)?;
 
-    let binding = bump.to_le_bytes();
-    let seeds = [Seed::from(state_account.key().as_ref()), Seed::from(&binding)];
-    let signer = [Signer::from(&seeds)];
+    // Move the collateral and the unsold tokens into the pool
+    migration.hand_over(pool_token_vault, pool_quote_vault)?;
 
-    // Mint the purchased tokens to the buyer’s associated token account
+    // The seed liquidity only passes through the LP vault. Burned, it stays counted in
+    // the pool supply, so nobody can ever withdraw its share of the reserves.
+    let pool_signer = [Signer::from(&pool_seeds)];
     MintTo {
-        mint: buying_mint,
-        token: buyer_ata,
-        mint_authority: state_account, // Must be the program's authority
-        amount,
+        mint: lp_mint,
+        token: lp_vault,
+        mint_authority: pool_account,
+        amount: pool.lp_supply,
     }
-    .invoke_signed(&signer)?;
+    .invoke_signed(&pool_signer)?;
+    migration.dispose_lp(lp_mint, lp_vault, pool_account, &pool_signer, pool.lp_supply)
+}
 
-    Ok(())
+/// Hands the liquidity to the curve's migration authority, which creates the pool
+/// built by `create_pool` on an external DEX, see [`migrate`].
+fn migrate_externally<'a, const ACCOUNTS: usize, const DATA: usize>(
+    migration: &Migration,
+    target: MigrationTarget,
+    accounts: &'a [AccountInfo],
+    data: &[u8],
+    create_pool: impl FnOnce(
+        &'a [AccountInfo],
+        &Liquidity,
+    ) -> Result<PoolCreation<'a, ACCOUNTS, DATA>, ProgramError>,
+) -> ProgramResult {
+    let [authority, token_account, quote_account, dex_accounts @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    let [authority_bump, token_account_bump, quote_account_bump] = *data else {
+        return Err(ProgramError::InvalidInstructionData);
+    };
+
+    let state_key = migration.state_account.key();
+    let authority_key = authority.key();
+    pda::check(authority, pda::migration_authority_address(state_key, authority_bump)?)?;
+    pda::check(token_account, pda::migration_token_address(authority_key, token_account_bump)?)?;
+    pda::check(quote_account, pda::migration_quote_address(authority_key, quote_account_bump)?)?;
+
+    let liquidity = Liquidity {
+        authority: authority_key,
+        token_mint: migration.vtoken_mint.key(),
+        // External targets only take curves quoted in an SPL token, see `InitializeArgs`
+        quote_mint: migration.quote_account.key(),
+        token_account: token_account.key(),
+        quote_account: quote_account.key(),
+        token_amount: migration.tokens,
+        quote_amount: migration.quote,
+    };
+    let pool_creation = create_pool(dex_accounts, &liquidity)?;
+
+    msg!(
+        "Creating pool with {} tokens and {} quote, paying {} lamports to the DEX.",
+        migration.tokens,
+        migration.quote,
+        target.fee()
+    );
+
+    // The authority pays the DEX for the pool and holds the liquidity until it does
+    system::Transfer {
+        from: migration.payer,
+        to: authority,
+        lamports: target.fee(),
+    }
+    .invoke()?;
+    create_token_account(
+        migration.payer,
+        token_account,
+        pda::MIGRATION_TOKEN_SEED,
+        authority_key,
+        token_account_bump,
+        migration.vtoken_mint,
+    )?;
+    create_token_account(
+        migration.payer,
+        quote_account,
+        pda::MIGRATION_QUOTE_SEED,
+        authority_key,
+        quote_account_bump,
+        migration.quote_account,
+    )?;
+    migration.hand_over(token_account, quote_account)?;
+
+    let authority_bump = [authority_bump];
+    let authority_seeds = pda::migration_authority_seeds(state_key, &authority_bump);
+    let authority_signer = [Signer::from(&authority_seeds)];
+    pool_creation.invoke_signed(&authority_signer)?;
+
+    // The DEX mints the seed liquidity to the authority as it creates the pool
+    let (lp_mint, lp_account) = (pool_creation.lp_mint(), pool_creation.lp_account());
+    let lp_amount = {
+        let lp_account = TokenAccount::from_account_info(lp_account)?;
+        if lp_account.owner() != authority_key || lp_account.mint() != lp_mint.key() {
+            return Err(BondingCurveError::InvalidMigrationAccount.into());
+        }
+        lp_account.amount()
+    };
+    migration.dispose_lp(lp_mint, lp_account, authority, &authority_signer, lp_amount)
 }
 
-pub fn migrate(accounts: &[AccountInfo]) -> ProgramResult {
-    let [state_account, raydium_account, _token_program] = accounts
+/// Swaps an exact amount in on a graduated pool.
+///
+/// Accounts: the pool, the user (signer), the user's token account, the user's quote
+/// account, the pool token vault, the pool quote vault, the token program and the
+/// system program. The quote account of a native pool is where quote is paid to; quote
+/// paid in comes from the user.
+///
+/// Instruction data: the [`SwapDirection`], then the amount in and the least amount the
+/// user accepts out, both as little-endian `u64`s.
+pub fn swap(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [pool_account, user, user_token_account, user_quote_account, pool_token_vault, pool_quote_vault, _token_program, _system_program, ..] =
+        accounts
     else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
-    let bonding_curve_state = BondingCurveState::from_account_info(state_account);
+    if !user.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
 
-    if !bonding_curve_state.is_initialized() {
-        return Err(ProgramError::UninitializedAccount);
+    let [direction, data @ ..] = data else {
+        return Err(ProgramError::InvalidInstructionData);
+    };
+    let direction = SwapDirection::try_from(*direction)?;
+    let amount_in = read_u64(data, 0)?;
+    let min_amount_out = read_u64(data, 8)?;
+
+    let (amount_out, quote_mode, curve, bump) = {
+        let mut pool_state = PoolState::load_mut(pool_account)?;
+        check_pool_vaults(pool_account.key(), &pool_state, pool_token_vault, pool_quote_vault)?;
+
+        let mut pool = pool_state.pool();
+        let amount_out = pool.swap_out(direction, amount_in)?;
+        if amount_out < min_amount_out {
+            return Err(BondingCurveError::SlippageExceeded.into());
+        }
+        pool.swap(direction, amount_in, amount_out)?;
+        pool_state.set_pool(&pool);
+
+        (amount_out, pool_state.quote_mode()?, *pool_state.curve(), pool_state.bump())
+    };
+
+    let bump = [bump];
+    let pool_seeds = pda::pool_seeds(&curve, &bump);
+    let pool_signer = PoolSigner {
+        pool: pool_account,
+        seeds: &pool_seeds,
+        quote_mode,
+    };
+    match direction {
+        SwapDirection::QuoteToToken => {
+            pool_signer.deposit_quote(user, user_quote_account, pool_quote_vault, amount_in)?;
+            pool_signer.pay_tokens(pool_token_vault, user_token_account, amount_out)?;
+        }
+        SwapDirection::TokenToQuote => {
+            Transfer {
+                from: user_token_account,
+                to: pool_token_vault,
+                authority: user,
+                amount: amount_in,
+            }
+            .invoke()?;
+            pool_signer.pay_quote(pool_quote_vault, user_quote_account, amount_out)?;
+        }
     }
 
-    let allocation_at_migration = bonding_curve_state.allocation_at_migration();
+    Ok(())
+}
 
-    if bonding_curve_state.vtoken_reserve_amount() >= allocation_at_migration {
-        let collateral_collected = bonding_curve_state.vsol_reserve_amount() - INITIAL_VSOL;
-        let fees = MIGRATION_FEE_RAYDIUM;
-        let sol_to_transfer = collateral_collected - fees;
-        msg!("Migrating {} SOL to Raydium.", sol_to_transfer);
+/// Deposits tokens and quote into a graduated pool at its current ratio, for liquidity
+/// tokens.
+///
+/// Accounts: the pool, the user (signer), the user's token account, the user's quote
+/// account, the user's LP token account, the pool token vault, the pool quote vault,
+/// the LP mint, the token program and the system program.
+///
+/// Instruction data: the most tokens and quote to deposit, and the least liquidity the
+/// user accepts for them, all as little-endian `u64`s.
+pub fn add_liquidity(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [pool_account, user, user_token_account, user_quote_account, user_lp_account, pool_token_vault, pool_quote_vault, lp_mint, _token_program, _system_program, ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
 
-        // Transfer the collateral to Raydium
-        Transfer {
-            from: state_account,
-            to: raydium_account,
-            authority: state_account,
-            amount: sol_to_transfer,
-        }.invoke()?;
+    if !user.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
     }
 
+    let max_token_in = read_u64(data, 0)?;
+    let max_quote_in = read_u64(data, 8)?;
+    let min_lp_out = read_u64(data, 16)?;
+
+    let (token_in, quote_in, lp_out, quote_mode, curve, bump) = {
+        let mut pool_state = PoolState::load_mut(pool_account)?;
+        check_pool_vaults(pool_account.key(), &pool_state, pool_token_vault, pool_quote_vault)?;
+        pda::check(lp_mint, pda::lp_mint_address(pool_account.key(), pool_state.lp_mint_bump())?)?;
+
+        let mut pool = pool_state.pool();
+        let (token_in, quote_in, lp_out) = pool.deposit(max_token_in, max_quote_in)?;
+        if lp_out == 0 || lp_out < min_lp_out {
+            return Err(BondingCurveError::SlippageExceeded.into());
+        }
+        pool.add_liquidity(token_in, quote_in, lp_out)?;
+        pool_state.set_pool(&pool);
+
+        (
+            token_in,
+            quote_in,
+            lp_out,
+            pool_state.quote_mode()?,
+            *pool_state.curve(),
+            pool_state.bump(),
+        )
+    };
+
+    let bump = [bump];
+    let pool_seeds = pda::pool_seeds(&curve, &bump);
+    let pool_signer = PoolSigner {
+        pool: pool_account,
+        seeds: &pool_seeds,
+        quote_mode,
+    };
+    Transfer {
+        from: user_token_account,
+        to: pool_token_vault,
+        authority: user,
+        amount: token_in,
+    }
+    .invoke()?;
+    pool_signer.deposit_quote(user, user_quote_account, pool_quote_vault, quote_in)?;
+    MintTo {
+        mint: lp_mint,
+        token: user_lp_account,
+        mint_authority: pool_account,
+        amount: lp_out,
+    }
+    .invoke_signed(&[Signer::from(&pool_seeds)])?;
+
     Ok(())
+}
 
+/// Burns liquidity tokens of a graduated pool for their share of its reserves.
+///
+/// Accounts: as for [`add_liquidity`].
+///
+/// Instruction data: the liquidity to burn, and the least tokens and quote the user
+/// accepts for it, all as little-endian `u64`s.
+pub fn remove_liquidity(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [pool_account, user, user_token_account, user_quote_account, user_lp_account, pool_token_vault, pool_quote_vault, lp_mint, _token_program, _system_program, ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !user.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let lp_in = read_u64(data, 0)?;
+    let min_token_out = read_u64(data, 8)?;
+    let min_quote_out = read_u64(data, 16)?;
+
+    let (token_out, quote_out, quote_mode, curve, bump) = {
+        let mut pool_state = PoolState::load_mut(pool_account)?;
+        check_pool_vaults(pool_account.key(), &pool_state, pool_token_vault, pool_quote_vault)?;
+        pda::check(lp_mint, pda::lp_mint_address(pool_account.key(), pool_state.lp_mint_bump())?)?;
+
+        let mut pool = pool_state.pool();
+        let (token_out, quote_out) = pool.withdrawal(lp_in)?;
+        if token_out < min_token_out || quote_out < min_quote_out {
+            return Err(BondingCurveError::SlippageExceeded.into());
+        }
+        pool.remove_liquidity(token_out, quote_out, lp_in)?;
+        pool_state.set_pool(&pool);
+
+        (
+            token_out,
+            quote_out,
+            pool_state.quote_mode()?,
+            *pool_state.curve(),
+            pool_state.bump(),
+        )
+    };
+
+    Burn {
+        token: user_lp_account,
+        mint: lp_mint,
+        authority: user,
+        amount: lp_in,
+    }
+    .invoke()?;
+
+    let bump = [bump];
+    let pool_seeds = pda::pool_seeds(&curve, &bump);
+    let pool_signer = PoolSigner {
+        pool: pool_account,
+        seeds: &pool_seeds,
+        quote_mode,
+    };
+    pool_signer.pay_tokens(pool_token_vault, user_token_account, token_out)?;
+    pool_signer.pay_quote(pool_quote_vault, user_quote_account, quote_out)?;
+
+    Ok(())
 }
 
+/// Sells an exact amount of tokens.
+///
+/// Instruction data: the token amount and the least quote the seller accepts for it
+/// after the fee, both as little-endian `u64`s.
 pub fn sell(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
-    let [state_account, seller, seller_ata, vtoken_mint, state_token_account, vsol_mint, _token_program] =
+    let amount = read_u64(instruction_data, 0)?;
+    let min_quote_out = read_u64(instruction_data, 8)?;
+
+    process_sell(accounts, |curve, fees| {
+        let refund = curve.refund_for_sell(amount)?;
+        let fee = fees.fee_on(refund)?;
+        if refund - fee < min_quote_out {
+            return Err(BondingCurveError::SlippageExceeded.into());
+        }
+        Ok((amount, refund, fee))
+    })
+}
+
+/// Sells tokens for an exact amount of quote, received after the fee.
+///
+/// Instruction data: the quote amount and the most tokens the seller is willing to
+/// give for it, both as little-endian `u64`s.
+pub fn sell_exact_out(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let quote_out = read_u64(instruction_data, 0)?;
+    let max_tokens_in = read_u64(instruction_data, 8)?;
+
+    process_sell(accounts, |curve, fees| {
+        let (refund, fee) = fees.gross_for_quote_out(quote_out)?;
+        let amount = curve.amount_for_quote_out(refund)?;
+        if amount > max_tokens_in {
+            return Err(BondingCurveError::SlippageExceeded.into());
+        }
+        Ok((amount, refund, fee))
+    })
+}
+
+/// Shared by the sell instructions: `trade` prices the trade on the curve and returns
+/// the token amount sold, the quote the curve refunds for it and the fee kept out of
+/// that refund.
+///
+/// Takes the accounts of [`process_buy`], the seller in place of the buyer.
+fn process_sell(
+    accounts: &[AccountInfo],
+    trade: impl FnOnce(&BondingCurve, &Fees) -> Result<(u64, u64, u64), ProgramError>,
+) -> ProgramResult {
+    let [state_account, seller, seller_token_account, vtoken_mint, quote_account, _token_program, config_account, fee_vault, remaining @ ..] =
         accounts
     else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
-    let bonding_curve_state = BondingCurveState::from_account_info(state_account);
+    if !seller.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let referral_fee_share_bps = load_active_config(config_account)?.referral_fee_share_bps();
+
+    let (amount, payout, fee, referral, spl_quote, token_vault, bump) = {
+        let mut state = BondingCurveState::load_mut(state_account)?;
+        check_curve(state_account, &state)?;
+        check_not_paused(&state)?;
+        check_trading(&state)?;
+
+        if vtoken_mint.key() != state.vtoken_mint() {
+            return Err(BondingCurveError::InvalidMint.into());
+        }
+        check_token_account(seller_token_account, state.vtoken_mint())?;
+        check_quote_account(state_account.key(), &state, quote_account)?;
+        check_fee_vault(state_account.key(), &state, fee_vault)?;
+        let TrailingAccounts {
+            spl_quote,
+            token_vault,
+            referral,
+        } = trailing_accounts(state_account.key(), &state, remaining)?;
+
+        let vtoken_reserve = state.vtoken_reserve_amount();
+        let vsol_reserve = state.vsol_reserve_amount();
+
+        // Price the trade based on the bonding curve
+        let (amount, refund, fee) = trade(&BondingCurve::from_state(&state)?, &state.fees())?;
+
+        // Sold tokens return to the virtual reserve and the refund leaves it, the fee
+        // is kept out of the refund and accrued separately
+        state.set_vtoken_reserve_amount(
+            vtoken_reserve
+                .checked_add(amount)
+                .ok_or(BondingCurveError::MathOverflow)?,
+        );
+        state.set_vsol_reserve_amount(
+            vsol_reserve
+                .checked_sub(refund)
+                .ok_or(BondingCurveError::InsufficientReserve)?,
+        );
+        let referral = record_referral(&mut state, fee, seller, referral, referral_fee_share_bps)?;
+
+        (amount, refund - fee, fee, referral, spl_quote, token_vault, state.bump())
+    };
+    let referral_fee = referral.map_or(0, |(_, referral_fee)| referral_fee);
+
+    // Burn tokens from the seller's account (reducing token supply), or return them to
+    // the token vault
+    match token_vault {
+        None => Burn {
+            token: seller_token_account,
+            mint: vtoken_mint,
+            authority: seller,
+            amount,
+        }
+        .invoke()?,
+        Some(token_vault) => Transfer {
+            from: seller_token_account,
+            to: token_vault,
+            authority: seller,
+            amount,
+        }
+        .invoke()?,
+    }
+
+    // Refund the quote to the seller, move the fee to the fee vault and pay the
+    // referrer its share of it
+    match spl_quote {
+        Some((quote_vault, seller_quote_account)) => {
+            let bump = [bump];
+            let seeds = pda::curve_seeds(vtoken_mint.key(), &bump);
+            let signer = [Signer::from(&seeds)];
+
+            Transfer {
+                from: quote_vault,
+                to: seller_quote_account,
+                authority: state_account,
+                amount: payout,
+            }
+            .invoke_signed(&signer)?;
+            Transfer {
+                from: quote_vault,
+                to: fee_vault,
+                authority: state_account,
+                amount: fee - referral_fee,
+            }
+            .invoke_signed(&signer)?;
+            if let Some((referrer, referral_fee)) = referral {
+                Transfer {
+                    from: quote_vault,
+                    to: referrer,
+                    authority: state_account,
+                    amount: referral_fee,
+                }
+                .invoke_signed(&signer)?;
+            }
+        }
+        None => {
+            withdraw_lamports(quote_account, seller, payout)?;
+            withdraw_lamports(quote_account, fee_vault, fee - referral_fee)?;
+            if let Some((referrer, referral_fee)) = referral {
+                withdraw_lamports(quote_account, referrer, referral_fee)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads the global config, checking it is the program's config PDA. The stored bump
+/// is the canonical one, the only bump [`initialize_config`] accepts.
+fn load_config(config_account: &AccountInfo) -> Result<Ref<'_, GlobalConfig>, ProgramError> {
+    let config = GlobalConfig::load(config_account)?;
+    pda::check(config_account, pda::config_address(config.bump())?)?;
+    Ok(config)
+}
+
+/// Loads the global config for writing, checking it is the program's config PDA like
+/// [`load_config`].
+fn load_config_mut(config_account: &AccountInfo) -> Result<RefMut<'_, GlobalConfig>, ProgramError> {
+    let config = GlobalConfig::load_mut(config_account)?;
+    pda::check(config_account, pda::config_address(config.bump())?)?;
+    Ok(config)
+}
+
+/// Loads the global config for writing, checking `admin` is its admin and signed.
+fn load_config_as_admin<'a>(
+    config_account: &'a AccountInfo,
+    admin: &AccountInfo,
+) -> Result<RefMut<'a, GlobalConfig>, ProgramError> {
+    if !admin.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let config = load_config_mut(config_account)?;
+    if config.admin() != admin.key() {
+        return Err(BondingCurveError::Unauthorized.into());
+    }
+    Ok(config)
+}
+
+/// Loads the global config of an instruction that trades or migrates a curve, which
+/// the admin may have paused.
+fn load_active_config(config_account: &AccountInfo) -> Result<Ref<'_, GlobalConfig>, ProgramError> {
+    let config = load_config(config_account)?;
+    if config.paused() {
+        return Err(BondingCurveError::ProgramPaused.into());
+    }
+    Ok(config)
+}
+
+/// Checks the state account is the PDA of the curve it holds, so it can sign for the
+/// curve's accounts.
+fn check_curve(state_account: &AccountInfo, state: &BondingCurveState) -> ProgramResult {
+    pda::check(state_account, pda::curve_address(state.vtoken_mint(), state.bump())?)
+}
+
+/// Checks the vault holding the fees accrued by a curve.
+fn check_fee_vault(
+    state_key: &Pubkey,
+    state: &BondingCurveState,
+    fee_vault: &AccountInfo,
+) -> ProgramResult {
+    pda::check(fee_vault, pda::fee_vault_address(state_key, state.fee_vault_bump())?)
+}
+
+/// Checks the token mint of a curve is still as `initialize` left it: nobody can
+/// freeze and the decimals are [`TOKEN_DECIMALS`]. Only the curve can mint, unless its
+/// supply was pre-minted: then nobody can, which caps the supply at `total_supply`.
+fn check_mint(
+    vtoken_mint: &AccountInfo,
+    state_key: &Pubkey,
+    state: &BondingCurveState,
+) -> ProgramResult {
+    let mint = Mint::from_account_info(vtoken_mint)?;
+    let mint_authority = match state.supply_mode()? {
+        SupplyMode::MintOnBuy => Some(state_key),
+        SupplyMode::PreMinted => None,
+    };
+    if !mint.is_initialized()
+        || mint.mint_authority() != mint_authority
+        || mint.supply() > state.total_supply()
+        || mint.has_freeze_authority()
+        || mint.decimals() != TOKEN_DECIMALS
+    {
+        return Err(BondingCurveError::InvalidMintAuthority.into());
+    }
+    Ok(())
+}
+
+/// Checks a trader's `token_account` holds `mint`. The token program checks the trader
+/// owns it when moving tokens out of it.
+fn check_token_account(token_account: &AccountInfo, mint: &Pubkey) -> ProgramResult {
+    if TokenAccount::from_account_info(token_account)?.mint() != mint {
+        return Err(BondingCurveError::InvalidMint.into());
+    }
+    Ok(())
+}
 
-    if !bonding_curve_state.is_initialized() {
-        return Err(ProgramError::UninitializedAccount);
+fn check_not_paused(state: &BondingCurveState) -> ProgramResult {
+    if state.paused() {
+        return Err(BondingCurveError::CurvePaused.into());
     }
+    Ok(())
+}
 
-    assert!(seller_ata.owner() == _token_program.key());
-    assert!(state_token_account.owner() == _token_program.key());
+fn check_trading(state: &BondingCurveState) -> ProgramResult {
+    if state.status()? != CurveStatus::Trading {
+        return Err(BondingCurveError::CurveNotTrading.into());
+    }
+    Ok(())
+}
 
-    assert!(vtoken_mint.key() == &bonding_curve_state.vtoken_mint());
-    assert!(vsol_mint.key() == &bonding_curve_state.vsol_mint());
+/// Pays the protocol fees accrued by a curve to the destination account. The config
+/// fee recipient must sign.
+pub fn claim_protocol_fees(accounts: &[AccountInfo]) -> ProgramResult {
+    process_claim(accounts, |config, state, recipient| {
+        if config.fee_recipient() != recipient {
+            return Err(BondingCurveError::Unauthorized.into());
+        }
+        let amount = state.protocol_fees();
+        state.set_protocol_fees(0);
+        Ok(amount)
+    })
+}
 
+/// Pays the creator fees accrued by a curve to the destination account. The curve
+/// creator must sign.
+pub fn claim_creator_fees(accounts: &[AccountInfo]) -> ProgramResult {
+    process_claim(accounts, |_config, state, recipient| {
+        if state.creator() != recipient {
+            return Err(BondingCurveError::Unauthorized.into());
+        }
+        let amount = state.creator_fees();
+        state.set_creator_fees(0);
+        Ok(amount)
+    })
+}
 
+/// Shared by the claim instructions: `claim` checks the recipient may claim, then
+/// takes the accrued amount out of its counter and returns it.
+///
+/// The destination is a token account of the quote mint for SPL quoted curves, and any
+/// account for native ones.
+fn process_claim(
+    accounts: &[AccountInfo],
+    claim: impl FnOnce(&GlobalConfig, &mut BondingCurveState, &Pubkey) -> Result<u64, ProgramError>,
+) -> ProgramResult {
+    let [state_account, config_account, recipient, fee_vault, destination, _token_program, ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
 
-    let amount = unsafe { *(instruction_data.as_ptr() as *const u64) };
+    if !recipient.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (amount, quote_mode, fee_vault_bump) = {
+        let config = load_config(config_account)?;
+        let mut state = BondingCurveState::load_mut(state_account)?;
+        check_curve(state_account, &state)?;
+        let address = pda::fee_vault_address(state_account.key(), state.fee_vault_bump())?;
+        pda::check(fee_vault, address)?;
 
-    // Calculate refund for selling based on quadratic bonding curve logic
-    let refund = calculate_refund(bonding_curve_state.vtoken_reserve_amount(), bonding_curve_state.vsol_reserve_amount(), amount);
+        let amount = claim(&config, &mut state, recipient.key())?;
+        (amount, state.quote_mode()?, state.fee_vault_bump())
+    };
 
-    // Update the state
-    if state_account.data_len() != 0 {
-        unsafe {
-            // Get a mutable pointer to the account's data once
-            let data_ptr = state_account.borrow_mut_data_unchecked().as_mut_ptr();
-    
-            // Calculate the new amount and store it in the correct position (32-byte offset)
-            *(data_ptr.add(32) as *mut [u8; 8]) = (BondingCurveState::from_account_info(state_account).vtoken_reserve_amount() - amount).to_le_bytes();
-            *(data_ptr.add(40) as *mut [u8; 8]) = (BondingCurveState::from_account_info(state_account).vsol_reserve_amount() - refund).to_le_bytes();
+    match quote_mode {
+        QuoteMode::SplToken => {
+            let bump = [fee_vault_bump];
+            let seeds = [
+                Seed::from(pda::FEE_VAULT_SEED),
+                Seed::from(state_account.key().as_ref()),
+                Seed::from(&bump),
+            ];
+
+            Transfer {
+                from: fee_vault,
+                to: destination,
+                authority: fee_vault,
+                amount,
+            }
+            .invoke_signed(&[Signer::from(&seeds)])?
         }
-    } else {
-        return Err(ProgramError::UninitializedAccount);
+        QuoteMode::Native => withdraw_lamports(fee_vault, destination, amount)?,
     }
 
+    Ok(())
+}
 
-    // Burn tokens from the seller's account (reducing token supply)
-    Burn {
-        token: seller_ata,
-        mint: vtoken_mint,
-        authority: seller,
+/// Pays the seed liquidity a migrated curve locked to the destination account, a token
+/// account of the liquidity mint. The curve creator must sign, from the curve's unlock
+/// timestamp on.
+///
+/// Accounts: the curve state, its LP lock, the creator (signer), the destination and
+/// the token program.
+pub fn claim_locked_lp(accounts: &[AccountInfo]) -> ProgramResult {
+    let [state_account, lp_lock, creator, destination, _token_program, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !creator.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (vtoken_mint, bump) = {
+        let state = BondingCurveState::load(state_account)?;
+        check_curve(state_account, &state)?;
+        if state.creator() != creator.key() {
+            return Err(BondingCurveError::Unauthorized.into());
+        }
+        // Only curves that migrated and locked their liquidity have a lock
+        if state.status()? != CurveStatus::Migrated {
+            return Err(BondingCurveError::NotMigrated.into());
+        }
+        if state.lp_policy()? != LpPolicy::Lock {
+            return Err(BondingCurveError::NothingToClaim.into());
+        }
+        pda::check(lp_lock, pda::lp_lock_address(state_account.key(), state.lp_lock_bump())?)?;
+        if sysvars::clock()?.unix_timestamp < state.lp_unlock_timestamp() {
+            return Err(BondingCurveError::LpLocked.into());
+        }
+        (*state.vtoken_mint(), state.bump())
+    };
+
+    let amount = TokenAccount::from_account_info(lp_lock)?.amount();
+    let bump = [bump];
+    let curve_seeds = pda::curve_seeds(&vtoken_mint, &bump);
+    Transfer {
+        from: lp_lock,
+        to: destination,
+        authority: state_account,
         amount,
     }
-    .invoke()?;
+    .invoke_signed(&[Signer::from(&curve_seeds)])
+}
+
+/// Pays the unsold tokens a migrated curve has released from its vesting account so
+/// far and the creator has not claimed yet to the destination account, a token account
+/// of the curve's token. The curve creator must sign.
+///
+/// Accounts: the curve state, its vesting account, the creator (signer), the
+/// destination and the token program.
+pub fn claim_vested_tokens(accounts: &[AccountInfo]) -> ProgramResult {
+    let [state_account, vesting, creator, destination, _token_program, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
 
-    // Refund SOL to seller
-    // (Token transfer code using invoke goes here)
+    if !creator.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
 
+    let (amount, vtoken_mint, bump) = {
+        let mut state = BondingCurveState::load_mut(state_account)?;
+        check_curve(state_account, &state)?;
+        if state.creator() != creator.key() {
+            return Err(BondingCurveError::Unauthorized.into());
+        }
+        // Only curves that migrated and vested their unsold tokens have a vesting account
+        if state.status()? != CurveStatus::Migrated {
+            return Err(BondingCurveError::NotMigrated.into());
+        }
+        if state.unsold_token_policy()? != UnsoldTokenPolicy::Vest {
+            return Err(BondingCurveError::NothingToClaim.into());
+        }
+        pda::check(vesting, pda::vesting_address(state_account.key(), state.vesting_bump())?)?;
+
+        let vested = state.vested_tokens(sysvars::clock()?.unix_timestamp)?;
+        let amount = vested.saturating_sub(state.vesting_claimed());
+        state.set_vesting_claimed(vested);
+        (amount, *state.vtoken_mint(), state.bump())
+    };
+
+    let bump = [bump];
+    let curve_seeds = pda::curve_seeds(&vtoken_mint, &bump);
     Transfer {
-        from: state_token_account,
-        to: seller_ata,
+        from: vesting,
+        to: destination,
         authority: state_account,
-        amount: refund,
-    }.invoke()?;
+        amount,
+    }
+    .invoke_signed(&[Signer::from(&curve_seeds)])
+}
 
+/// Creates the [`ReferrerStats`] of a referrer, so trades can name it. Only the config
+/// admin may approve referrers, so traders cannot name themselves to take the referral
+/// share of the protocol fee.
+///
+/// Accounts: the stats PDA, the referrer account referral fees are paid to, the config,
+/// the admin (signer), who pays for the stats, and the system program. Instruction
+/// data: the stats bump.
+pub fn initialize_referrer_stats(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [stats_account, referrer, config_account, admin, _system_program, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    load_config_as_admin(config_account, admin)?;
+
+    let [bump] = data else {
+        return Err(ProgramError::InvalidInstructionData);
+    };
+
+    pda::check(stats_account, pda::referrer_stats_address(referrer.key(), *bump)?)?;
+
+    let bump_seed = [*bump];
+    let seeds = [
+        Seed::from(pda::REFERRER_SEED),
+        Seed::from(referrer.key().as_ref()),
+        Seed::from(&bump_seed),
+    ];
+
+    CreateAccount {
+        from: admin,
+        to: stats_account,
+        lamports: sysvars::rent()?.minimum_balance(ReferrerStats::LEN),
+        space: ReferrerStats::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    let mut stats = ReferrerStats::init(stats_account)?;
+    stats.set_bump(*bump);
+    stats.set_referrer(referrer.key());
+
+    Ok(())
+}
+
+/// Accounts trailing the fixed accounts of a trade.
+struct TrailingAccounts<'a> {
+    /// Quote vault of an SPL quoted curve and the trader's quote token account.
+    spl_quote: Option<(&'a AccountInfo, &'a AccountInfo)>,
+
+    /// Token vault of a pre-minted curve.
+    token_vault: Option<&'a AccountInfo>,
+
+    /// Referrer named by the trade and its [`ReferrerStats`].
+    referral: Option<(&'a AccountInfo, &'a AccountInfo)>,
+}
+
+/// Splits the accounts trailing a trade: the quote vault and the trader's quote token
+/// account of an SPL quoted curve or the system program of a native one, the token
+/// vault of a pre-minted curve, then optionally a referrer account and its
+/// [`ReferrerStats`].
+fn trailing_accounts<'a>(
+    state_key: &Pubkey,
+    state: &BondingCurveState,
+    remaining: &'a [AccountInfo],
+) -> Result<TrailingAccounts<'a>, ProgramError> {
+    let (spl_quote, remaining) = match state.quote_mode()? {
+        QuoteMode::SplToken => {
+            let [quote_vault, quote_token_account, remaining @ ..] = remaining else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+            pda::check(quote_vault, pda::vault_address(state_key, state.vault_bump())?)?;
+            check_token_account(quote_token_account, state.vsol_mint())?;
+            (Some((quote_vault, quote_token_account)), remaining)
+        }
+        QuoteMode::Native => (None, remaining.get(1..).unwrap_or_default()),
+    };
+    let (token_vault, remaining) = match state.supply_mode()? {
+        SupplyMode::MintOnBuy => (None, remaining),
+        SupplyMode::PreMinted => {
+            let [token_vault, remaining @ ..] = remaining else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+            let address = pda::token_vault_address(state_key, state.token_vault_bump())?;
+            pda::check(token_vault, address)?;
+            (Some(token_vault), remaining)
+        }
+    };
+    let referral = match remaining {
+        [] => None,
+        [referrer, stats_account, ..] => Some((referrer, stats_account)),
+        [_] => return Err(ProgramError::NotEnoughAccountKeys),
+    };
+
+    Ok(TrailingAccounts {
+        spl_quote,
+        token_vault,
+        referral,
+    })
+}
+
+/// Accrues the fee of a trade and, when it names a referrer, records the referrer's
+/// share of it in its stats. Returns the referrer and its share, which is paid out
+/// rather than accrued; without a referrer that share stays with the protocol.
+///
+/// The referrer may not be the trader, nor a token account the trader owns.
+fn record_referral<'a>(
+    state: &mut BondingCurveState,
+    fee: u64,
+    trader: &AccountInfo,
+    referral: Option<(&'a AccountInfo, &AccountInfo)>,
+    referral_fee_share_bps: u16,
+) -> Result<Option<(&'a AccountInfo, u64)>, ProgramError> {
+    let Some((referrer, stats_account)) = referral else {
+        state.accrue_fee(fee, 0)?;
+        return Ok(None);
+    };
+
+    let mut stats = ReferrerStats::load_mut(stats_account)?;
+    pda::check(stats_account, pda::referrer_stats_address(referrer.key(), stats.bump())?)?;
+    let referrer_owner = if referrer.owner() == &pinocchio_token::ID {
+        *TokenAccount::from_account_info(referrer)?.owner()
+    } else {
+        *referrer.key()
+    };
+    if &referrer_owner == trader.key() {
+        return Err(BondingCurveError::InvalidReferrer.into());
+    }
+
+    let referral_fee = state.accrue_fee(fee, referral_fee_share_bps)?;
+    stats.record(referral_fee)?;
+    Ok(Some((referrer, referral_fee)))
+}
+
+/// Checks the trade account that stands for the quote asset: the quote mint of an SPL
+/// quoted curve or the quote vault of a native one.
+fn check_quote_account(
+    state_key: &Pubkey,
+    state: &BondingCurveState,
+    quote_account: &AccountInfo,
+) -> Result<QuoteMode, ProgramError> {
+    let quote_mode = state.quote_mode()?;
+    match quote_mode {
+        QuoteMode::SplToken if quote_account.key() != state.vsol_mint() => {
+            return Err(BondingCurveError::InvalidMint.into());
+        }
+        QuoteMode::SplToken => {}
+        QuoteMode::Native => pda::check(
+            quote_account,
+            pda::quote_vault_address(state_key, state.quote_vault_bump())?,
+        )?,
+    }
+    Ok(quote_mode)
+}
+
+/// Checks the token and quote vaults of a graduated pool.
+fn check_pool_vaults(
+    pool_key: &Pubkey,
+    pool: &PoolState,
+    token_vault: &AccountInfo,
+    quote_vault: &AccountInfo,
+) -> ProgramResult {
+    pda::check(token_vault, pda::pool_token_vault_address(pool_key, pool.token_vault_bump())?)?;
+    pda::check(quote_vault, pda::pool_quote_vault_address(pool_key, pool.quote_vault_bump())?)
+}
+
+/// Creates a token account of `mint` owned by `owner`, at the PDA of `seed`, `owner`
+/// and `bump`.
+fn create_token_account(
+    payer: &AccountInfo,
+    account: &AccountInfo,
+    seed: &[u8],
+    owner: &Pubkey,
+    bump: u8,
+    mint: &AccountInfo,
+) -> ProgramResult {
+    let bump = [bump];
+    let seeds = [Seed::from(seed), Seed::from(owner.as_ref()), Seed::from(&bump)];
+    CreateAccount {
+        from: payer,
+        to: account,
+        lamports: sysvars::rent()?.minimum_balance(TokenAccount::LEN),
+        space: TokenAccount::LEN as u64,
+        owner: &pinocchio_token::ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+    InitilizeAccount3 {
+        token: account,
+        owner,
+        mint,
+    }
+    .invoke()
+}
+
+/// A graduated pool signing for the assets it holds.
+struct PoolSigner<'a> {
+    pool: &'a AccountInfo,
+    seeds: &'a [Seed<'a>; 3],
+    quote_mode: QuoteMode,
+}
+
+impl PoolSigner<'_> {
+    /// Moves `amount` of quote from `user` into the pool quote vault.
+    fn deposit_quote(
+        &self,
+        user: &AccountInfo,
+        user_quote_account: &AccountInfo,
+        quote_vault: &AccountInfo,
+        amount: u64,
+    ) -> ProgramResult {
+        match self.quote_mode {
+            QuoteMode::SplToken => Transfer {
+                from: user_quote_account,
+                to: quote_vault,
+                authority: user,
+                amount,
+            }
+            .invoke(),
+            QuoteMode::Native => system::Transfer {
+                from: user,
+                to: quote_vault,
+                lamports: amount,
+            }
+            .invoke(),
+        }
+    }
+
+    /// Pays `amount` of quote out of the pool quote vault.
+    fn pay_quote(&self, quote_vault: &AccountInfo, to: &AccountInfo, amount: u64) -> ProgramResult {
+        match self.quote_mode {
+            QuoteMode::SplToken => Transfer {
+                from: quote_vault,
+                to,
+                authority: self.pool,
+                amount,
+            }
+            .invoke_signed(&[Signer::from(self.seeds)]),
+            QuoteMode::Native => withdraw_lamports(quote_vault, to, amount),
+        }
+    }
+
+    /// Pays `amount` of tokens out of the pool token vault.
+    fn pay_tokens(
+        &self,
+        token_vault: &AccountInfo,
+        to: &AccountInfo,
+        amount: u64,
+    ) -> ProgramResult {
+        Transfer {
+            from: token_vault,
+            to,
+            authority: self.pool,
+            amount,
+        }
+        .invoke_signed(&[Signer::from(self.seeds)])
+    }
+}
+
+/// Moves lamports out of a native curve's quote or fee vault, or a native pool's quote
+/// vault. The program owns the vault, so it debits it directly, but never into its
+/// rent-exempt minimum.
+fn withdraw_lamports(vault: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    if vault.owner() != &ID {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let minimum_balance = sysvars::rent()?.minimum_balance(vault.data_len());
+    let remaining = vault
+        .lamports()
+        .checked_sub(lamports)
+        .filter(|remaining| *remaining >= minimum_balance)
+        .ok_or(BondingCurveError::InsufficientReserve)?;
+    let credited = to
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(BondingCurveError::MathOverflow)?;
+
+    *vault.try_borrow_mut_lamports()? = remaining;
+    *to.try_borrow_mut_lamports()? = credited;
     Ok(())
 }
 
-fn calculate_refund(vtoken_reserve: u64, vsol_reserve: u64, amount: u64) -> u64 {
-    // Calculate refund based on current bonding curve position
-    let k = vtoken_reserve * vsol_reserve;
-    let new_vtoken_reserve = vtoken_reserve.checked_add(amount).unwrap();
-    let new_vsol_reserve = k / new_vtoken_reserve;
-    vsol_reserve.checked_sub(new_vsol_reserve).unwrap()
+/// Reads instruction data made of a single boolean byte.
+fn read_flag(data: &[u8]) -> Result<bool, ProgramError> {
+    match data {
+        [0] => Ok(false),
+        [1] => Ok(true),
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
 }
 
-fn calculate_cost(vtoken_reserve_amount: u64, vsol_reserve: u64, amount: u64) -> u64 {
-    // Using the constant product formula, calculate cost for the amount to be purchased
-    let k = vtoken_reserve_amount * vsol_reserve;
-    let new_vtoken_reserve = vtoken_reserve_amount.checked_sub(amount).unwrap();
-    let new_vsol_reserve = k / new_vtoken_reserve;
-    new_vsol_reserve.checked_sub(vsol_reserve).unwrap()
+/// Reads a little-endian `u64` at `offset` of the instruction data.
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    data.get(offset..offset + 8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_le_bytes)
+        .ok_or(ProgramError::InvalidInstructionData)
 }
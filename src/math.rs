@@ -0,0 +1,222 @@
+//! Overflow-safe fixed-point arithmetic for curve pricing.
+//!
+//! Products are formed in 256 bits before dividing, so no intermediate can wrap, and
+//! every division takes an explicit [`Rounding`]. Pricing code rounds in favour of
+//! the pool: up for what a trader pays, down for what a trader receives.
+
+use pinocchio::program_error::ProgramError;
+
+use crate::error::BondingCurveError;
+
+/// `1` in Q64.64.
+pub const ONE: u128 = 1 << 64;
+
+/// `ln(2)` in Q64.64, rounded down.
+const LN_2: u128 = 0xb17217f7d1cf79ab;
+
+/// Direction in which a division discards its remainder.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+impl Rounding {
+    /// The opposite direction, for subtrahends whose error must not leak into a
+    /// difference rounded in `self`'s direction.
+    pub fn reverse(self) -> Self {
+        match self {
+            Self::Down => Self::Up,
+            Self::Up => Self::Down,
+        }
+    }
+}
+
+/// `a * b / denominator`, rounded as requested.
+///
+/// Fails with `MathOverflow` if the denominator is zero or the quotient does not fit
+/// in a `u128`.
+pub fn mul_div(
+    a: u128,
+    b: u128,
+    denominator: u128,
+    rounding: Rounding,
+) -> Result<u128, ProgramError> {
+    if denominator == 0 {
+        return Err(BondingCurveError::MathOverflow.into());
+    }
+
+    let (high, low) = full_mul(a, b);
+    let (quotient, remainder) = if high == 0 {
+        (low / denominator, low % denominator)
+    } else {
+        div_rem_256(high, low, denominator)?
+    };
+
+    match rounding {
+        Rounding::Up if remainder > 0 => quotient
+            .checked_add(1)
+            .ok_or(BondingCurveError::MathOverflow.into()),
+        _ => Ok(quotient),
+    }
+}
+
+/// [`mul_div`] for `u64` operands whose result must fit back in a `u64`.
+pub fn mul_div_u64(
+    a: u64,
+    b: u64,
+    denominator: u64,
+    rounding: Rounding,
+) -> Result<u64, ProgramError> {
+    to_u64(mul_div(
+        a as u128,
+        b as u128,
+        denominator as u128,
+        rounding,
+    )?)
+}
+
+/// `numerator / denominator`, rounded as requested.
+pub fn div(numerator: u128, denominator: u128, rounding: Rounding) -> Result<u128, ProgramError> {
+    mul_div(numerator, 1, denominator, rounding)
+}
+
+pub fn to_u64(value: u128) -> Result<u64, ProgramError> {
+    u64::try_from(value).map_err(|_| BondingCurveError::MathOverflow.into())
+}
+
+/// `e^x` for `x` in Q64.64, as Q64.64, rounded as requested.
+///
+/// Splits `x` into `n * ln(2) + r` with `0 <= r < ln(2)`, evaluates `e^r` with its
+/// Taylor series and scales the result by `2^n`. Rounding down uses an upper bound
+/// of `ln(2)` and truncates every term; rounding up uses a lower bound, rounds every
+/// term up and adds the bound of the truncated tail.
+pub fn exp(x: u128, rounding: Rounding) -> Result<u128, ProgramError> {
+    let ln_2 = match rounding {
+        Rounding::Down => LN_2 + 1,
+        Rounding::Up => LN_2,
+    };
+    let n = x / ln_2;
+    let r = x % ln_2;
+
+    // `e^r < 2`, so the result fits as long as it is shifted by less than 63 bits
+    if n >= 63 {
+        return Err(BondingCurveError::MathOverflow.into());
+    }
+
+    let mut term = ONE;
+    let mut sum = ONE;
+    let mut i = 1;
+    match rounding {
+        Rounding::Down => {
+            while term > 0 {
+                term = ((term * r) >> 64) / i;
+                sum += term;
+                i += 1;
+            }
+        }
+        Rounding::Up => {
+            // Once a term is at most one unit, `r < 1` bounds the rest of the
+            // series by that same unit
+            while term > 1 {
+                term = (((term * r) >> 64) + 1).div_ceil(i);
+                sum += term;
+                i += 1;
+            }
+            sum += 1;
+        }
+    }
+
+    Ok(sum << n)
+}
+
+/// `ln(x)` for `x >= 1` in Q64.64, as Q64.64, rounded down to within a few units.
+///
+/// Splits `x` into `2^n * y` with `1 <= y < 2` and evaluates `ln(y)` as
+/// `2 * atanh((y - 1) / (y + 1))`, whose series converges quickly for `y < 2`. Only
+/// used to estimate inverses that are then checked against the forward pricing.
+pub fn ln(x: u128) -> Result<u128, ProgramError> {
+    if x < ONE {
+        return Err(BondingCurveError::MathOverflow.into());
+    }
+
+    let n = 63 - x.leading_zeros() as u128;
+    let y = x >> n;
+
+    let z = ((y - ONE) << 64) / (y + ONE);
+    let z_squared = (z * z) >> 64;
+    let mut power = z;
+    let mut sum = z;
+    let mut k = 1;
+    loop {
+        power = (power * z_squared) >> 64;
+        k += 2;
+        let term = power / k;
+        if term == 0 {
+            break;
+        }
+        sum += term;
+    }
+
+    Ok(n * LN_2 + 2 * sum)
+}
+
+/// Integer square root, rounded down.
+pub fn sqrt(value: u128) -> u128 {
+    if value < 2 {
+        return value;
+    }
+
+    // Newton's method from a power of two above the root decreases monotonically
+    let mut root = 1 << (128 - value.leading_zeros()).div_ceil(2);
+    loop {
+        let next = (root + value / root) / 2;
+        if next >= root {
+            return root;
+        }
+        root = next;
+    }
+}
+
+/// Full 256-bit product of two `u128`s, as `(high, low)` halves.
+fn full_mul(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+
+    let (a_high, a_low) = (a >> 64, a & MASK);
+    let (b_high, b_low) = (b >> 64, b & MASK);
+
+    let low_low = a_low * b_low;
+    let low_high = a_low * b_high;
+    let high_low = a_high * b_low;
+    let high_high = a_high * b_high;
+
+    let middle = (low_low >> 64) + (low_high & MASK) + (high_low & MASK);
+    let low = (low_low & MASK) | (middle << 64);
+    let high = high_high + (low_high >> 64) + (high_low >> 64) + (middle >> 64);
+
+    (high, low)
+}
+
+/// Divides the 256-bit value `high * 2^128 + low` by `denominator` with a binary long
+/// division, returning `(quotient, remainder)`.
+fn div_rem_256(high: u128, low: u128, denominator: u128) -> Result<(u128, u128), ProgramError> {
+    // The quotient only fits in 128 bits if the high half is below the divisor
+    if high >= denominator {
+        return Err(BondingCurveError::MathOverflow.into());
+    }
+
+    let mut remainder = high;
+    let mut quotient = 0;
+    for bit in (0..128).rev() {
+        // `remainder < denominator`, so shifting it overflows into at most one bit
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= denominator {
+            remainder = remainder.wrapping_sub(denominator);
+            quotient |= 1;
+        }
+    }
+
+    Ok((quotient, remainder))
+}
@@ -0,0 +1,423 @@
+//! Migration targets of graduated curves.
+//!
+//! A complete curve migrates its collateral and unsold tokens into a pool on the
+//! target stored in its state, or as many of the tokens as its [`UnsoldTokenPolicy`]
+//! puts in the pool: the program's own pool (see [`crate::amm`]) or a pool
+//! created on an external DEX. External pools are created by the curve's migration
+//! authority, a PDA that holds the liquidity, pays what the DEX charges and signs the
+//! pool-creation instruction built by the adapters below.
+//!
+//! Adapters take the DEX program followed by the accounts of its pool-creation
+//! instruction, in the order the DEX expects them. They check the accounts that carry
+//! the curve's liquidity and leave the rest to the DEX.
+//!
+//! Whatever the target, the liquidity minted for the seed is then burned or locked
+//! for the creator, see [`LpPolicy`].
+
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{AccountMeta, Instruction, Signer},
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    ProgramResult,
+};
+
+use crate::error::BondingCurveError;
+
+/// Raydium constant product AMM (CPMM) program.
+pub const RAYDIUM_CPMM_ID: Pubkey =
+    five8_const::decode_32_const("CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C");
+
+/// Meteora dynamic AMM (DAMM v1) program.
+pub const METEORA_DAMM_ID: Pubkey =
+    five8_const::decode_32_const("Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB");
+
+/// Lamports the in-house pool costs the migration authority; the payer funds its
+/// accounts directly.
+pub const IN_HOUSE_MIGRATION_FEE: u64 = 0;
+
+/// Lamports a Raydium CPMM pool costs the migration authority: the 0.15 SOL pool
+/// creation fee, and rent for the pool, its vaults, liquidity mint and observation
+/// state and the authority's liquidity account.
+pub const RAYDIUM_CPMM_MIGRATION_FEE: u64 = 200_000_000;
+
+/// Lamports a Meteora DAMM pool costs the migration authority: rent for the pool,
+/// its liquidity mint and metadata, its vault liquidity accounts, its protocol fee
+/// accounts and the authority's liquidity account.
+pub const METEORA_DAMM_MIGRATION_FEE: u64 = 100_000_000;
+
+/// Anchor discriminator of the Raydium CPMM `initialize` instruction.
+pub const RAYDIUM_CPMM_INITIALIZE: [u8; 8] = [175, 175, 109, 31, 13, 152, 155, 237];
+
+/// Anchor discriminator of the Meteora DAMM `initialize_permissionless_pool`
+/// instruction.
+pub const METEORA_DAMM_INITIALIZE_PERMISSIONLESS_POOL: [u8; 8] =
+    [118, 173, 41, 157, 173, 72, 97, 103];
+
+/// Number of accounts of the Raydium CPMM `initialize` instruction.
+pub const RAYDIUM_CPMM_ACCOUNTS_LEN: usize = 20;
+
+/// Number of accounts of the Meteora DAMM `initialize_permissionless_pool` instruction.
+pub const METEORA_DAMM_ACCOUNTS_LEN: usize = 24;
+
+/// Number of accounts `Migrate` takes for the in-house pool: the pool, its token and
+/// quote vaults, its liquidity mint and the vault its seed liquidity is minted to.
+const IN_HOUSE_ACCOUNTS_LEN: usize = 5;
+
+/// Accounts `Migrate` takes for an external target before the DEX program: the
+/// migration authority and its token and quote accounts.
+const EXTERNAL_ACCOUNTS_LEN: usize = 3;
+
+/// Where a complete curve migrates, selected at `Initialize`.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MigrationTarget {
+    /// The program's own constant product pool.
+    InHouse = 0,
+
+    /// A Raydium CPMM pool.
+    RaydiumCpmm = 1,
+
+    /// A Meteora DAMM pool.
+    MeteoraDamm = 2,
+}
+
+impl TryFrom<u8> for MigrationTarget {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::InHouse),
+            1 => Ok(Self::RaydiumCpmm),
+            2 => Ok(Self::MeteoraDamm),
+            _ => Err(BondingCurveError::InvalidCurveParameters.into()),
+        }
+    }
+}
+
+impl MigrationTarget {
+    /// Whether the target is a pool of another program, which only takes SPL tokens.
+    pub fn is_external(self) -> bool {
+        self != Self::InHouse
+    }
+
+    /// Lamports the payer of a migration forwards to the migration authority. The
+    /// curve refunds them out of its migration fee.
+    pub fn fee(self) -> u64 {
+        match self {
+            Self::InHouse => IN_HOUSE_MIGRATION_FEE,
+            Self::RaydiumCpmm => RAYDIUM_CPMM_MIGRATION_FEE,
+            Self::MeteoraDamm => METEORA_DAMM_MIGRATION_FEE,
+        }
+    }
+
+    /// Number of accounts `Migrate` takes for the target, after the config.
+    pub fn accounts_len(self) -> usize {
+        match self {
+            Self::InHouse => IN_HOUSE_ACCOUNTS_LEN,
+            Self::RaydiumCpmm => EXTERNAL_ACCOUNTS_LEN + 1 + RAYDIUM_CPMM_ACCOUNTS_LEN,
+            Self::MeteoraDamm => EXTERNAL_ACCOUNTS_LEN + 1 + METEORA_DAMM_ACCOUNTS_LEN,
+        }
+    }
+}
+
+/// What a migration does with the liquidity minted for the seed of the pool, selected
+/// at `Initialize`. Either way nobody can pull the seed out of the pool right after
+/// migrating.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LpPolicy {
+    /// Burn all of it.
+    #[default]
+    Burn = 0,
+
+    /// Move it to the curve's LP lock, which releases it to the creator from the
+    /// curve's unlock timestamp on, see `ClaimLockedLp`. The timestamp must be later
+    /// than the curve's creation.
+    Lock = 1,
+}
+
+impl TryFrom<u8> for LpPolicy {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Burn),
+            1 => Ok(Self::Lock),
+            _ => Err(BondingCurveError::InvalidCurveParameters.into()),
+        }
+    }
+}
+
+/// What a migration does with the tokens the curve did not sell, selected at
+/// `Initialize`.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum UnsoldTokenPolicy {
+    /// Seed the pool with all of them, which opens the pool below the final price of
+    /// the curve when there are more than that price calls for.
+    #[default]
+    SeedPool = 0,
+
+    /// Seed the pool with the tokens that match the collateral at the final price of
+    /// the curve and burn the rest.
+    Burn = 1,
+
+    /// Seed the pool like [`Self::Burn`] and move the rest to the curve's vesting
+    /// account, which releases them to the creator linearly over the curve's vesting
+    /// duration from the migration on, see `ClaimVestedTokens`. The duration must not
+    /// be zero.
+    Vest = 2,
+}
+
+impl TryFrom<u8> for UnsoldTokenPolicy {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::SeedPool),
+            1 => Ok(Self::Burn),
+            2 => Ok(Self::Vest),
+            _ => Err(BondingCurveError::InvalidCurveParameters.into()),
+        }
+    }
+}
+
+/// Liquidity the migration authority puts into an external pool.
+pub struct Liquidity<'a> {
+    /// The migration authority, which creates the pool.
+    pub authority: &'a Pubkey,
+    pub token_mint: &'a Pubkey,
+    pub quote_mint: &'a Pubkey,
+    /// Token account of the authority holding `token_amount`.
+    pub token_account: &'a Pubkey,
+    /// Token account of the authority holding `quote_amount`.
+    pub quote_account: &'a Pubkey,
+    pub token_amount: u64,
+    pub quote_amount: u64,
+}
+
+/// How an instruction accesses one of its accounts.
+#[derive(Clone, Copy)]
+enum Access {
+    Readonly,
+    Writable,
+    WritableSigner,
+}
+
+use Access::{Readonly as R, Writable as W, WritableSigner as WS};
+
+/// Pool-creation instruction of an external target, ready to be invoked.
+pub struct PoolCreation<'a, const ACCOUNTS: usize, const DATA: usize> {
+    pub program_id: &'static Pubkey,
+    pub accounts: [AccountMeta<'a>; ACCOUNTS],
+    pub account_infos: [&'a AccountInfo; ACCOUNTS],
+    pub data: [u8; DATA],
+    /// Positions of the liquidity mint of the pool and of the authority's account the
+    /// DEX mints the liquidity to.
+    lp_mint_index: usize,
+    lp_account_index: usize,
+}
+
+impl<'a, const ACCOUNTS: usize, const DATA: usize> PoolCreation<'a, ACCOUNTS, DATA> {
+    /// Builds the instruction of `program_id` over `accounts`, the program account
+    /// followed by the instruction accounts, accessed as `access` lists. `lp` holds the
+    /// positions of the liquidity mint and of the account the liquidity is minted to.
+    fn new(
+        program_id: &'static Pubkey,
+        accounts: &'a [AccountInfo],
+        access: &[Access; ACCOUNTS],
+        lp: [usize; 2],
+        data: [u8; DATA],
+    ) -> Result<Self, ProgramError> {
+        let [program, accounts @ ..] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        if program.key() != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let accounts: &'a [AccountInfo; ACCOUNTS] = accounts
+            .get(..ACCOUNTS)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?
+            .try_into()
+            .unwrap();
+
+        Ok(Self {
+            program_id,
+            accounts: core::array::from_fn(|index| {
+                let key = accounts[index].key();
+                match access[index] {
+                    Access::Readonly => AccountMeta::readonly(key),
+                    Access::Writable => AccountMeta::writable(key),
+                    Access::WritableSigner => AccountMeta::writable_signer(key),
+                }
+            }),
+            account_infos: core::array::from_fn(|index| &accounts[index]),
+            data,
+            lp_mint_index: lp[0],
+            lp_account_index: lp[1],
+        })
+    }
+
+    pub fn instruction(&self) -> Instruction<'a, '_, '_, '_> {
+        Instruction {
+            program_id: self.program_id,
+            data: &self.data,
+            accounts: &self.accounts,
+        }
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        invoke_signed(&self.instruction(), &self.account_infos, signers)
+    }
+
+    /// Liquidity mint of the pool.
+    pub fn lp_mint(&self) -> &'a AccountInfo {
+        self.account_infos[self.lp_mint_index]
+    }
+
+    /// Account of the migration authority the DEX mints the seed liquidity to. The DEX
+    /// creates it, so it only holds the liquidity once the pool is created.
+    pub fn lp_account(&self) -> &'a AccountInfo {
+        self.account_infos[self.lp_account_index]
+    }
+
+    /// Checks that the account at `index` is `key`.
+    fn expect(&self, index: usize, key: &Pubkey) -> Result<(), ProgramError> {
+        if self.accounts[index].pubkey != key {
+            return Err(BondingCurveError::InvalidMigrationAccount.into());
+        }
+        Ok(())
+    }
+}
+
+/// Accounts of the Raydium CPMM `initialize` instruction.
+const RAYDIUM_CPMM_ACCESS: [Access; RAYDIUM_CPMM_ACCOUNTS_LEN] = [
+    WS, // creator
+    R,  // amm config
+    R,  // pool authority
+    W,  // pool state
+    R,  // token 0 mint
+    R,  // token 1 mint
+    W,  // liquidity mint
+    W,  // creator token 0 account
+    W,  // creator token 1 account
+    W,  // creator liquidity account
+    W,  // token 0 vault
+    W,  // token 1 vault
+    W,  // pool creation fee receiver
+    W,  // observation state
+    R,  // token program
+    R,  // token 0 program
+    R,  // token 1 program
+    R,  // associated token program
+    R,  // system program
+    R,  // rent sysvar
+];
+
+/// Raydium CPMM `initialize`, creating a pool with the liquidity that opens
+/// immediately.
+///
+/// Raydium orders the two mints of a pool by address, so the token is token 0 when its
+/// mint sorts before the quote mint and token 1 otherwise.
+pub fn raydium_cpmm<'a>(
+    accounts: &'a [AccountInfo],
+    liquidity: &Liquidity,
+) -> Result<PoolCreation<'a, RAYDIUM_CPMM_ACCOUNTS_LEN, 32>, ProgramError> {
+    let token_first = liquidity.token_mint < liquidity.quote_mint;
+    let ((mint_0, account_0, amount_0), (mint_1, account_1, amount_1)) = {
+        let token = (
+            liquidity.token_mint,
+            liquidity.token_account,
+            liquidity.token_amount,
+        );
+        let quote = (
+            liquidity.quote_mint,
+            liquidity.quote_account,
+            liquidity.quote_amount,
+        );
+        if token_first {
+            (token, quote)
+        } else {
+            (quote, token)
+        }
+    };
+
+    let mut data = [0; 32];
+    data[0..8].copy_from_slice(&RAYDIUM_CPMM_INITIALIZE);
+    data[8..16].copy_from_slice(&amount_0.to_le_bytes());
+    data[16..24].copy_from_slice(&amount_1.to_le_bytes());
+    // Open time; Raydium opens pools whose open time has passed right away
+    data[24..32].copy_from_slice(&0u64.to_le_bytes());
+
+    let creation = PoolCreation::new(
+        &RAYDIUM_CPMM_ID,
+        accounts,
+        &RAYDIUM_CPMM_ACCESS,
+        [6, 9],
+        data,
+    )?;
+    creation.expect(0, liquidity.authority)?;
+    creation.expect(4, mint_0)?;
+    creation.expect(5, mint_1)?;
+    creation.expect(7, account_0)?;
+    creation.expect(8, account_1)?;
+    Ok(creation)
+}
+
+/// Accounts of the Meteora DAMM `initialize_permissionless_pool` instruction.
+const METEORA_DAMM_ACCESS: [Access; METEORA_DAMM_ACCOUNTS_LEN] = [
+    W,  // pool
+    W,  // liquidity mint
+    R,  // token a mint
+    R,  // token b mint
+    W,  // token a vault
+    W,  // token b vault
+    W,  // token a vault liquidity mint
+    W,  // token b vault liquidity mint
+    W,  // pool token a vault liquidity account
+    W,  // pool token b vault liquidity account
+    W,  // payer token a account
+    W,  // payer token b account
+    W,  // payer liquidity account
+    W,  // protocol token a fee account
+    W,  // protocol token b fee account
+    WS, // payer
+    R,  // fee owner
+    R,  // rent sysvar
+    W,  // liquidity mint metadata
+    R,  // metadata program
+    R,  // vault program
+    R,  // token program
+    R,  // associated token program
+    R,  // system program
+];
+
+/// Meteora DAMM `initialize_permissionless_pool`, creating a constant product pool
+/// with the token as token a and the quote as token b.
+pub fn meteora_damm<'a>(
+    accounts: &'a [AccountInfo],
+    liquidity: &Liquidity,
+) -> Result<PoolCreation<'a, METEORA_DAMM_ACCOUNTS_LEN, 25>, ProgramError> {
+    let mut data = [0; 25];
+    data[0..8].copy_from_slice(&METEORA_DAMM_INITIALIZE_PERMISSIONLESS_POOL);
+    // Curve type: constant product
+    data[8] = 0;
+    data[9..17].copy_from_slice(&liquidity.token_amount.to_le_bytes());
+    data[17..25].copy_from_slice(&liquidity.quote_amount.to_le_bytes());
+
+    let creation = PoolCreation::new(
+        &METEORA_DAMM_ID,
+        accounts,
+        &METEORA_DAMM_ACCESS,
+        [1, 12],
+        data,
+    )?;
+    creation.expect(2, liquidity.token_mint)?;
+    creation.expect(3, liquidity.quote_mint)?;
+    creation.expect(10, liquidity.token_account)?;
+    creation.expect(11, liquidity.quote_account)?;
+    creation.expect(15, liquidity.authority)?;
+    Ok(creation)
+}
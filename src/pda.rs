@@ -0,0 +1,269 @@
+//! Program derived addresses.
+//!
+//! Every account the program signs for, or trusts by address, is a PDA of [`ID`]
+//! derived as `create_program_address` derives it: the SHA-256 of the seeds, the bump,
+//! the program id and the `ProgramDerivedAddress` marker. Clients find the bumps once
+//! and pass them in; the program stores them and only verifies addresses afterwards.
+//! The global config is created only at its canonical bump, see
+//! [`find_config_address`], so there is one; it is verified by its stored bump too.
+//!
+//! A curve state is the authority of the accounts it owns: its vaults and its token
+//! mint. Instructions sign for it with [`curve_seeds`]. Likewise a graduated pool is
+//! the authority of its vaults and liquidity mint, see [`pool_seeds`]. A curve
+//! migrating to an external DEX hands its liquidity to its migration authority, which
+//! signs with [`migration_authority_seeds`].
+
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::Seed,
+    program_error::ProgramError,
+    pubkey::{Pubkey, MAX_SEEDS},
+};
+
+use crate::{error::BondingCurveError, BPF_LOADER_UPGRADEABLE_ID, ID};
+
+/// Seed of a curve state, followed by the token mint it sells.
+pub const CURVE_SEED: &[u8] = b"curve";
+
+/// Seed of the token account holding the SPL quote of a curve, followed by the state.
+pub const VAULT_SEED: &[u8] = b"vault";
+
+/// Seed of the token account holding the unsold supply of a pre-minted curve,
+/// followed by the state.
+pub const TOKEN_VAULT_SEED: &[u8] = b"token_vault";
+
+/// Seed of the account holding the lamports of a curve quoted in native SOL, followed
+/// by the state.
+pub const QUOTE_VAULT_SEED: &[u8] = b"quote_vault";
+
+/// Seed of the account holding the fees accrued by a curve, followed by the state.
+pub const FEE_VAULT_SEED: &[u8] = b"fee_vault";
+
+/// Seed of the global config.
+pub const CONFIG_SEED: &[u8] = b"config";
+
+/// Seed of the stats of a referrer, followed by the account it is paid at.
+pub const REFERRER_SEED: &[u8] = b"referrer";
+
+/// Seed of the pool a curve graduates into, followed by the curve state.
+pub const POOL_SEED: &[u8] = b"pool";
+
+/// Seed of the token account holding the tokens of a pool, followed by the pool.
+pub const POOL_TOKEN_VAULT_SEED: &[u8] = b"pool_token_vault";
+
+/// Seed of the account holding the quote of a pool, followed by the pool.
+pub const POOL_QUOTE_VAULT_SEED: &[u8] = b"pool_quote_vault";
+
+/// Seed of the liquidity token mint of a pool, followed by the pool.
+pub const LP_MINT_SEED: &[u8] = b"lp_mint";
+
+/// Seed of the token account holding the liquidity minted when a pool is seeded,
+/// followed by the pool.
+pub const LP_VAULT_SEED: &[u8] = b"lp_vault";
+
+/// Seed of the token account locking the seed liquidity of a migrated curve for its
+/// creator, followed by the curve state.
+pub const LP_LOCK_SEED: &[u8] = b"lp_lock";
+
+/// Seed of the token account vesting the unsold tokens of a migrated curve for its
+/// creator, followed by the curve state.
+pub const VESTING_SEED: &[u8] = b"vesting";
+
+/// Seed of the authority creating the external pool a curve migrates to, followed by
+/// the curve state.
+pub const MIGRATION_AUTHORITY_SEED: &[u8] = b"migration_authority";
+
+/// Seed of the token account of a migration authority, followed by the authority.
+pub const MIGRATION_TOKEN_SEED: &[u8] = b"migration_token";
+
+/// Seed of the quote token account of a migration authority, followed by the
+/// authority.
+pub const MIGRATION_QUOTE_SEED: &[u8] = b"migration_quote";
+
+/// Marker appended to the seeds of every program derived address.
+#[cfg(not(target_os = "solana"))]
+const PDA_MARKER: &[u8] = b"ProgramDerivedAddress";
+
+/// Address of the program derived from `seeds` and `bump`.
+///
+/// On-chain this is `create_program_address`, which rejects bumps that leave the
+/// address on the ed25519 curve. Host builds have no curve arithmetic and only hash.
+pub fn create_program_address(seeds: &[&[u8]], bump: u8) -> Result<Pubkey, ProgramError> {
+    derive_address(seeds, bump, &ID)
+}
+
+/// Canonical address and bump of `seeds` under `program_id`: the highest bump that
+/// derives an address, as clients find it. Host builds accept every bump, so they
+/// always find the highest.
+fn find_program_address(
+    seeds: &[&[u8]],
+    program_id: &Pubkey,
+) -> Result<(Pubkey, u8), ProgramError> {
+    #[cfg(target_os = "solana")]
+    {
+        pinocchio::pubkey::try_find_program_address(seeds, program_id)
+            .ok_or(BondingCurveError::InvalidPda.into())
+    }
+
+    #[cfg(not(target_os = "solana"))]
+    {
+        Ok((derive_address(seeds, u8::MAX, program_id)?, u8::MAX))
+    }
+}
+
+/// [`create_program_address`] under any `program_id`.
+fn derive_address(seeds: &[&[u8]], bump: u8, program_id: &Pubkey) -> Result<Pubkey, ProgramError> {
+    if seeds.len() >= MAX_SEEDS {
+        return Err(ProgramError::MaxSeedLengthExceeded);
+    }
+
+    let bump = [bump];
+    let mut input: [&[u8]; MAX_SEEDS + 2] = [&[]; MAX_SEEDS + 2];
+    input[..seeds.len()].copy_from_slice(seeds);
+    input[seeds.len()] = &bump;
+
+    #[cfg(target_os = "solana")]
+    {
+        pinocchio::pubkey::create_program_address(&input[..seeds.len() + 1], program_id)
+            .map_err(|_| BondingCurveError::InvalidPda.into())
+    }
+
+    #[cfg(not(target_os = "solana"))]
+    {
+        input[seeds.len() + 1] = program_id.as_ref();
+        input[seeds.len() + 2] = PDA_MARKER;
+        Ok(solana_nostd_sha256::hashv(&input[..seeds.len() + 3]))
+    }
+}
+
+/// Checks that `account` is at `address`, derived by one of the functions below.
+pub fn check(account: &AccountInfo, address: Pubkey) -> Result<(), ProgramError> {
+    if address != *account.key() {
+        return Err(BondingCurveError::InvalidPda.into());
+    }
+    Ok(())
+}
+
+/// Address of the state of the curve selling `vtoken_mint`.
+pub fn curve_address(vtoken_mint: &Pubkey, bump: u8) -> Result<Pubkey, ProgramError> {
+    create_program_address(&[CURVE_SEED, vtoken_mint], bump)
+}
+
+/// Signer seeds of the state of the curve selling `vtoken_mint`.
+pub fn curve_seeds<'a>(vtoken_mint: &'a Pubkey, bump: &'a [u8; 1]) -> [Seed<'a>; 3] {
+    [
+        Seed::from(CURVE_SEED),
+        Seed::from(vtoken_mint.as_ref()),
+        Seed::from(bump.as_ref()),
+    ]
+}
+
+/// Address of the token account holding the SPL quote of a curve.
+pub fn vault_address(state: &Pubkey, bump: u8) -> Result<Pubkey, ProgramError> {
+    create_program_address(&[VAULT_SEED, state], bump)
+}
+
+/// Address of the token account holding the unsold supply of a pre-minted curve.
+pub fn token_vault_address(state: &Pubkey, bump: u8) -> Result<Pubkey, ProgramError> {
+    create_program_address(&[TOKEN_VAULT_SEED, state], bump)
+}
+
+/// Address of the quote vault of a native curve.
+pub fn quote_vault_address(state: &Pubkey, bump: u8) -> Result<Pubkey, ProgramError> {
+    create_program_address(&[QUOTE_VAULT_SEED, state], bump)
+}
+
+/// Address of the vault holding the fees accrued by a curve.
+pub fn fee_vault_address(state: &Pubkey, bump: u8) -> Result<Pubkey, ProgramError> {
+    create_program_address(&[FEE_VAULT_SEED, state], bump)
+}
+
+/// Address of the global config.
+pub fn config_address(bump: u8) -> Result<Pubkey, ProgramError> {
+    create_program_address(&[CONFIG_SEED], bump)
+}
+
+/// Address and bump of the global config at its canonical bump, the only config the
+/// program accepts. Configs at other bumps would each have their own admin.
+pub fn find_config_address() -> Result<(Pubkey, u8), ProgramError> {
+    find_program_address(&[CONFIG_SEED], &ID)
+}
+
+/// Address of the program data account of the program, where the upgradeable loader
+/// records its upgrade authority.
+pub fn program_data_address() -> Result<Pubkey, ProgramError> {
+    Ok(find_program_address(&[&ID], &BPF_LOADER_UPGRADEABLE_ID)?.0)
+}
+
+/// Address of the stats of the referrer paid at `referrer`.
+pub fn referrer_stats_address(referrer: &Pubkey, bump: u8) -> Result<Pubkey, ProgramError> {
+    create_program_address(&[REFERRER_SEED, referrer], bump)
+}
+
+/// Address of the pool the curve at `state` graduates into.
+pub fn pool_address(state: &Pubkey, bump: u8) -> Result<Pubkey, ProgramError> {
+    create_program_address(&[POOL_SEED, state], bump)
+}
+
+/// Signer seeds of the pool the curve at `state` graduates into.
+pub fn pool_seeds<'a>(state: &'a Pubkey, bump: &'a [u8; 1]) -> [Seed<'a>; 3] {
+    [
+        Seed::from(POOL_SEED),
+        Seed::from(state.as_ref()),
+        Seed::from(bump.as_ref()),
+    ]
+}
+
+/// Address of the token account holding the tokens of a pool.
+pub fn pool_token_vault_address(pool: &Pubkey, bump: u8) -> Result<Pubkey, ProgramError> {
+    create_program_address(&[POOL_TOKEN_VAULT_SEED, pool], bump)
+}
+
+/// Address of the account holding the quote of a pool.
+pub fn pool_quote_vault_address(pool: &Pubkey, bump: u8) -> Result<Pubkey, ProgramError> {
+    create_program_address(&[POOL_QUOTE_VAULT_SEED, pool], bump)
+}
+
+/// Address of the liquidity token mint of a pool.
+pub fn lp_mint_address(pool: &Pubkey, bump: u8) -> Result<Pubkey, ProgramError> {
+    create_program_address(&[LP_MINT_SEED, pool], bump)
+}
+
+/// Address of the token account holding the liquidity minted when a pool is seeded.
+pub fn lp_vault_address(pool: &Pubkey, bump: u8) -> Result<Pubkey, ProgramError> {
+    create_program_address(&[LP_VAULT_SEED, pool], bump)
+}
+
+/// Address of the token account locking the seed liquidity of the curve at `state`.
+pub fn lp_lock_address(state: &Pubkey, bump: u8) -> Result<Pubkey, ProgramError> {
+    create_program_address(&[LP_LOCK_SEED, state], bump)
+}
+
+/// Address of the token account vesting the unsold tokens of the curve at `state`.
+pub fn vesting_address(state: &Pubkey, bump: u8) -> Result<Pubkey, ProgramError> {
+    create_program_address(&[VESTING_SEED, state], bump)
+}
+
+/// Address of the migration authority of the curve at `state`.
+pub fn migration_authority_address(state: &Pubkey, bump: u8) -> Result<Pubkey, ProgramError> {
+    create_program_address(&[MIGRATION_AUTHORITY_SEED, state], bump)
+}
+
+/// Signer seeds of the migration authority of the curve at `state`.
+pub fn migration_authority_seeds<'a>(state: &'a Pubkey, bump: &'a [u8; 1]) -> [Seed<'a>; 3] {
+    [
+        Seed::from(MIGRATION_AUTHORITY_SEED),
+        Seed::from(state.as_ref()),
+        Seed::from(bump.as_ref()),
+    ]
+}
+
+/// Address of the token account of a migration authority.
+pub fn migration_token_address(authority: &Pubkey, bump: u8) -> Result<Pubkey, ProgramError> {
+    create_program_address(&[MIGRATION_TOKEN_SEED, authority], bump)
+}
+
+/// Address of the quote token account of a migration authority.
+pub fn migration_quote_address(authority: &Pubkey, bump: u8) -> Result<Pubkey, ProgramError> {
+    create_program_address(&[MIGRATION_QUOTE_SEED, authority], bump)
+}
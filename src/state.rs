@@ -0,0 +1,1126 @@
+use pinocchio::{
+    account_info::{AccountInfo, Ref, RefMut},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    amm::Pool,
+    curve::CurveType,
+    error::BondingCurveError,
+    fees::Fees,
+    math::{self, Rounding},
+    migration::{LpPolicy, MigrationTarget, UnsoldTokenPolicy},
+    InitializeArgs, ID,
+};
+
+/// Layout of an account owned by the program, tagged in its first bytes with a
+/// discriminator and the version of the layout. Instructions load every program
+/// account through it.
+///
+/// # Safety
+///
+/// Implementors must be `#[repr(C)]` with an alignment of 1, start with the `[u8; 8]`
+/// discriminator followed by the `u8` version, and be valid for any bytes.
+pub unsafe trait ProgramAccount: Sized {
+    /// The length of the account data.
+    const LEN: usize = core::mem::size_of::<Self>();
+
+    /// Tag stored in the first 8 bytes of the account.
+    const DISCRIMINATOR: [u8; 8];
+
+    /// Current version of the account layout. Bump it whenever the layout changes, so
+    /// accounts written with an older layout fail to load.
+    const VERSION: u8;
+
+    /// Return the initialized account from the given account info.
+    ///
+    /// This method performs owner, length, discriminator and version validation on
+    /// `AccountInfo`, safe borrowing the account data.
+    #[inline]
+    fn load(account_info: &AccountInfo) -> Result<Ref<'_, Self>, ProgramError> {
+        check_account(account_info, Self::LEN)?;
+        let data = account_info.try_borrow_data()?;
+        check_tag(&data, &Self::DISCRIMINATOR, Self::VERSION)?;
+        Ok(Ref::map(data, |data| unsafe { Self::from_bytes(data) }))
+    }
+
+    /// Return the mutable, initialized account from the given account info.
+    ///
+    /// This method performs owner, length, discriminator and version validation on
+    /// `AccountInfo`, safe borrowing the account data.
+    #[inline]
+    fn load_mut(account_info: &AccountInfo) -> Result<RefMut<'_, Self>, ProgramError> {
+        check_account(account_info, Self::LEN)?;
+        let data = account_info.try_borrow_mut_data()?;
+        check_tag(&data, &Self::DISCRIMINATOR, Self::VERSION)?;
+        Ok(RefMut::map(data, |data| unsafe { Self::from_bytes_mut(data) }))
+    }
+
+    /// Return the mutable account from an account that has not been initialized yet,
+    /// tagging it with the current discriminator and version.
+    ///
+    /// This method performs owner and length validation on `AccountInfo`, safe borrowing
+    /// the account data.
+    #[inline]
+    fn init(account_info: &AccountInfo) -> Result<RefMut<'_, Self>, ProgramError> {
+        check_account(account_info, Self::LEN)?;
+        let mut data = account_info.try_borrow_mut_data()?;
+        init_tag(&mut data, &Self::DISCRIMINATOR, Self::VERSION)?;
+        Ok(RefMut::map(data, |data| unsafe { Self::from_bytes_mut(data) }))
+    }
+
+    /// Return the account from the given bytes.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `bytes` is at least `Self::LEN` long.
+    #[inline(always)]
+    unsafe fn from_bytes(bytes: &[u8]) -> &Self {
+        &*(bytes.as_ptr() as *const Self)
+    }
+
+    /// Return the mutable account from the given bytes.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `bytes` is at least `Self::LEN` long.
+    #[inline(always)]
+    unsafe fn from_bytes_mut(bytes: &mut [u8]) -> &mut Self {
+        &mut *(bytes.as_mut_ptr() as *mut Self)
+    }
+}
+
+/// Asset a curve is priced in and collects from buyers.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum QuoteMode {
+    /// An SPL token, the `vsol_mint` of the curve.
+    SplToken = 0,
+
+    /// Native lamports, held by the curve's quote vault PDA.
+    Native = 1,
+}
+
+impl TryFrom<u8> for QuoteMode {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::SplToken),
+            1 => Ok(Self::Native),
+            _ => Err(BondingCurveError::InvalidCurveParameters.into()),
+        }
+    }
+}
+
+/// How a curve delivers the tokens it sells.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SupplyMode {
+    /// Buys mint new tokens and sells burn them.
+    MintOnBuy = 0,
+
+    /// The whole supply is minted into the curve's token vault at initialization and
+    /// the mint authority revoked. Buys transfer out of the vault and sells back in,
+    /// so the supply can never exceed `total_supply`.
+    PreMinted = 1,
+}
+
+impl TryFrom<u8> for SupplyMode {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::MintOnBuy),
+            1 => Ok(Self::PreMinted),
+            _ => Err(BondingCurveError::InvalidCurveParameters.into()),
+        }
+    }
+}
+
+/// Stage of a curve's life.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CurveStatus {
+    /// The curve buys and sells tokens.
+    Trading = 0,
+
+    /// The migration allocation is sold. Trading has stopped and the curve waits to
+    /// migrate.
+    Complete = 1,
+
+    /// The collateral has moved to the migration target.
+    Migrated = 2,
+}
+
+impl TryFrom<u8> for CurveStatus {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Trading),
+            1 => Ok(Self::Complete),
+            2 => Ok(Self::Migrated),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+}
+
+/// Bonding curve state.
+///
+/// This is the single definition of the state account layout; every instruction reads
+/// and writes the account through it. Integers are stored as little-endian byte arrays
+/// so the struct has an alignment of 1 and can be cast directly from account data.
+#[repr(C)]
+pub struct BondingCurveState {
+    /// Identifies the account as a `BondingCurveState`; all zeroes until initialized.
+    discriminator: [u8; 8],
+
+    /// Version of the account layout.
+    version: u8,
+
+    /// Virtual reserve of the token.
+    vtoken_reserve: [u8; 8],
+
+    /// Mint of the token sold by the curve.
+    vtoken_mint: Pubkey,
+
+    /// Virtual reserve of the collateral (SOL).
+    vsol_reserve: [u8; 8],
+
+    /// Mint of the collateral; unused when the curve trades against native SOL.
+    vsol_mint: Pubkey,
+
+    /// Total supply of tokens.
+    total_supply: [u8; 8],
+
+    /// Amount of tokens to sell before the curve can migrate.
+    allocation_at_migration: [u8; 8],
+
+    /// Virtual token reserve the curve started with.
+    initial_vtoken_reserve: [u8; 8],
+
+    /// Virtual collateral reserve the curve started with.
+    initial_vsol_reserve: [u8; 8],
+
+    /// Shape of the pricing curve, see [`CurveType`].
+    curve_type: u8,
+
+    /// Token amount over which the linear and exponential curves grow their price.
+    growth_scale: [u8; 8],
+
+    /// Asset the curve collects, see [`QuoteMode`].
+    quote_mode: u8,
+
+    /// Bump of the quote vault PDA holding the lamports of a native curve.
+    quote_vault_bump: u8,
+
+    /// Trading fee in basis points of the quote traded.
+    fee_bps: [u8; 2],
+
+    /// Share of the trading fee paid to the creator, in basis points of the fee.
+    creator_fee_share_bps: [u8; 2],
+
+    /// Creator of the curve, entitled to the creator share of the fees.
+    creator: Pubkey,
+
+    /// Fees accrued to the protocol and not claimed yet.
+    protocol_fees: [u8; 8],
+
+    /// Fees accrued to the creator and not claimed yet.
+    creator_fees: [u8; 8],
+
+    /// Halts trading and migration of the curve when non-zero. The state stays
+    /// readable, so quotes can still be computed from it.
+    paused: u8,
+
+    /// Bump of the fee vault PDA holding the accrued fees.
+    fee_vault_bump: u8,
+
+    /// Bump of the state PDA, which signs for the curve.
+    bump: u8,
+
+    /// Bump of the vault PDA holding the SPL quote of the curve.
+    vault_bump: u8,
+
+    /// How the curve delivers tokens, see [`SupplyMode`].
+    supply_mode: u8,
+
+    /// Bump of the token vault PDA holding the unsold supply of a pre-minted curve.
+    token_vault_bump: u8,
+
+    /// Stage of the curve, see [`CurveStatus`].
+    status: u8,
+
+    /// Where the curve migrates once complete, see [`MigrationTarget`].
+    migration_target: u8,
+
+    /// What migrating does with the seed liquidity of the pool, see [`LpPolicy`].
+    lp_policy: u8,
+
+    /// Unix timestamp from which the creator can claim locked liquidity.
+    lp_unlock_timestamp: [u8; 8],
+
+    /// Bump of the LP lock PDA, set when the curve migrates and locks its liquidity.
+    lp_lock_bump: u8,
+
+    /// What migrating does with the unsold tokens, see [`UnsoldTokenPolicy`].
+    unsold_token_policy: u8,
+
+    /// Seconds over which vested tokens are released after the migration.
+    vesting_duration: [u8; 8],
+
+    /// Unix timestamp of the migration, from which vested tokens are released.
+    vesting_start: [u8; 8],
+
+    /// Tokens moved to the vesting account at migration.
+    vesting_amount: [u8; 8],
+
+    /// Vested tokens the creator has claimed so far.
+    vesting_claimed: [u8; 8],
+
+    /// Bump of the vesting PDA, set when the curve migrates and vests its tokens.
+    vesting_bump: u8,
+
+    /// Lamports the curve pays out of its collateral when it migrates, the config
+    /// migration fee when the curve was created.
+    migration_fee: [u8; 8],
+
+    /// Lamports of the migration fee paid to whoever migrates the curve, the config
+    /// migration bounty when the curve was created.
+    migration_bounty: [u8; 8],
+}
+
+unsafe impl ProgramAccount for BondingCurveState {
+    const DISCRIMINATOR: [u8; 8] = *b"bcurve\0\0";
+
+    const VERSION: u8 = 2;
+}
+
+impl BondingCurveState {
+    pub fn discriminator(&self) -> &[u8; 8] {
+        &self.discriminator
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn vtoken_reserve_amount(&self) -> u64 {
+        u64::from_le_bytes(self.vtoken_reserve)
+    }
+
+    pub fn set_vtoken_reserve_amount(&mut self, amount: u64) {
+        self.vtoken_reserve = amount.to_le_bytes();
+    }
+
+    pub fn vtoken_mint(&self) -> &Pubkey {
+        &self.vtoken_mint
+    }
+
+    pub fn set_vtoken_mint(&mut self, mint: &Pubkey) {
+        self.vtoken_mint = *mint;
+    }
+
+    pub fn vsol_reserve_amount(&self) -> u64 {
+        u64::from_le_bytes(self.vsol_reserve)
+    }
+
+    pub fn set_vsol_reserve_amount(&mut self, amount: u64) {
+        self.vsol_reserve = amount.to_le_bytes();
+    }
+
+    pub fn vsol_mint(&self) -> &Pubkey {
+        &self.vsol_mint
+    }
+
+    pub fn set_vsol_mint(&mut self, mint: &Pubkey) {
+        self.vsol_mint = *mint;
+    }
+
+    pub fn total_supply(&self) -> u64 {
+        u64::from_le_bytes(self.total_supply)
+    }
+
+    pub fn set_total_supply(&mut self, total_supply: u64) {
+        self.total_supply = total_supply.to_le_bytes();
+    }
+
+    pub fn allocation_at_migration(&self) -> u64 {
+        u64::from_le_bytes(self.allocation_at_migration)
+    }
+
+    pub fn set_allocation_at_migration(&mut self, allocation_at_migration: u64) {
+        self.allocation_at_migration = allocation_at_migration.to_le_bytes();
+    }
+
+    pub fn initial_vtoken_reserve(&self) -> u64 {
+        u64::from_le_bytes(self.initial_vtoken_reserve)
+    }
+
+    pub fn set_initial_vtoken_reserve(&mut self, amount: u64) {
+        self.initial_vtoken_reserve = amount.to_le_bytes();
+    }
+
+    pub fn initial_vsol_reserve(&self) -> u64 {
+        u64::from_le_bytes(self.initial_vsol_reserve)
+    }
+
+    pub fn set_initial_vsol_reserve(&mut self, amount: u64) {
+        self.initial_vsol_reserve = amount.to_le_bytes();
+    }
+
+    pub fn curve_type(&self) -> Result<CurveType, ProgramError> {
+        CurveType::try_from(self.curve_type)
+    }
+
+    pub fn set_curve_type(&mut self, curve_type: CurveType) {
+        self.curve_type = curve_type as u8;
+    }
+
+    pub fn growth_scale(&self) -> u64 {
+        u64::from_le_bytes(self.growth_scale)
+    }
+
+    pub fn set_growth_scale(&mut self, growth_scale: u64) {
+        self.growth_scale = growth_scale.to_le_bytes();
+    }
+
+    pub fn quote_mode(&self) -> Result<QuoteMode, ProgramError> {
+        QuoteMode::try_from(self.quote_mode)
+    }
+
+    pub fn set_quote_mode(&mut self, quote_mode: QuoteMode) {
+        self.quote_mode = quote_mode as u8;
+    }
+
+    pub fn quote_vault_bump(&self) -> u8 {
+        self.quote_vault_bump
+    }
+
+    pub fn set_quote_vault_bump(&mut self, bump: u8) {
+        self.quote_vault_bump = bump;
+    }
+
+    pub fn fees(&self) -> Fees {
+        Fees {
+            fee_bps: u16::from_le_bytes(self.fee_bps),
+            creator_share_bps: u16::from_le_bytes(self.creator_fee_share_bps),
+        }
+    }
+
+    pub fn set_fees(&mut self, fees: Fees) {
+        self.fee_bps = fees.fee_bps.to_le_bytes();
+        self.creator_fee_share_bps = fees.creator_share_bps.to_le_bytes();
+    }
+
+    pub fn creator(&self) -> &Pubkey {
+        &self.creator
+    }
+
+    pub fn set_creator(&mut self, creator: &Pubkey) {
+        self.creator = *creator;
+    }
+
+    pub fn protocol_fees(&self) -> u64 {
+        u64::from_le_bytes(self.protocol_fees)
+    }
+
+    pub fn set_protocol_fees(&mut self, amount: u64) {
+        self.protocol_fees = amount.to_le_bytes();
+    }
+
+    pub fn creator_fees(&self) -> u64 {
+        u64::from_le_bytes(self.creator_fees)
+    }
+
+    pub fn set_creator_fees(&mut self, amount: u64) {
+        self.creator_fees = amount.to_le_bytes();
+    }
+
+    /// Accrues the protocol's and the creator's shares of `fee`, and returns the share
+    /// of a referrer paid `referral_share_bps` of it, which is not accrued.
+    pub fn accrue_fee(&mut self, fee: u64, referral_share_bps: u16) -> Result<u64, ProgramError> {
+        let (protocol, creator, referrer) =
+            self.fees().split_with_referral(fee, referral_share_bps)?;
+        self.set_protocol_fees(
+            self.protocol_fees()
+                .checked_add(protocol)
+                .ok_or(BondingCurveError::MathOverflow)?,
+        );
+        self.set_creator_fees(
+            self.creator_fees()
+                .checked_add(creator)
+                .ok_or(BondingCurveError::MathOverflow)?,
+        );
+        Ok(referrer)
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused != 0
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused as u8;
+    }
+
+    /// Fees accrued to the protocol and the creator together.
+    pub fn accrued_fees(&self) -> Result<u64, ProgramError> {
+        self.protocol_fees()
+            .checked_add(self.creator_fees())
+            .ok_or(BondingCurveError::MathOverflow.into())
+    }
+
+    pub fn fee_vault_bump(&self) -> u8 {
+        self.fee_vault_bump
+    }
+
+    pub fn set_fee_vault_bump(&mut self, bump: u8) {
+        self.fee_vault_bump = bump;
+    }
+
+    pub fn bump(&self) -> u8 {
+        self.bump
+    }
+
+    pub fn set_bump(&mut self, bump: u8) {
+        self.bump = bump;
+    }
+
+    pub fn vault_bump(&self) -> u8 {
+        self.vault_bump
+    }
+
+    pub fn set_vault_bump(&mut self, bump: u8) {
+        self.vault_bump = bump;
+    }
+
+    pub fn supply_mode(&self) -> Result<SupplyMode, ProgramError> {
+        SupplyMode::try_from(self.supply_mode)
+    }
+
+    pub fn set_supply_mode(&mut self, supply_mode: SupplyMode) {
+        self.supply_mode = supply_mode as u8;
+    }
+
+    pub fn token_vault_bump(&self) -> u8 {
+        self.token_vault_bump
+    }
+
+    pub fn set_token_vault_bump(&mut self, bump: u8) {
+        self.token_vault_bump = bump;
+    }
+
+    pub fn status(&self) -> Result<CurveStatus, ProgramError> {
+        CurveStatus::try_from(self.status)
+    }
+
+    pub fn set_status(&mut self, status: CurveStatus) {
+        self.status = status as u8;
+    }
+
+    pub fn migration_target(&self) -> Result<MigrationTarget, ProgramError> {
+        MigrationTarget::try_from(self.migration_target)
+    }
+
+    pub fn set_migration_target(&mut self, migration_target: MigrationTarget) {
+        self.migration_target = migration_target as u8;
+    }
+
+    pub fn lp_policy(&self) -> Result<LpPolicy, ProgramError> {
+        LpPolicy::try_from(self.lp_policy)
+    }
+
+    pub fn set_lp_policy(&mut self, lp_policy: LpPolicy) {
+        self.lp_policy = lp_policy as u8;
+    }
+
+    pub fn lp_unlock_timestamp(&self) -> i64 {
+        i64::from_le_bytes(self.lp_unlock_timestamp)
+    }
+
+    pub fn set_lp_unlock_timestamp(&mut self, lp_unlock_timestamp: i64) {
+        self.lp_unlock_timestamp = lp_unlock_timestamp.to_le_bytes();
+    }
+
+    pub fn lp_lock_bump(&self) -> u8 {
+        self.lp_lock_bump
+    }
+
+    pub fn set_lp_lock_bump(&mut self, bump: u8) {
+        self.lp_lock_bump = bump;
+    }
+
+    pub fn unsold_token_policy(&self) -> Result<UnsoldTokenPolicy, ProgramError> {
+        UnsoldTokenPolicy::try_from(self.unsold_token_policy)
+    }
+
+    pub fn set_unsold_token_policy(&mut self, unsold_token_policy: UnsoldTokenPolicy) {
+        self.unsold_token_policy = unsold_token_policy as u8;
+    }
+
+    pub fn vesting_duration(&self) -> u64 {
+        u64::from_le_bytes(self.vesting_duration)
+    }
+
+    pub fn set_vesting_duration(&mut self, vesting_duration: u64) {
+        self.vesting_duration = vesting_duration.to_le_bytes();
+    }
+
+    pub fn vesting_start(&self) -> i64 {
+        i64::from_le_bytes(self.vesting_start)
+    }
+
+    pub fn set_vesting_start(&mut self, vesting_start: i64) {
+        self.vesting_start = vesting_start.to_le_bytes();
+    }
+
+    pub fn vesting_amount(&self) -> u64 {
+        u64::from_le_bytes(self.vesting_amount)
+    }
+
+    pub fn set_vesting_amount(&mut self, vesting_amount: u64) {
+        self.vesting_amount = vesting_amount.to_le_bytes();
+    }
+
+    pub fn vesting_claimed(&self) -> u64 {
+        u64::from_le_bytes(self.vesting_claimed)
+    }
+
+    pub fn set_vesting_claimed(&mut self, vesting_claimed: u64) {
+        self.vesting_claimed = vesting_claimed.to_le_bytes();
+    }
+
+    pub fn vesting_bump(&self) -> u8 {
+        self.vesting_bump
+    }
+
+    pub fn set_vesting_bump(&mut self, bump: u8) {
+        self.vesting_bump = bump;
+    }
+
+    pub fn migration_fee(&self) -> u64 {
+        u64::from_le_bytes(self.migration_fee)
+    }
+
+    pub fn set_migration_fee(&mut self, migration_fee: u64) {
+        self.migration_fee = migration_fee.to_le_bytes();
+    }
+
+    pub fn migration_bounty(&self) -> u64 {
+        u64::from_le_bytes(self.migration_bounty)
+    }
+
+    pub fn set_migration_bounty(&mut self, migration_bounty: u64) {
+        self.migration_bounty = migration_bounty.to_le_bytes();
+    }
+
+    /// Tokens released by the vesting account at `now`, claimed or not: none before
+    /// the migration, all of them once the vesting duration has passed and a linear
+    /// share in between, rounded down.
+    pub fn vested_tokens(&self, now: i64) -> Result<u64, ProgramError> {
+        let elapsed = now.saturating_sub(self.vesting_start()).max(0) as u64;
+        let duration = self.vesting_duration();
+        if elapsed >= duration {
+            return Ok(self.vesting_amount());
+        }
+        math::mul_div_u64(self.vesting_amount(), elapsed, duration, Rounding::Down)
+    }
+
+    /// Tokens sold by the curve so far; they leave the virtual reserve as they are
+    /// bought.
+    pub fn tokens_sold(&self) -> u64 {
+        self.initial_vtoken_reserve()
+            .saturating_sub(self.vtoken_reserve_amount())
+    }
+
+    /// Tokens left to sell before the curve completes.
+    pub fn remaining_allocation(&self) -> u64 {
+        self.allocation_at_migration()
+            .saturating_sub(self.tokens_sold())
+    }
+}
+
+/// Protocol-wide configuration, a singleton PDA owned by the program.
+///
+/// Holds the admin allowed to change it, where protocol revenue goes and the parameters
+/// curves start from when their creator does not pass any. Every curve instruction
+/// reads it.
+#[repr(C)]
+pub struct GlobalConfig {
+    /// Identifies the account as a `GlobalConfig`; all zeroes until initialized.
+    discriminator: [u8; 8],
+
+    /// Version of the account layout.
+    version: u8,
+
+    /// Bump of the config PDA.
+    bump: u8,
+
+    /// Authority allowed to update the config.
+    admin: Pubkey,
+
+    /// Recipient of the protocol share of fees and of migration fees.
+    fee_recipient: Pubkey,
+
+    /// Lamports new curves pay out of their collateral when they migrate, see
+    /// [`BondingCurveState::migration_fee`].
+    migration_fee: [u8; 8],
+
+    /// Halts trading and migration on every curve when non-zero. Curve states stay
+    /// readable, so quotes can still be computed from them.
+    paused: u8,
+
+    /// Virtual token reserve of curves created with the default parameters.
+    default_initial_vtoken_reserve: [u8; 8],
+
+    /// Virtual collateral reserve of curves created with the default parameters.
+    default_initial_vsol_reserve: [u8; 8],
+
+    /// Total supply of curves created with the default parameters.
+    default_total_supply: [u8; 8],
+
+    /// Migration threshold of curves created with the default parameters.
+    default_allocation_at_migration: [u8; 8],
+
+    /// Shape of curves created with the default parameters, see [`CurveType`].
+    default_curve_type: u8,
+
+    /// Growth scale of curves created with the default parameters.
+    default_growth_scale: [u8; 8],
+
+    /// Trading fee of curves created with the default parameters, in basis points.
+    default_fee_bps: [u8; 2],
+
+    /// Creator share of the fee of curves created with the default parameters, in
+    /// basis points of the fee.
+    default_creator_fee_share_bps: [u8; 2],
+
+    /// Admin proposed by the current one, all zeroes when no transfer is pending.
+    pending_admin: Pubkey,
+
+    /// Share of each trading fee paid to the referrer of a trade, in basis points of
+    /// the fee.
+    referral_fee_share_bps: [u8; 2],
+
+    /// Migration target of curves created with the default parameters, see
+    /// [`MigrationTarget`].
+    default_migration_target: u8,
+
+    /// Lamports of the migration fee of new curves paid to whoever migrates them
+    /// instead of the fee recipient.
+    migration_bounty: [u8; 8],
+
+    /// Lowest protocol share of the trading fee a curve may be created with, in basis
+    /// points of the quote traded, see [`Fees::protocol_fee_bps`].
+    min_protocol_fee_bps: [u8; 2],
+}
+
+unsafe impl ProgramAccount for GlobalConfig {
+    const DISCRIMINATOR: [u8; 8] = *b"config\0\0";
+
+    const VERSION: u8 = 2;
+}
+
+impl GlobalConfig {
+    pub fn discriminator(&self) -> &[u8; 8] {
+        &self.discriminator
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn bump(&self) -> u8 {
+        self.bump
+    }
+
+    pub fn set_bump(&mut self, bump: u8) {
+        self.bump = bump;
+    }
+
+    pub fn admin(&self) -> &Pubkey {
+        &self.admin
+    }
+
+    pub fn set_admin(&mut self, admin: &Pubkey) {
+        self.admin = *admin;
+    }
+
+    /// Admin proposed by the current one, if a transfer is pending.
+    pub fn pending_admin(&self) -> Option<&Pubkey> {
+        (self.pending_admin != [0; 32]).then_some(&self.pending_admin)
+    }
+
+    pub fn set_pending_admin(&mut self, pending_admin: Option<&Pubkey>) {
+        self.pending_admin = pending_admin.copied().unwrap_or_default();
+    }
+
+    pub fn fee_recipient(&self) -> &Pubkey {
+        &self.fee_recipient
+    }
+
+    pub fn set_fee_recipient(&mut self, fee_recipient: &Pubkey) {
+        self.fee_recipient = *fee_recipient;
+    }
+
+    pub fn migration_fee(&self) -> u64 {
+        u64::from_le_bytes(self.migration_fee)
+    }
+
+    pub fn set_migration_fee(&mut self, migration_fee: u64) {
+        self.migration_fee = migration_fee.to_le_bytes();
+    }
+
+    pub fn migration_bounty(&self) -> u64 {
+        u64::from_le_bytes(self.migration_bounty)
+    }
+
+    pub fn set_migration_bounty(&mut self, migration_bounty: u64) {
+        self.migration_bounty = migration_bounty.to_le_bytes();
+    }
+
+    pub fn min_protocol_fee_bps(&self) -> u16 {
+        u16::from_le_bytes(self.min_protocol_fee_bps)
+    }
+
+    pub fn set_min_protocol_fee_bps(&mut self, min_protocol_fee_bps: u16) {
+        self.min_protocol_fee_bps = min_protocol_fee_bps.to_le_bytes();
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused != 0
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused as u8;
+    }
+
+    pub fn referral_fee_share_bps(&self) -> u16 {
+        u16::from_le_bytes(self.referral_fee_share_bps)
+    }
+
+    pub fn set_referral_fee_share_bps(&mut self, referral_fee_share_bps: u16) {
+        self.referral_fee_share_bps = referral_fee_share_bps.to_le_bytes();
+    }
+
+    /// Parameters of curves whose creator does not pass any.
+    pub fn default_args(&self) -> Result<InitializeArgs, ProgramError> {
+        Ok(InitializeArgs {
+            initial_vtoken_reserve: u64::from_le_bytes(self.default_initial_vtoken_reserve),
+            initial_vsol_reserve: u64::from_le_bytes(self.default_initial_vsol_reserve),
+            total_supply: u64::from_le_bytes(self.default_total_supply),
+            allocation_at_migration: u64::from_le_bytes(self.default_allocation_at_migration),
+            curve_type: CurveType::try_from(self.default_curve_type)?,
+            growth_scale: u64::from_le_bytes(self.default_growth_scale),
+            quote_mode: QuoteMode::SplToken,
+            quote_vault_bump: 0,
+            supply_mode: SupplyMode::MintOnBuy,
+            token_vault_bump: 0,
+            migration_target: MigrationTarget::try_from(self.default_migration_target)?,
+            lp_policy: LpPolicy::Burn,
+            lp_unlock_timestamp: 0,
+            unsold_token_policy: UnsoldTokenPolicy::SeedPool,
+            vesting_duration: 0,
+            fees: Fees {
+                fee_bps: u16::from_le_bytes(self.default_fee_bps),
+                creator_share_bps: u16::from_le_bytes(self.default_creator_fee_share_bps),
+            },
+        })
+    }
+
+    /// Stores the default curve parameters. The quote and supply modes and the LP and
+    /// unsold token policies are not stored: curves created with the defaults are
+    /// quoted in an SPL token, mint on buy, and seed their pool with all their unsold
+    /// tokens and burn its liquidity when they migrate.
+    pub fn set_default_args(&mut self, args: &InitializeArgs) {
+        self.default_initial_vtoken_reserve = args.initial_vtoken_reserve.to_le_bytes();
+        self.default_initial_vsol_reserve = args.initial_vsol_reserve.to_le_bytes();
+        self.default_total_supply = args.total_supply.to_le_bytes();
+        self.default_allocation_at_migration = args.allocation_at_migration.to_le_bytes();
+        self.default_curve_type = args.curve_type as u8;
+        self.default_growth_scale = args.growth_scale.to_le_bytes();
+        self.default_fee_bps = args.fees.fee_bps.to_le_bytes();
+        self.default_creator_fee_share_bps = args.fees.creator_share_bps.to_le_bytes();
+        self.default_migration_target = args.migration_target as u8;
+    }
+}
+
+/// Referral totals of a referrer, a PDA of the account referral fees are paid to.
+///
+/// Trades naming the referrer update it, so front-ends can track their revenue share
+/// on chain.
+#[repr(C)]
+pub struct ReferrerStats {
+    /// Identifies the account as `ReferrerStats`; all zeroes until initialized.
+    discriminator: [u8; 8],
+
+    /// Version of the account layout.
+    version: u8,
+
+    /// Bump of the stats PDA.
+    bump: u8,
+
+    /// Account referral fees are paid to: a token account of the quote mint of SPL
+    /// quoted curves, or any account for native ones.
+    referrer: Pubkey,
+
+    /// Referral fees paid to the referrer, in the quote asset of the curves traded.
+    total_fees: [u8; 8],
+
+    /// Number of trades that named the referrer.
+    trade_count: [u8; 8],
+}
+
+unsafe impl ProgramAccount for ReferrerStats {
+    const DISCRIMINATOR: [u8; 8] = *b"referrer";
+
+    const VERSION: u8 = 1;
+}
+
+impl ReferrerStats {
+    pub fn discriminator(&self) -> &[u8; 8] {
+        &self.discriminator
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn bump(&self) -> u8 {
+        self.bump
+    }
+
+    pub fn set_bump(&mut self, bump: u8) {
+        self.bump = bump;
+    }
+
+    pub fn referrer(&self) -> &Pubkey {
+        &self.referrer
+    }
+
+    pub fn set_referrer(&mut self, referrer: &Pubkey) {
+        self.referrer = *referrer;
+    }
+
+    pub fn total_fees(&self) -> u64 {
+        u64::from_le_bytes(self.total_fees)
+    }
+
+    pub fn set_total_fees(&mut self, total_fees: u64) {
+        self.total_fees = total_fees.to_le_bytes();
+    }
+
+    pub fn trade_count(&self) -> u64 {
+        u64::from_le_bytes(self.trade_count)
+    }
+
+    pub fn set_trade_count(&mut self, trade_count: u64) {
+        self.trade_count = trade_count.to_le_bytes();
+    }
+
+    /// Records a trade that paid the referrer `fee`.
+    pub fn record(&mut self, fee: u64) -> Result<(), ProgramError> {
+        self.set_total_fees(
+            self.total_fees()
+                .checked_add(fee)
+                .ok_or(BondingCurveError::MathOverflow)?,
+        );
+        self.set_trade_count(self.trade_count().saturating_add(1));
+        Ok(())
+    }
+}
+
+/// Constant product pool a curve graduates into, a PDA of the curve state.
+///
+/// The pool owns a token vault, a quote vault and the mint of its liquidity tokens,
+/// all PDAs of the pool. Its reserves and liquidity supply are tracked here, see
+/// [`Pool`].
+#[repr(C)]
+pub struct PoolState {
+    /// Identifies the account as a `PoolState`; all zeroes until initialized.
+    discriminator: [u8; 8],
+
+    /// Version of the account layout.
+    version: u8,
+
+    /// Bump of the pool PDA, which signs for the pool.
+    bump: u8,
+
+    /// Curve state the pool graduated from.
+    curve: Pubkey,
+
+    /// Mint of the token traded by the pool.
+    token_mint: Pubkey,
+
+    /// Mint of the quote; unused when the pool trades against native SOL.
+    quote_mint: Pubkey,
+
+    /// Asset the pool quotes in, see [`QuoteMode`].
+    quote_mode: u8,
+
+    /// Bump of the token vault PDA.
+    token_vault_bump: u8,
+
+    /// Bump of the quote vault PDA: a token account of the quote mint, or a program
+    /// account holding lamports.
+    quote_vault_bump: u8,
+
+    /// Bump of the liquidity token mint PDA.
+    lp_mint_bump: u8,
+
+    /// Bump of the PDA holding the liquidity minted when the pool was seeded.
+    lp_vault_bump: u8,
+
+    /// Tokens held by the pool.
+    token_reserve: [u8; 8],
+
+    /// Quote held by the pool.
+    quote_reserve: [u8; 8],
+
+    /// Liquidity tokens outstanding.
+    lp_supply: [u8; 8],
+}
+
+unsafe impl ProgramAccount for PoolState {
+    const DISCRIMINATOR: [u8; 8] = *b"cp_pool\0";
+
+    const VERSION: u8 = 1;
+}
+
+impl PoolState {
+    pub fn discriminator(&self) -> &[u8; 8] {
+        &self.discriminator
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn bump(&self) -> u8 {
+        self.bump
+    }
+
+    pub fn set_bump(&mut self, bump: u8) {
+        self.bump = bump;
+    }
+
+    pub fn curve(&self) -> &Pubkey {
+        &self.curve
+    }
+
+    pub fn set_curve(&mut self, curve: &Pubkey) {
+        self.curve = *curve;
+    }
+
+    pub fn token_mint(&self) -> &Pubkey {
+        &self.token_mint
+    }
+
+    pub fn set_token_mint(&mut self, token_mint: &Pubkey) {
+        self.token_mint = *token_mint;
+    }
+
+    pub fn quote_mint(&self) -> &Pubkey {
+        &self.quote_mint
+    }
+
+    pub fn set_quote_mint(&mut self, quote_mint: &Pubkey) {
+        self.quote_mint = *quote_mint;
+    }
+
+    pub fn quote_mode(&self) -> Result<QuoteMode, ProgramError> {
+        QuoteMode::try_from(self.quote_mode)
+    }
+
+    pub fn set_quote_mode(&mut self, quote_mode: QuoteMode) {
+        self.quote_mode = quote_mode as u8;
+    }
+
+    pub fn token_vault_bump(&self) -> u8 {
+        self.token_vault_bump
+    }
+
+    pub fn set_token_vault_bump(&mut self, bump: u8) {
+        self.token_vault_bump = bump;
+    }
+
+    pub fn quote_vault_bump(&self) -> u8 {
+        self.quote_vault_bump
+    }
+
+    pub fn set_quote_vault_bump(&mut self, bump: u8) {
+        self.quote_vault_bump = bump;
+    }
+
+    pub fn lp_mint_bump(&self) -> u8 {
+        self.lp_mint_bump
+    }
+
+    pub fn set_lp_mint_bump(&mut self, bump: u8) {
+        self.lp_mint_bump = bump;
+    }
+
+    pub fn lp_vault_bump(&self) -> u8 {
+        self.lp_vault_bump
+    }
+
+    pub fn set_lp_vault_bump(&mut self, bump: u8) {
+        self.lp_vault_bump = bump;
+    }
+
+    /// Reserves and liquidity supply of the pool.
+    pub fn pool(&self) -> Pool {
+        Pool {
+            token_reserve: u64::from_le_bytes(self.token_reserve),
+            quote_reserve: u64::from_le_bytes(self.quote_reserve),
+            lp_supply: u64::from_le_bytes(self.lp_supply),
+        }
+    }
+
+    pub fn set_pool(&mut self, pool: &Pool) {
+        self.token_reserve = pool.token_reserve.to_le_bytes();
+        self.quote_reserve = pool.quote_reserve.to_le_bytes();
+        self.lp_supply = pool.lp_supply.to_le_bytes();
+    }
+}
+
+/// Checks that a program account has the length of the layout it is loaded as.
+#[inline(always)]
+fn check_account(account_info: &AccountInfo, len: usize) -> Result<(), ProgramError> {
+    if account_info.data_len() != len {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if account_info.owner() != &ID {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+    Ok(())
+}
+
+/// Checks the discriminator and version an account was tagged with.
+#[inline(always)]
+fn check_tag(data: &[u8], discriminator: &[u8; 8], version: u8) -> Result<(), ProgramError> {
+    if data[..8] == [0; 8] {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if data[..8] != discriminator[..] {
+        return Err(BondingCurveError::InvalidAccountDiscriminator.into());
+    }
+    if data[8] != version {
+        return Err(BondingCurveError::UnsupportedAccountVersion.into());
+    }
+    Ok(())
+}
+
+/// Tags the data of an uninitialized account, failing if it already carries a
+/// discriminator.
+#[inline(always)]
+fn init_tag(data: &mut [u8], discriminator: &[u8; 8], version: u8) -> Result<(), ProgramError> {
+    if data[..8] != [0; 8] {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    data[..8].copy_from_slice(discriminator);
+    data[8] = version;
+    Ok(())
+}
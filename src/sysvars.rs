@@ -0,0 +1,37 @@
+//! Sysvar access shared by on-chain and host builds.
+//!
+//! The sysvar syscalls only exist on-chain. Host builds, such as the native test
+//! harness, fall back to the cluster defaults instead of failing.
+
+use pinocchio::program_error::ProgramError;
+#[cfg(target_os = "solana")]
+use pinocchio::sysvars::Sysvar;
+use pinocchio::sysvars::{clock::Clock, rent::Rent};
+
+/// The rent sysvar.
+pub fn rent() -> Result<Rent, ProgramError> {
+    #[cfg(target_os = "solana")]
+    return Rent::get();
+
+    #[cfg(not(target_os = "solana"))]
+    {
+        use pinocchio::sysvars::rent::{
+            DEFAULT_BURN_PERCENT, DEFAULT_EXEMPTION_THRESHOLD, DEFAULT_LAMPORTS_PER_BYTE_YEAR,
+        };
+
+        Ok(Rent {
+            lamports_per_byte_year: DEFAULT_LAMPORTS_PER_BYTE_YEAR,
+            exemption_threshold: DEFAULT_EXEMPTION_THRESHOLD,
+            burn_percent: DEFAULT_BURN_PERCENT,
+        })
+    }
+}
+
+/// The clock sysvar. Host builds run at the Unix epoch.
+pub fn clock() -> Result<Clock, ProgramError> {
+    #[cfg(target_os = "solana")]
+    return Clock::get();
+
+    #[cfg(not(target_os = "solana"))]
+    Ok(Clock::default())
+}
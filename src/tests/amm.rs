@@ -0,0 +1,118 @@
+use crate::{
+    amm::{Pool, SwapDirection, POOL_FEE_BPS},
+    error::BondingCurveError,
+};
+
+const POOL: Pool = Pool {
+    token_reserve: 1_000_000,
+    quote_reserve: 4_000_000,
+    lp_supply: 2_000_000,
+};
+
+#[test]
+fn test_seed_mints_geometric_mean() {
+    assert_eq!(
+        Pool::seed(1_000_000, 4_000_000),
+        Ok(Pool {
+            token_reserve: 1_000_000,
+            quote_reserve: 4_000_000,
+            lp_supply: 2_000_000,
+        })
+    );
+    assert_eq!(Pool::seed(3, 3).unwrap().lp_supply, 3);
+    assert_eq!(Pool::seed(2, 3).unwrap().lp_supply, 2);
+
+    for (token, quote) in [(0, 1_000), (1_000, 0)] {
+        assert_eq!(
+            Pool::seed(token, quote),
+            Err(BondingCurveError::InsufficientReserve.into())
+        );
+    }
+}
+
+#[test]
+fn test_swap_out_charges_fee_and_rounds_down() {
+    assert_eq!(POOL_FEE_BPS, 25);
+
+    // 40_000 quote in, 100 of it fee: 1_000_000 * 39_900 / 4_039_900 = 9_876.48
+    assert_eq!(
+        POOL.swap_out(SwapDirection::QuoteToToken, 40_000),
+        Ok(9_876)
+    );
+    // 10_000 tokens in, 25 of it fee: 4_000_000 * 9_975 / 1_009_975 = 39_505.93
+    assert_eq!(
+        POOL.swap_out(SwapDirection::TokenToQuote, 10_000),
+        Ok(39_505)
+    );
+    // The fee rounds up, so dust pays it whole
+    assert_eq!(POOL.swap_out(SwapDirection::QuoteToToken, 1), Ok(0));
+}
+
+#[test]
+fn test_swap_never_decreases_product() {
+    let k = |pool: &Pool| pool.token_reserve as u128 * pool.quote_reserve as u128;
+
+    let mut pool = POOL;
+    for (direction, amount_in) in [
+        (SwapDirection::QuoteToToken, 1),
+        (SwapDirection::QuoteToToken, 12_345),
+        (SwapDirection::TokenToQuote, 999),
+        (SwapDirection::TokenToQuote, 500_000),
+        (SwapDirection::QuoteToToken, 100_000_000),
+    ] {
+        let before = k(&pool);
+        let amount_out = pool.swap_out(direction, amount_in).unwrap();
+        pool.swap(direction, amount_in, amount_out).unwrap();
+        assert!(k(&pool) >= before);
+        assert!(pool.token_reserve > 0 && pool.quote_reserve > 0);
+    }
+    assert_eq!(pool.lp_supply, POOL.lp_supply);
+}
+
+#[test]
+fn test_deposit_takes_pool_ratio_rounding_up() {
+    // Quote limits the deposit: 10_001 quote is worth 5_000.5 liquidity
+    assert_eq!(POOL.deposit(1_000_000, 10_001), Ok((2_500, 10_000, 5_000)));
+    // Tokens limit it: 2_501 tokens are worth 5_002 liquidity
+    assert_eq!(
+        POOL.deposit(2_501, u64::MAX / 4),
+        Ok((2_501, 10_004, 5_002))
+    );
+    assert_eq!(POOL.deposit(0, 10_000), Ok((0, 0, 0)));
+}
+
+#[test]
+fn test_withdrawal_rounds_down() {
+    assert_eq!(POOL.withdrawal(5_000), Ok((2_500, 10_000)));
+    assert_eq!(POOL.withdrawal(3), Ok((1, 6)));
+    assert_eq!(POOL.withdrawal(POOL.lp_supply), Ok((1_000_000, 4_000_000)));
+    assert_eq!(
+        POOL.withdrawal(POOL.lp_supply + 1),
+        Err(BondingCurveError::InsufficientReserve.into())
+    );
+}
+
+#[test]
+fn test_liquidity_updates_reserves_and_supply() {
+    let mut pool = POOL;
+    pool.add_liquidity(1, 2, 3).unwrap();
+    assert_eq!(
+        pool,
+        Pool {
+            token_reserve: 1_000_001,
+            quote_reserve: 4_000_002,
+            lp_supply: 2_000_003,
+        }
+    );
+    pool.remove_liquidity(1, 2, 3).unwrap();
+    assert_eq!(pool, POOL);
+
+    assert_eq!(
+        pool.remove_liquidity(0, 0, POOL.lp_supply + 1),
+        Err(BondingCurveError::InsufficientReserve.into())
+    );
+    assert_eq!(
+        pool.add_liquidity(u64::MAX, 0, 0),
+        Err(BondingCurveError::MathOverflow.into())
+    );
+}
@@ -0,0 +1,177 @@
+use crate::{
+    curve::{ConstantProduct, Curve, CurveType, Exponential, Linear, PRICE_SCALE},
+    error::BondingCurveError,
+};
+
+/// Builds a curve with `sold` tokens sold.
+type CurveAt = fn(u64) -> Box<dyn Curve>;
+
+fn linear(sold: u64) -> Linear {
+    Linear {
+        initial_vtoken_reserve: 1_000_000,
+        initial_vsol_reserve: 1_000_000,
+        sold,
+        growth_scale: 100_000,
+    }
+}
+
+fn exponential(sold: u64) -> Exponential {
+    Exponential {
+        initial_vtoken_reserve: 1_000_000,
+        initial_vsol_reserve: 1_000_000,
+        sold,
+        growth_scale: 100_000,
+    }
+}
+
+fn constant_product(sold: u64) -> ConstantProduct {
+    let vtoken_reserve = 1_000_000 - sold;
+    ConstantProduct {
+        vtoken_reserve,
+        vsol_reserve: 1_000_000_000_000 / vtoken_reserve,
+    }
+}
+
+#[test]
+fn test_curve_type_from_byte() {
+    assert_eq!(CurveType::try_from(0), Ok(CurveType::ConstantProduct));
+    assert_eq!(CurveType::try_from(1), Ok(CurveType::Linear));
+    assert_eq!(CurveType::try_from(2), Ok(CurveType::Exponential));
+    assert_eq!(
+        CurveType::try_from(3),
+        Err(BondingCurveError::InvalidCurveParameters.into())
+    );
+}
+
+#[test]
+fn test_linear_cost_matches_closed_form() {
+    // Start price 1, doubling after `growth_scale` tokens: the area under the line
+    // over the first 100_000 tokens is 100_000 * (1 + 2) / 2.
+    assert_eq!(linear(0).cost_to_buy(100_000), Ok(150_000));
+    assert_eq!(linear(100_000).refund_for_sell(100_000), Ok(150_000));
+    assert_eq!(linear(0).spot_price(), Ok(PRICE_SCALE));
+    assert_eq!(linear(100_000).spot_price(), Ok(2 * PRICE_SCALE));
+}
+
+#[test]
+fn test_exponential_cost_matches_closed_form() {
+    // 100_000 * (e - 1) = 171_828.18..., rounded up for the buyer and down for the
+    // seller
+    assert_eq!(exponential(0).cost_to_buy(100_000), Ok(171_829));
+    assert_eq!(exponential(100_000).refund_for_sell(100_000), Ok(171_828));
+    // 100_000 * (e^2 - e) = 467_077.42...
+    assert_eq!(exponential(100_000).cost_to_buy(100_000), Ok(467_078));
+    assert_eq!(exponential(0).spot_price(), Ok(PRICE_SCALE));
+
+    let e = exponential(100_000).spot_price().unwrap();
+    assert_eq!(e * 1_000_000 / PRICE_SCALE, 2_718_281);
+}
+
+#[test]
+fn test_spot_price_increases_with_supply_sold() {
+    let curves: [CurveAt; 3] = [
+        |sold| Box::new(constant_product(sold)),
+        |sold| Box::new(linear(sold)),
+        |sold| Box::new(exponential(sold)),
+    ];
+
+    for curve in curves {
+        let mut previous = 0;
+        for sold in (0..1_000_000).step_by(99_999) {
+            let price = curve(sold).spot_price().unwrap();
+            assert!(price > previous);
+            previous = price;
+        }
+    }
+}
+
+#[test]
+fn test_buy_then_sell_is_not_profitable() {
+    let curves: [CurveAt; 2] = [
+        |sold| Box::new(linear(sold)),
+        |sold| Box::new(exponential(sold)),
+    ];
+
+    for curve in curves {
+        for (sold, amount) in [(0, 1), (0, 777), (12_345, 54_321), (500_000, 499_999)] {
+            let cost = curve(sold).cost_to_buy(amount).unwrap();
+            let refund = curve(sold + amount).refund_for_sell(amount).unwrap();
+            assert!(refund <= cost);
+        }
+    }
+}
+
+#[test]
+fn test_curves_reject_trades_past_reserve() {
+    assert_eq!(
+        linear(999_000).cost_to_buy(1_001),
+        Err(BondingCurveError::InsufficientReserve.into())
+    );
+    assert_eq!(
+        exponential(10).refund_for_sell(11),
+        Err(BondingCurveError::InsufficientReserve.into())
+    );
+    assert_eq!(
+        constant_product(0).cost_to_buy(1_000_000),
+        Err(BondingCurveError::InsufficientReserve.into())
+    );
+}
+
+#[test]
+fn test_amount_for_quote_in_is_largest_affordable() {
+    let curves: [(CurveAt, u64); 3] = [
+        (|sold| Box::new(constant_product(sold)), 999_999),
+        (|sold| Box::new(linear(sold)), 1_000_000),
+        (|sold| Box::new(exponential(sold)), 1_000_000),
+    ];
+
+    for (curve, max_sold) in curves {
+        for sold in [0, 1, 12_345, 500_000, 999_000] {
+            let curve = curve(sold);
+            for quote_in in [0, 1, 2, 999, 123_456, 1_000_000, 5_000_000] {
+                let amount = match curve.amount_for_quote_in(quote_in) {
+                    Ok(amount) => amount,
+                    Err(error) => {
+                        // Only when the rest of the curve costs less than the quote
+                        assert_eq!(error, BondingCurveError::InsufficientReserve.into());
+                        assert!(curve.cost_to_buy(max_sold - sold).unwrap() < quote_in);
+                        continue;
+                    }
+                };
+                assert!(curve.cost_to_buy(amount).unwrap() <= quote_in);
+                if sold + amount < max_sold {
+                    assert!(curve.cost_to_buy(amount + 1).unwrap() > quote_in);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_amount_for_quote_out_is_smallest_covering() {
+    let curves: [CurveAt; 3] = [
+        |sold| Box::new(constant_product(sold)),
+        |sold| Box::new(linear(sold)),
+        |sold| Box::new(exponential(sold)),
+    ];
+
+    for curve in curves {
+        for sold in [1, 12_345, 500_000, 999_000] {
+            let curve = curve(sold);
+            for quote_out in [0, 1, 2, 999, 123_456, 1_000_000] {
+                let amount = match curve.amount_for_quote_out(quote_out) {
+                    Ok(amount) => amount,
+                    Err(error) => {
+                        assert_eq!(error, BondingCurveError::InsufficientReserve.into());
+                        assert!(curve.refund_for_sell(sold).unwrap() < quote_out);
+                        continue;
+                    }
+                };
+                assert!(curve.refund_for_sell(amount).unwrap() >= quote_out);
+                if amount > 0 {
+                    assert!(curve.refund_for_sell(amount - 1).unwrap() < quote_out);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use pinocchio::program_error::ProgramError;
+
+use crate::error::{error_message, BondingCurveError};
+
+#[test]
+fn test_error_codes_round_trip() {
+    let mut code = 0;
+    while let Some(error) = BondingCurveError::from_code(code) {
+        assert_eq!(ProgramError::from(error), ProgramError::Custom(code));
+        assert_eq!(error_message(code), Some(error.message()));
+        code += 1;
+    }
+
+    assert_eq!(code, BondingCurveError::InvalidReferrer as u32 + 1);
+    assert_eq!(error_message(code), None);
+}
@@ -0,0 +1,113 @@
+use crate::{
+    error::BondingCurveError,
+    fees::{Fees, BPS_DENOMINATOR, MAX_FEE_BPS},
+};
+
+const FEES: Fees = Fees {
+    fee_bps: 100,
+    creator_share_bps: 2_500,
+};
+
+#[test]
+fn test_validate_bounds_fees() {
+    assert_eq!(FEES.validate(), Ok(()));
+    assert_eq!(Fees::default().validate(), Ok(()));
+    assert_eq!(
+        Fees {
+            fee_bps: MAX_FEE_BPS,
+            creator_share_bps: BPS_DENOMINATOR as u16,
+        }
+        .validate(),
+        Ok(())
+    );
+
+    for fees in [
+        Fees {
+            fee_bps: MAX_FEE_BPS + 1,
+            creator_share_bps: 0,
+        },
+        Fees {
+            fee_bps: 0,
+            creator_share_bps: BPS_DENOMINATOR as u16 + 1,
+        },
+    ] {
+        assert_eq!(
+            fees.validate(),
+            Err(BondingCurveError::InvalidCurveParameters.into())
+        );
+    }
+}
+
+#[test]
+fn test_fee_rounds_up() {
+    assert_eq!(FEES.fee_on(0), Ok(0));
+    assert_eq!(FEES.fee_on(1), Ok(1));
+    assert_eq!(FEES.fee_on(100), Ok(1));
+    assert_eq!(FEES.fee_on(101), Ok(2));
+    assert_eq!(Fees::default().fee_on(u64::MAX), Ok(0));
+}
+
+#[test]
+fn test_split_quote_in_covers_fee() {
+    for quote_in in [0, 1, 101, 1_000_000, 3_083_247_688, u64::MAX] {
+        let (net, fee) = FEES.split_quote_in(quote_in).unwrap();
+        assert_eq!(net + fee, quote_in);
+        assert!(fee >= FEES.fee_on(net).unwrap(), "{quote_in}");
+    }
+    assert_eq!(FEES.split_quote_in(10_100), Ok((10_000, 100)));
+}
+
+#[test]
+fn test_gross_for_quote_out_covers_fee() {
+    for quote_out in [0, 1, 99, 1_000_000, 3_083_247_688, u64::MAX / 2] {
+        let (gross, fee) = FEES.gross_for_quote_out(quote_out).unwrap();
+        assert_eq!(gross - fee, quote_out);
+        assert!(fee >= FEES.fee_on(gross).unwrap(), "{quote_out}");
+    }
+    assert_eq!(FEES.gross_for_quote_out(9_900), Ok((10_000, 100)));
+    assert_eq!(
+        FEES.gross_for_quote_out(u64::MAX),
+        Err(BondingCurveError::MathOverflow.into())
+    );
+}
+
+#[test]
+fn test_split_leaves_remainder_to_protocol() {
+    assert_eq!(FEES.split(0), Ok((0, 0)));
+    assert_eq!(FEES.split(3), Ok((3, 0)));
+    assert_eq!(FEES.split(30_832_477), Ok((23_124_358, 7_708_119)));
+    assert_eq!(Fees::default().split(100), Ok((100, 0)));
+}
+
+#[test]
+fn test_protocol_fee_bps_rounds_down() {
+    assert_eq!(FEES.protocol_fee_bps(), 75);
+    assert_eq!(
+        Fees {
+            fee_bps: 3,
+            creator_share_bps: 5_000,
+        }
+        .protocol_fee_bps(),
+        1
+    );
+    assert_eq!(
+        Fees {
+            fee_bps: MAX_FEE_BPS,
+            creator_share_bps: BPS_DENOMINATOR as u16,
+        }
+        .protocol_fee_bps(),
+        0
+    );
+}
+
+#[test]
+fn test_split_with_referral_comes_out_of_protocol_share() {
+    assert_eq!(FEES.split_with_referral(100, 0), Ok((75, 25, 0)));
+    assert_eq!(FEES.split_with_referral(100, 2_000), Ok((55, 25, 20)));
+    assert_eq!(FEES.split_with_referral(99, 2_000), Ok((56, 24, 19)));
+    // The referrer never takes the creator's share
+    assert_eq!(
+        FEES.split_with_referral(100, BPS_DENOMINATOR as u16),
+        Ok((0, 25, 75))
+    );
+}
@@ -0,0 +1,136 @@
+//! Runs instruction handlers natively against accounts serialized in the loader's
+//! input format, so program logic can be tested without an SBF build. Cross-program
+//! invocations are no-ops off-chain; only this program's own writes are observable.
+
+use core::mem::MaybeUninit;
+
+use pinocchio::{account_info::AccountInfo, pubkey::Pubkey, ProgramResult};
+
+const NON_DUP_MARKER: u8 = u8::MAX;
+
+const MAX_PERMITTED_DATA_INCREASE: usize = 1_024 * 10;
+
+const MAX_ACCOUNTS: usize = 64;
+
+/// Size of the serialized account header preceding the account data.
+const HEADER_LEN: usize = 88;
+
+#[derive(Clone)]
+pub struct TestAccount {
+    pub key: Pubkey,
+    pub owner: Pubkey,
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl TestAccount {
+    /// A writable, non-signer account holding `data`.
+    pub fn new(key: Pubkey, owner: Pubkey, data: Vec<u8>) -> Self {
+        Self {
+            key,
+            owner,
+            lamports: 1_000_000_000,
+            data,
+            is_signer: false,
+            is_writable: true,
+        }
+    }
+
+    pub fn signer(mut self) -> Self {
+        self.is_signer = true;
+        self
+    }
+
+    pub fn readonly(mut self) -> Self {
+        self.is_writable = false;
+        self
+    }
+}
+
+/// Returns a fresh, unique key.
+pub fn unique_key() -> Pubkey {
+    solana_sdk::pubkey::Pubkey::new_unique().to_bytes()
+}
+
+/// Serializes `accounts`, hands the deserialized `AccountInfo`s to `f` and copies
+/// lamports, owner and data back into `accounts` once it returns.
+pub fn with_account_infos<R>(
+    accounts: &mut [TestAccount],
+    f: impl FnOnce(&[AccountInfo]) -> R,
+) -> R {
+    assert!(accounts.len() <= MAX_ACCOUNTS);
+
+    let mut bytes = Vec::new();
+    let mut offsets: Vec<Option<usize>> = Vec::with_capacity(accounts.len());
+
+    bytes.extend_from_slice(&(accounts.len() as u64).to_le_bytes());
+
+    for (index, account) in accounts.iter().enumerate() {
+        if let Some(original) = accounts[..index].iter().position(|a| a.key == account.key) {
+            bytes.push(original as u8);
+            bytes.extend_from_slice(&[0; 7]);
+            offsets.push(None);
+            continue;
+        }
+
+        offsets.push(Some(bytes.len()));
+        bytes.push(NON_DUP_MARKER);
+        bytes.push(account.is_signer as u8);
+        bytes.push(account.is_writable as u8);
+        bytes.push(0); // executable
+        bytes.extend_from_slice(&[0; 4]); // original data length
+        bytes.extend_from_slice(&account.key);
+        bytes.extend_from_slice(&account.owner);
+        bytes.extend_from_slice(&account.lamports.to_le_bytes());
+        bytes.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&account.data);
+        bytes.resize(bytes.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+        bytes.resize(bytes.len().next_multiple_of(8), 0);
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes()); // rent epoch
+    }
+
+    // Instruction data and program id are passed to the handlers directly.
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&crate::ID);
+
+    // The loader hands programs an 8-byte aligned buffer.
+    let mut buffer = vec![0u64; bytes.len().div_ceil(8)];
+    let input = buffer.as_mut_ptr() as *mut u8;
+    unsafe { core::ptr::copy_nonoverlapping(bytes.as_ptr(), input, bytes.len()) };
+
+    const UNINIT: MaybeUninit<AccountInfo> = MaybeUninit::<AccountInfo>::uninit();
+    let mut account_infos = [UNINIT; MAX_ACCOUNTS];
+
+    let result = unsafe {
+        let (_, count, _) =
+            pinocchio::entrypoint::deserialize::<MAX_ACCOUNTS>(input, &mut account_infos);
+        f(core::slice::from_raw_parts(
+            account_infos.as_ptr() as *const AccountInfo,
+            count,
+        ))
+    };
+
+    for (account, offset) in accounts.iter_mut().zip(offsets) {
+        let Some(offset) = offset else { continue };
+        unsafe {
+            let header = input.add(offset);
+            account
+                .owner
+                .copy_from_slice(core::slice::from_raw_parts(header.add(40), 32));
+            account.lamports = core::ptr::read_unaligned(header.add(72) as *const u64);
+            let data_len = core::ptr::read_unaligned(header.add(80) as *const u64) as usize;
+            account.data = core::slice::from_raw_parts(header.add(HEADER_LEN), data_len).to_vec();
+        }
+    }
+
+    result
+}
+
+/// Runs `process_instruction` over `accounts` with the given instruction data.
+pub fn process(accounts: &mut [TestAccount], instruction_data: &[u8]) -> ProgramResult {
+    with_account_infos(accounts, |account_infos| {
+        crate::process_instruction(&crate::ID, account_infos, instruction_data)
+    })
+}
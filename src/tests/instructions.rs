@@ -0,0 +1,3196 @@
+use pinocchio::{program_error::ProgramError, pubkey::Pubkey, ProgramResult};
+use pinocchio_token::state::{Mint, TokenAccount};
+
+use super::harness::{process, unique_key, TestAccount};
+use crate::{
+    amm::{Pool, SwapDirection},
+    curve::{BondingCurve, Curve as _, CurveType, MAX_EXPONENT},
+    error::BondingCurveError,
+    fees::{Fees, MAX_FEE_BPS},
+    migration::{
+        LpPolicy, MigrationTarget, UnsoldTokenPolicy, METEORA_DAMM_ACCOUNTS_LEN, METEORA_DAMM_ID,
+        RAYDIUM_CPMM_ACCOUNTS_LEN, RAYDIUM_CPMM_ID, RAYDIUM_CPMM_MIGRATION_FEE,
+    },
+    pda::{
+        config_address, curve_address, fee_vault_address, lp_lock_address, lp_mint_address,
+        lp_vault_address, migration_authority_address, migration_quote_address,
+        migration_token_address, pool_address, pool_quote_vault_address, pool_token_vault_address,
+        program_data_address, quote_vault_address, referrer_stats_address, token_vault_address,
+        vault_address, vesting_address,
+    },
+    state::{
+        BondingCurveState, CurveStatus, GlobalConfig, PoolState, ProgramAccount, QuoteMode,
+        ReferrerStats, SupplyMode,
+    },
+    sysvars, ConfigArgs, InitializeArgs, ALLOCATION_AT_MIGRATION, BPF_LOADER_UPGRADEABLE_ID, ID,
+    INITIAL_VSOL, INITIAL_VTOKEN, NATIVE_MINT, TOKEN_DECIMALS, TOTAL_SUPPLY,
+};
+
+const TOKEN_PROGRAM: Pubkey = pinocchio_token::ID;
+
+const SYSTEM_PROGRAM: Pubkey = pinocchio_system::ID;
+
+const BUMP: u8 = 7;
+
+const VAULT_BUMP: u8 = 4;
+
+const FEE_VAULT_BUMP: u8 = 5;
+
+/// Canonical bump of the config. Host builds accept every bump, so it is the highest.
+const CONFIG_BUMP: u8 = u8::MAX;
+
+const TOKEN_VAULT_BUMP: u8 = 6;
+
+const POOL_BUMP: u8 = 8;
+
+const POOL_TOKEN_VAULT_BUMP: u8 = 10;
+
+const POOL_QUOTE_VAULT_BUMP: u8 = 11;
+
+const LP_MINT_BUMP: u8 = 12;
+
+const LP_VAULT_BUMP: u8 = 13;
+
+const LP_LOCK_BUMP: u8 = 17;
+
+const VESTING_BUMP: u8 = 18;
+
+const REFERRER_STATS_BUMP: u8 = 9;
+
+const MIGRATION_AUTHORITY_BUMP: u8 = 14;
+
+const MIGRATION_TOKEN_BUMP: u8 = 15;
+
+const MIGRATION_QUOTE_BUMP: u8 = 16;
+
+/// An initialized curve, kept in sync with the instructions processed against it.
+struct Curve {
+    key: Pubkey,
+    data: Vec<u8>,
+    vtoken_mint: Pubkey,
+    /// The quote mint, or the quote vault of a native curve.
+    quote_account: Pubkey,
+    /// Lamports of the quote account; system transfers into a native vault are no-ops
+    /// off-chain, so tests fund it explicitly.
+    quote_lamports: u64,
+    fee_vault: Pubkey,
+    /// Lamports of the fee vault, funded explicitly like `quote_lamports`.
+    fee_vault_lamports: u64,
+    /// The admin that initialized the curve, its creator.
+    creator: Pubkey,
+    /// Data of the global config passed to every instruction.
+    config: Vec<u8>,
+}
+
+impl Curve {
+    fn initialize() -> Self {
+        Self::initialize_with(&InitializeArgs::default())
+    }
+
+    fn initialize_with(args: &InitializeArgs) -> Self {
+        Self::initialize_under(args, &ConfigArgs::default())
+    }
+
+    /// A curve created under a config initialized with `config_args`. A config charging
+    /// a migration fee only takes curves holding lamports, so SPL quoted curves are then
+    /// quoted in wrapped SOL.
+    fn initialize_under(args: &InitializeArgs, config_args: &ConfigArgs) -> Self {
+        let mut accounts = initialize_accounts_for(args);
+        accounts[5] = config_account(config_args);
+        if config_args.migration_fee > 0 && args.quote_mode == QuoteMode::SplToken {
+            accounts[3].key = NATIVE_MINT;
+        }
+        process(&mut accounts, &initialize_data(args)).unwrap();
+
+        Self {
+            key: accounts[0].key,
+            data: accounts[0].data.clone(),
+            vtoken_mint: accounts[1].key,
+            quote_account: accounts[3].key,
+            quote_lamports: accounts[3].lamports,
+            fee_vault: accounts[6].key,
+            fee_vault_lamports: accounts[6].lamports,
+            creator: accounts[4].key,
+            config: accounts[5].data.clone(),
+        }
+    }
+
+    /// A curve quoted in native SOL.
+    fn initialize_native() -> Self {
+        Self::initialize_with(&InitializeArgs {
+            quote_mode: QuoteMode::Native,
+            quote_vault_bump: BUMP,
+            ..Default::default()
+        })
+    }
+
+    /// A curve whose whole supply is minted into its token vault.
+    fn initialize_pre_minted() -> Self {
+        Self::initialize_with(&pre_minted_args())
+    }
+
+    /// A curve with lamport-sized reserves charging a 1% fee, a quarter of it to the
+    /// creator.
+    fn initialize_with_fees(quote_mode: QuoteMode) -> Self {
+        Self::initialize_with(&InitializeArgs {
+            initial_vsol_reserve: 30_000_000_000,
+            quote_mode,
+            quote_vault_bump: BUMP,
+            fees: Fees {
+                fee_bps: 100,
+                creator_share_bps: 2_500,
+            },
+            ..Default::default()
+        })
+    }
+
+    /// A curve that sold its whole allocation, for 1_000 of collateral, with 400 tokens
+    /// left unsold.
+    fn complete(args: InitializeArgs) -> Self {
+        let mut curve = Self::initialize_with(&InitializeArgs {
+            initial_vtoken_reserve: 1_000,
+            initial_vsol_reserve: 1_000,
+            total_supply: 900,
+            allocation_at_migration: 500,
+            ..args
+        });
+        curve.buy(500).unwrap();
+        curve
+    }
+
+    /// A complete curve migrating to the external `target`, created under a config
+    /// charging just what the target costs. It sold half its 1e9 tokens for 1 SOL of
+    /// collateral, with 4e8 tokens left unsold.
+    fn complete_external(target: MigrationTarget, args: InitializeArgs) -> Self {
+        let config_args = ConfigArgs {
+            migration_fee: target.fee(),
+            ..Default::default()
+        };
+        let mut curve = Self::initialize_under(
+            &InitializeArgs {
+                initial_vtoken_reserve: 1_000_000_000,
+                initial_vsol_reserve: 1_000_000_000,
+                total_supply: 900_000_000,
+                allocation_at_migration: 500_000_000,
+                migration_target: target,
+                ..args
+            },
+            &config_args,
+        );
+        curve.buy(500_000_000).unwrap();
+        curve
+    }
+
+    /// A complete in-house curve that locks its liquidity until a second past the host
+    /// clock, see [`Self::unlock_lp`].
+    fn locking() -> Self {
+        Self::complete(InitializeArgs {
+            lp_policy: LpPolicy::Lock,
+            lp_unlock_timestamp: 1,
+            ..Default::default()
+        })
+    }
+
+    /// A complete in-house curve that vests its unsold tokens to its creator over 100
+    /// seconds, see [`Self::elapse_vesting`].
+    fn vesting() -> Self {
+        Self::complete(InitializeArgs {
+            unsold_token_policy: UnsoldTokenPolicy::Vest,
+            vesting_duration: 100,
+            ..Default::default()
+        })
+    }
+
+    fn is_native(&self) -> bool {
+        self.state().quote_mode() == Ok(QuoteMode::Native)
+    }
+
+    fn is_pre_minted(&self) -> bool {
+        self.state().supply_mode() == Ok(SupplyMode::PreMinted)
+    }
+
+    fn locks_lp(&self) -> bool {
+        self.state().lp_policy() == Ok(LpPolicy::Lock)
+    }
+
+    fn vests_tokens(&self) -> bool {
+        self.state().unsold_token_policy() == Ok(UnsoldTokenPolicy::Vest)
+    }
+
+    fn quote_account(&self) -> TestAccount {
+        let mut account = TestAccount::new(self.quote_account, ID, vec![]);
+        account.lamports = self.quote_lamports;
+        account
+    }
+
+    fn fee_vault(&self) -> TestAccount {
+        let owner = if self.is_native() { ID } else { TOKEN_PROGRAM };
+        let mut account = TestAccount::new(self.fee_vault, owner, vec![]);
+        account.lamports = self.fee_vault_lamports;
+        account
+    }
+
+    fn state(&self) -> &BondingCurveState {
+        unsafe { BondingCurveState::from_bytes(&self.data) }
+    }
+
+    fn config_account(&self) -> TestAccount {
+        TestAccount::new(
+            config_address(CONFIG_BUMP).unwrap(),
+            ID,
+            self.config.clone(),
+        )
+        .readonly()
+    }
+
+    fn state_token_account(&self) -> Pubkey {
+        vault_address(&self.key, VAULT_BUMP).unwrap()
+    }
+
+    /// The token mint as the curve left it.
+    fn mint_data(&self) -> Vec<u8> {
+        if self.is_pre_minted() {
+            mint_data(None, None)
+        } else {
+            mint_data(Some(&self.key), None)
+        }
+    }
+
+    /// Accounts for `Buy` and `Sell`, which share the same layout.
+    fn trade_accounts(&self) -> Vec<TestAccount> {
+        let trader = unique_key();
+        let mut accounts = vec![
+            TestAccount::new(self.key, ID, self.data.clone()),
+            TestAccount::new(trader, ID, vec![]).signer(),
+            TestAccount::new(
+                unique_key(),
+                TOKEN_PROGRAM,
+                token_account_data(&self.vtoken_mint, &trader),
+            ),
+            TestAccount::new(self.vtoken_mint, TOKEN_PROGRAM, self.mint_data()),
+            self.quote_account(),
+            TestAccount::new(TOKEN_PROGRAM, ID, vec![]).readonly(),
+            self.config_account(),
+            self.fee_vault(),
+        ];
+        if self.is_native() {
+            accounts.push(TestAccount::new(SYSTEM_PROGRAM, ID, vec![]).readonly());
+        } else {
+            accounts.push(TestAccount::new(
+                self.state_token_account(),
+                TOKEN_PROGRAM,
+                token_account_data(&self.quote_account, &self.key),
+            ));
+            accounts.push(TestAccount::new(
+                unique_key(),
+                TOKEN_PROGRAM,
+                token_account_data(&self.quote_account, &trader),
+            ));
+        }
+        if self.is_pre_minted() {
+            accounts.push(TestAccount::new(
+                token_vault_address(&self.key, TOKEN_VAULT_BUMP).unwrap(),
+                TOKEN_PROGRAM,
+                vec![],
+            ));
+        }
+        accounts
+    }
+
+    fn process(&mut self, mut accounts: Vec<TestAccount>, data: &[u8]) -> ProgramResult {
+        process(&mut accounts, data)?;
+        self.data = accounts[0].data.clone();
+        if let Some(quote_account) = accounts.iter().find(|a| a.key == self.quote_account) {
+            self.quote_lamports = quote_account.lamports;
+        }
+        if let Some(fee_vault) = accounts.iter().find(|a| a.key == self.fee_vault) {
+            self.fee_vault_lamports = fee_vault.lamports;
+        }
+        Ok(())
+    }
+
+    fn buy(&mut self, amount: u64) -> ProgramResult {
+        self.buy_with_limit(amount, u64::MAX)
+    }
+
+    fn buy_with_limit(&mut self, amount: u64, max_quote_in: u64) -> ProgramResult {
+        self.process(self.trade_accounts(), &buy_data(amount, max_quote_in))
+    }
+
+    fn sell(&mut self, amount: u64) -> ProgramResult {
+        self.sell_with_limit(amount, 0)
+    }
+
+    fn sell_with_limit(&mut self, amount: u64, min_quote_out: u64) -> ProgramResult {
+        let data = [
+            &[2][..],
+            &amount.to_le_bytes(),
+            &min_quote_out.to_le_bytes(),
+        ]
+        .concat();
+        self.process(self.trade_accounts(), &data)
+    }
+
+    fn buy_exact_in(&mut self, quote_in: u64, min_tokens_out: u64) -> ProgramResult {
+        let data = [
+            &[4][..],
+            &quote_in.to_le_bytes(),
+            &min_tokens_out.to_le_bytes(),
+        ]
+        .concat();
+        self.process(self.trade_accounts(), &data)
+    }
+
+    fn sell_exact_out(&mut self, quote_out: u64, max_tokens_in: u64) -> ProgramResult {
+        let data = [
+            &[5][..],
+            &quote_out.to_le_bytes(),
+            &max_tokens_in.to_le_bytes(),
+        ]
+        .concat();
+        self.process(self.trade_accounts(), &data)
+    }
+
+    /// Accounts for `Migrate`; the pool accounts stand in for the ones created by the
+    /// system program.
+    fn migrate_accounts(&self) -> Vec<TestAccount> {
+        let pool = pool_address(&self.key, POOL_BUMP).unwrap();
+        let mut pool_quote_vault = TestAccount::new(
+            pool_quote_vault_address(&pool, POOL_QUOTE_VAULT_BUMP).unwrap(),
+            TOKEN_PROGRAM,
+            vec![],
+        );
+        if self.is_native() {
+            pool_quote_vault.owner = ID;
+        }
+        let payer = unique_key();
+
+        let mut accounts = vec![
+            TestAccount::new(self.key, ID, self.data.clone()),
+            TestAccount::new(self.state_token_account(), TOKEN_PROGRAM, vec![]),
+            self.quote_account(),
+            TestAccount::new(self.vtoken_mint, TOKEN_PROGRAM, self.mint_data()),
+            TestAccount::new(payer, ID, vec![]).signer(),
+            TestAccount::new(TOKEN_PROGRAM, ID, vec![]).readonly(),
+            TestAccount::new(SYSTEM_PROGRAM, ID, vec![]).readonly(),
+            self.config_account(),
+            self.lamports_account(
+                *unsafe { GlobalConfig::from_bytes(&self.config) }.fee_recipient(),
+            ),
+            self.lamports_account(payer),
+            TestAccount::new(pool, ID, vec![0; PoolState::LEN]),
+            TestAccount::new(
+                pool_token_vault_address(&pool, POOL_TOKEN_VAULT_BUMP).unwrap(),
+                TOKEN_PROGRAM,
+                vec![],
+            ),
+            pool_quote_vault,
+            TestAccount::new(
+                lp_mint_address(&pool, LP_MINT_BUMP).unwrap(),
+                TOKEN_PROGRAM,
+                vec![],
+            ),
+            TestAccount::new(
+                lp_vault_address(&pool, LP_VAULT_BUMP).unwrap(),
+                TOKEN_PROGRAM,
+                vec![],
+            ),
+        ];
+        if self.locks_lp() {
+            accounts.push(TestAccount::new(
+                lp_lock_address(&self.key, LP_LOCK_BUMP).unwrap(),
+                TOKEN_PROGRAM,
+                vec![],
+            ));
+        }
+        if self.vests_tokens() {
+            accounts.push(TestAccount::new(
+                vesting_address(&self.key, VESTING_BUMP).unwrap(),
+                TOKEN_PROGRAM,
+                vec![],
+            ));
+        }
+        if self.is_pre_minted() {
+            accounts.push(TestAccount::new(
+                token_vault_address(&self.key, TOKEN_VAULT_BUMP).unwrap(),
+                TOKEN_PROGRAM,
+                vec![],
+            ));
+        }
+        accounts
+    }
+
+    /// The account `recipient` is paid lamports of the migration fee at: itself for
+    /// native curves, or its wrapped SOL account.
+    fn lamports_account(&self, recipient: Pubkey) -> TestAccount {
+        if self.is_native() {
+            TestAccount::new(recipient, SYSTEM_PROGRAM, vec![])
+        } else {
+            TestAccount::new(
+                unique_key(),
+                TOKEN_PROGRAM,
+                token_account_data(&NATIVE_MINT, &recipient),
+            )
+        }
+    }
+
+    /// `Migrate` instruction `data`, followed by the LP lock bump if the curve locks its
+    /// liquidity and the vesting bump if it vests its unsold tokens.
+    fn with_trailing_bumps(&self, mut data: Vec<u8>) -> Vec<u8> {
+        if self.locks_lp() {
+            data.push(LP_LOCK_BUMP);
+        }
+        if self.vests_tokens() {
+            data.push(VESTING_BUMP);
+        }
+        data
+    }
+
+    fn migrate(&mut self) -> ProgramResult {
+        self.process(
+            self.migrate_accounts(),
+            &self.with_trailing_bumps(migrate_data()),
+        )
+    }
+
+    /// Migrates the curve and returns the pool it graduated into.
+    fn graduate(&mut self) -> GraduatedPool {
+        let mut accounts = self.migrate_accounts();
+        process(&mut accounts, &self.with_trailing_bumps(migrate_data())).unwrap();
+        self.data = accounts[0].data.clone();
+        self.quote_lamports = accounts[2].lamports;
+
+        GraduatedPool {
+            key: accounts[10].key,
+            data: accounts[10].data.clone(),
+            token_vault: accounts[11].key,
+            quote_vault: accounts[12].clone(),
+            lp_mint: accounts[13].key,
+        }
+    }
+
+    /// Charges the curve `migration_fee`, `migration_bounty` of it to the caller and the
+    /// rest to `fee_recipient`, as if it was created under a config charging them. The
+    /// fee is set past `Initialize`, which only takes it from quotes holding lamports,
+    /// to fit the collateral of [`Self::complete`].
+    fn charge_migration(
+        &mut self,
+        fee_recipient: Pubkey,
+        migration_fee: u64,
+        migration_bounty: u64,
+    ) {
+        self.config = config_account(&ConfigArgs {
+            fee_recipient,
+            ..Default::default()
+        })
+        .data;
+        let state = unsafe { BondingCurveState::from_bytes_mut(&mut self.data) };
+        state.set_migration_fee(migration_fee);
+        state.set_migration_bounty(migration_bounty);
+    }
+
+    fn migration_authority(&self) -> (Pubkey, Pubkey, Pubkey) {
+        let authority = migration_authority_address(&self.key, MIGRATION_AUTHORITY_BUMP).unwrap();
+        (
+            authority,
+            migration_token_address(&authority, MIGRATION_TOKEN_BUMP).unwrap(),
+            migration_quote_address(&authority, MIGRATION_QUOTE_BUMP).unwrap(),
+        )
+    }
+
+    /// Accounts for `Migrate` to an external target: the migration authority and its
+    /// token accounts, then `program` and its pool-creation accounts, with the ones the
+    /// program checks at their positions and placeholders for the rest.
+    fn external_migrate_accounts(&self, program: Pubkey, dex_len: usize) -> Vec<TestAccount> {
+        let (authority, token_account, quote_account) = self.migration_authority();
+        let (mint, quote_mint) = (self.vtoken_mint, self.quote_account);
+        let (positions, lp_mint, lp_account) = if program == RAYDIUM_CPMM_ID {
+            let ((mint_0, account_0), (mint_1, account_1)) = if mint < quote_mint {
+                ((mint, token_account), (quote_mint, quote_account))
+            } else {
+                ((quote_mint, quote_account), (mint, token_account))
+            };
+            let positions = vec![
+                (0, authority),
+                (4, mint_0),
+                (5, mint_1),
+                (7, account_0),
+                (8, account_1),
+            ];
+            (positions, 6, 9)
+        } else {
+            let positions = vec![
+                (2, mint),
+                (3, quote_mint),
+                (10, token_account),
+                (11, quote_account),
+                (15, authority),
+            ];
+            (positions, 1, 12)
+        };
+        let mut dex_accounts: Vec<TestAccount> = (0..dex_len)
+            .map(|_| TestAccount::new(unique_key(), SYSTEM_PROGRAM, vec![]))
+            .collect();
+        for (index, key) in positions {
+            dex_accounts[index].key = key;
+        }
+        // The liquidity account as the DEX leaves it once it created the pool
+        let lp_mint = dex_accounts[lp_mint].key;
+        dex_accounts[lp_account].owner = TOKEN_PROGRAM;
+        dex_accounts[lp_account].data = token_account_data(&lp_mint, &authority);
+
+        let mut accounts = self.migrate_accounts();
+        // The LP lock and the token vault follow the target accounts
+        let trailing = accounts.split_off(15);
+        accounts.truncate(10);
+        accounts.extend([
+            TestAccount::new(authority, SYSTEM_PROGRAM, vec![]),
+            TestAccount::new(token_account, TOKEN_PROGRAM, vec![]),
+            TestAccount::new(quote_account, TOKEN_PROGRAM, vec![]),
+            TestAccount::new(program, SYSTEM_PROGRAM, vec![]).readonly(),
+        ]);
+        accounts.extend(dex_accounts);
+        accounts.extend(trailing);
+        accounts
+    }
+
+    /// Runs the fee claim `instruction` signed by `recipient`, returning its result and
+    /// the lamports paid to the destination.
+    fn claim(&mut self, instruction: u8, recipient: Pubkey) -> (ProgramResult, u64) {
+        let mut destination = TestAccount::new(unique_key(), ID, vec![]);
+        destination.lamports = 0;
+        let accounts = vec![
+            TestAccount::new(self.key, ID, self.data.clone()),
+            self.config_account(),
+            TestAccount::new(recipient, ID, vec![]).signer(),
+            self.fee_vault(),
+            destination,
+            TestAccount::new(TOKEN_PROGRAM, ID, vec![]).readonly(),
+        ];
+        let mut synced = accounts;
+        let result = process(&mut synced, &[instruction]);
+        if result.is_ok() {
+            self.data = synced[0].data.clone();
+            self.fee_vault_lamports = synced[3].lamports;
+        }
+        (result, synced[4].lamports)
+    }
+
+    /// Stands in for the clock passing the unlock timestamp, which host builds never
+    /// do.
+    fn unlock_lp(&mut self) {
+        unsafe { BondingCurveState::from_bytes_mut(&mut self.data) }.set_lp_unlock_timestamp(0);
+    }
+
+    /// Accounts for `ClaimLockedLp`; the LP lock holds the liquidity of `lp_mint`, as
+    /// `Migrate` leaves it.
+    fn claim_locked_lp_accounts(&self, lp_mint: &Pubkey) -> Vec<TestAccount> {
+        vec![
+            TestAccount::new(self.key, ID, self.data.clone()),
+            TestAccount::new(
+                lp_lock_address(&self.key, LP_LOCK_BUMP).unwrap(),
+                TOKEN_PROGRAM,
+                token_account_data(lp_mint, &self.key),
+            ),
+            TestAccount::new(self.creator, ID, vec![]).signer(),
+            TestAccount::new(unique_key(), TOKEN_PROGRAM, vec![]),
+            TestAccount::new(TOKEN_PROGRAM, ID, vec![]).readonly(),
+        ]
+    }
+
+    /// Stands in for `seconds` passing on the clock since the curve migrated, which
+    /// host builds never do.
+    fn elapse_vesting(&mut self, seconds: i64) {
+        unsafe { BondingCurveState::from_bytes_mut(&mut self.data) }.set_vesting_start(-seconds);
+    }
+
+    /// Accounts for `ClaimVestedTokens`, with the vesting account as `Migrate` leaves
+    /// it.
+    fn claim_vested_tokens_accounts(&self) -> Vec<TestAccount> {
+        vec![
+            TestAccount::new(self.key, ID, self.data.clone()),
+            TestAccount::new(
+                vesting_address(&self.key, VESTING_BUMP).unwrap(),
+                TOKEN_PROGRAM,
+                token_account_data(&self.vtoken_mint, &self.key),
+            ),
+            TestAccount::new(self.creator, ID, vec![]).signer(),
+            TestAccount::new(unique_key(), TOKEN_PROGRAM, vec![]),
+            TestAccount::new(TOKEN_PROGRAM, ID, vec![]).readonly(),
+        ]
+    }
+}
+
+fn migrate_data() -> Vec<u8> {
+    vec![
+        3,
+        POOL_BUMP,
+        POOL_TOKEN_VAULT_BUMP,
+        POOL_QUOTE_VAULT_BUMP,
+        LP_MINT_BUMP,
+        LP_VAULT_BUMP,
+    ]
+}
+
+fn external_migrate_data() -> Vec<u8> {
+    vec![
+        3,
+        MIGRATION_AUTHORITY_BUMP,
+        MIGRATION_TOKEN_BUMP,
+        MIGRATION_QUOTE_BUMP,
+    ]
+}
+
+/// A pool a curve graduated into, kept in sync with the instructions processed
+/// against it.
+struct GraduatedPool {
+    key: Pubkey,
+    data: Vec<u8>,
+    token_vault: Pubkey,
+    /// The quote vault, whose lamports back the quote of a native pool.
+    quote_vault: TestAccount,
+    lp_mint: Pubkey,
+}
+
+impl GraduatedPool {
+    fn state(&self) -> &PoolState {
+        unsafe { PoolState::from_bytes(&self.data) }
+    }
+
+    /// Accounts for `Swap`.
+    fn swap_accounts(&self) -> Vec<TestAccount> {
+        vec![
+            TestAccount::new(self.key, ID, self.data.clone()),
+            TestAccount::new(unique_key(), ID, vec![]).signer(),
+            TestAccount::new(unique_key(), TOKEN_PROGRAM, vec![]),
+            TestAccount::new(unique_key(), TOKEN_PROGRAM, vec![]),
+            TestAccount::new(self.token_vault, TOKEN_PROGRAM, vec![]),
+            self.quote_vault.clone(),
+            TestAccount::new(TOKEN_PROGRAM, ID, vec![]).readonly(),
+            TestAccount::new(SYSTEM_PROGRAM, ID, vec![]).readonly(),
+        ]
+    }
+
+    /// Accounts for `AddLiquidity` and `RemoveLiquidity`, which share the same layout.
+    fn liquidity_accounts(&self) -> Vec<TestAccount> {
+        vec![
+            TestAccount::new(self.key, ID, self.data.clone()),
+            TestAccount::new(unique_key(), ID, vec![]).signer(),
+            TestAccount::new(unique_key(), TOKEN_PROGRAM, vec![]),
+            TestAccount::new(unique_key(), TOKEN_PROGRAM, vec![]),
+            TestAccount::new(unique_key(), TOKEN_PROGRAM, vec![]),
+            TestAccount::new(self.token_vault, TOKEN_PROGRAM, vec![]),
+            self.quote_vault.clone(),
+            TestAccount::new(self.lp_mint, TOKEN_PROGRAM, vec![]),
+            TestAccount::new(TOKEN_PROGRAM, ID, vec![]).readonly(),
+            TestAccount::new(SYSTEM_PROGRAM, ID, vec![]).readonly(),
+        ]
+    }
+
+    fn process(&mut self, mut accounts: Vec<TestAccount>, data: &[u8]) -> ProgramResult {
+        process(&mut accounts, data)?;
+        self.data = accounts[0].data.clone();
+        if let Some(quote_vault) = accounts.iter().find(|a| a.key == self.quote_vault.key) {
+            self.quote_vault = quote_vault.clone();
+        }
+        Ok(())
+    }
+
+    fn swap(&mut self, direction: SwapDirection, amount_in: u64, min_out: u64) -> ProgramResult {
+        let data = [
+            &[16, direction as u8][..],
+            &amount_in.to_le_bytes(),
+            &min_out.to_le_bytes(),
+        ]
+        .concat();
+        self.process(self.swap_accounts(), &data)
+    }
+
+    fn add_liquidity(
+        &mut self,
+        max_token_in: u64,
+        max_quote_in: u64,
+        min_lp: u64,
+    ) -> ProgramResult {
+        let data = [
+            &[17][..],
+            &max_token_in.to_le_bytes(),
+            &max_quote_in.to_le_bytes(),
+            &min_lp.to_le_bytes(),
+        ]
+        .concat();
+        self.process(self.liquidity_accounts(), &data)
+    }
+
+    fn remove_liquidity(&mut self, lp: u64, min_token: u64, min_quote: u64) -> ProgramResult {
+        let data = [
+            &[18][..],
+            &lp.to_le_bytes(),
+            &min_token.to_le_bytes(),
+            &min_quote.to_le_bytes(),
+        ]
+        .concat();
+        self.process(self.liquidity_accounts(), &data)
+    }
+}
+
+fn buy_data(amount: u64, max_quote_in: u64) -> Vec<u8> {
+    [&[1][..], &amount.to_le_bytes(), &max_quote_in.to_le_bytes()].concat()
+}
+
+/// Data of a token mint with `mint_authority`, as the token program lays it out.
+fn mint_data(mint_authority: Option<&Pubkey>, freeze_authority: Option<&Pubkey>) -> Vec<u8> {
+    let mut data = vec![0; Mint::LEN];
+    if let Some(mint_authority) = mint_authority {
+        data[0] = 1;
+        data[4..36].copy_from_slice(mint_authority);
+    }
+    data[44] = TOKEN_DECIMALS;
+    data[45] = 1;
+    if let Some(freeze_authority) = freeze_authority {
+        data[46] = 1;
+        data[50..82].copy_from_slice(freeze_authority);
+    }
+    data
+}
+
+fn token_account_data(mint: &Pubkey, owner: &Pubkey) -> Vec<u8> {
+    let mut data = vec![0; TokenAccount::LEN];
+    data[0..32].copy_from_slice(mint);
+    data[32..64].copy_from_slice(owner);
+    // Initialized
+    data[108] = 1;
+    data
+}
+
+fn pre_minted_args() -> InitializeArgs {
+    InitializeArgs {
+        supply_mode: SupplyMode::PreMinted,
+        token_vault_bump: TOKEN_VAULT_BUMP,
+        ..Default::default()
+    }
+}
+
+fn initialize_data(args: &InitializeArgs) -> Vec<u8> {
+    [&[0, BUMP, VAULT_BUMP, FEE_VAULT_BUMP][..], &args.to_bytes()].concat()
+}
+
+fn initialize_accounts() -> Vec<TestAccount> {
+    initialize_accounts_for(&InitializeArgs::default())
+}
+
+fn initialize_accounts_for(args: &InitializeArgs) -> Vec<TestAccount> {
+    let vtoken_mint = unique_key();
+    let state = curve_address(&vtoken_mint, BUMP).unwrap();
+    let mut accounts = vec![
+        TestAccount::new(state, ID, vec![0; BondingCurveState::LEN]),
+        TestAccount::new(vtoken_mint, TOKEN_PROGRAM, vec![]).signer(),
+        TestAccount::new(
+            vault_address(&state, VAULT_BUMP).unwrap(),
+            TOKEN_PROGRAM,
+            vec![],
+        ),
+        TestAccount::new(unique_key(), TOKEN_PROGRAM, vec![]),
+        TestAccount::new(unique_key(), ID, vec![]).signer(),
+        config_account(&ConfigArgs::default()),
+        TestAccount::new(
+            fee_vault_address(&state, FEE_VAULT_BUMP).unwrap(),
+            TOKEN_PROGRAM,
+            vec![],
+        ),
+        TestAccount::new(SYSTEM_PROGRAM, ID, vec![]).readonly(),
+    ];
+
+    if args.quote_mode == QuoteMode::Native {
+        // The vaults as the system program leaves them, rent-exempt and owned by the
+        // program
+        let rent = sysvars::rent().unwrap().minimum_balance(0);
+        accounts[3] = TestAccount::new(
+            quote_vault_address(&state, args.quote_vault_bump).unwrap(),
+            ID,
+            vec![],
+        );
+        accounts[3].lamports = rent;
+        accounts[6].owner = ID;
+        accounts[6].lamports = rent;
+    }
+    if args.supply_mode == SupplyMode::PreMinted {
+        accounts.push(TestAccount::new(
+            token_vault_address(&state, args.token_vault_bump).unwrap(),
+            TOKEN_PROGRAM,
+            vec![],
+        ));
+    }
+    accounts
+}
+
+fn initialize_config_data(args: &ConfigArgs) -> Vec<u8> {
+    [&[6, CONFIG_BUMP][..], &args.to_bytes()].concat()
+}
+
+/// Program data account of the program, as the upgradeable loader writes it.
+fn program_data_account(upgrade_authority: Option<Pubkey>) -> TestAccount {
+    let mut data = [&3u32.to_le_bytes()[..], &[0; 8]].concat();
+    match upgrade_authority {
+        Some(authority) => data.extend([&[1][..], &authority].concat()),
+        None => data.push(0),
+    }
+    TestAccount::new(
+        program_data_address().unwrap(),
+        BPF_LOADER_UPGRADEABLE_ID,
+        data,
+    )
+    .readonly()
+}
+
+/// Accounts for `InitializeConfig`, signed by the upgrade authority; the config
+/// account stands in for the one created by the system program.
+fn initialize_config_accounts() -> Vec<TestAccount> {
+    let admin = unique_key();
+    vec![
+        TestAccount::new(
+            config_address(CONFIG_BUMP).unwrap(),
+            ID,
+            vec![0; GlobalConfig::LEN],
+        ),
+        TestAccount::new(admin, ID, vec![]).signer(),
+        TestAccount::new(SYSTEM_PROGRAM, ID, vec![]).readonly(),
+        program_data_account(Some(admin)),
+    ]
+}
+
+/// Data of a global config initialized with `args`, and its admin.
+fn initialize_config(args: &ConfigArgs) -> (Vec<u8>, Pubkey) {
+    let mut accounts = initialize_config_accounts();
+    process(&mut accounts, &initialize_config_data(args)).unwrap();
+    (accounts[0].data.clone(), accounts[1].key)
+}
+
+/// A global config initialized with `args`.
+fn config_account(args: &ConfigArgs) -> TestAccount {
+    let (data, _) = initialize_config(args);
+    TestAccount::new(config_address(CONFIG_BUMP).unwrap(), ID, data).readonly()
+}
+
+fn config_args() -> ConfigArgs {
+    ConfigArgs {
+        fee_recipient: unique_key(),
+        migration_fee: 300_000_000,
+        paused: false,
+        default_args: InitializeArgs {
+            initial_vtoken_reserve: 2_000_000,
+            total_supply: 1_500_000,
+            allocation_at_migration: 1_000_000,
+            curve_type: CurveType::Linear,
+            growth_scale: 500_000,
+            fees: Fees {
+                fee_bps: 100,
+                creator_share_bps: 2_500,
+            },
+            ..Default::default()
+        },
+        referral_fee_share_bps: 2_000,
+        migration_bounty: 50_000_000,
+        min_protocol_fee_bps: 50,
+    }
+}
+
+/// Runs a config instruction signed by `signer` against `config`, keeping it in sync.
+fn process_config(config: &mut Vec<u8>, signer: Pubkey, data: &[u8]) -> ProgramResult {
+    let mut accounts = [
+        TestAccount::new(config_address(CONFIG_BUMP).unwrap(), ID, config.clone()),
+        TestAccount::new(signer, ID, vec![]).signer(),
+    ];
+    process(&mut accounts, data)?;
+    *config = accounts[0].data.clone();
+    Ok(())
+}
+
+/// Accounts for `InitializeReferrerStats` of `referrer`, signed by the config admin.
+fn referrer_stats_accounts(referrer: Pubkey) -> [TestAccount; 5] {
+    let (config, admin) = initialize_config(&config_args());
+    [
+        TestAccount::new(
+            referrer_stats_address(&referrer, REFERRER_STATS_BUMP).unwrap(),
+            ID,
+            vec![0; ReferrerStats::LEN],
+        ),
+        TestAccount::new(referrer, ID, vec![]),
+        TestAccount::new(config_address(CONFIG_BUMP).unwrap(), ID, config),
+        TestAccount::new(admin, ID, vec![]).signer(),
+        TestAccount::new(SYSTEM_PROGRAM, ID, vec![]).readonly(),
+    ]
+}
+
+/// Stats of `referrer`, created by `InitializeReferrerStats`.
+fn referrer_stats(referrer: Pubkey) -> TestAccount {
+    let mut accounts = referrer_stats_accounts(referrer);
+    process(&mut accounts, &[15, REFERRER_STATS_BUMP]).unwrap();
+    let [stats, ..] = accounts;
+    stats
+}
+
+#[test]
+fn test_buy_reads_state_written_by_initialize() {
+    let mut curve = Curve::initialize();
+    assert_eq!(curve.state().vtoken_reserve_amount(), INITIAL_VTOKEN);
+    assert_eq!(curve.state().vsol_reserve_amount(), INITIAL_VSOL);
+
+    let amount = 100_000_000;
+    curve.buy(amount).unwrap();
+
+    assert_eq!(
+        curve.state().vtoken_reserve_amount(),
+        INITIAL_VTOKEN - amount
+    );
+    assert!(curve.state().vsol_reserve_amount() > INITIAL_VSOL);
+}
+
+#[test]
+fn test_sell_returns_tokens_to_reserve() {
+    let mut curve = Curve::initialize();
+    curve.buy(100_000_000).unwrap();
+    curve.sell(40_000_000).unwrap();
+
+    assert_eq!(
+        curve.state().vtoken_reserve_amount(),
+        INITIAL_VTOKEN - 60_000_000
+    );
+}
+
+#[test]
+fn test_initialize_stores_curve_parameters() {
+    let args = InitializeArgs {
+        initial_vtoken_reserve: 2_000_000,
+        initial_vsol_reserve: 5_000_000_000,
+        total_supply: 1_500_000,
+        allocation_at_migration: 1_000_000,
+        curve_type: CurveType::Linear,
+        growth_scale: 500_000,
+        quote_mode: QuoteMode::SplToken,
+        quote_vault_bump: 0,
+        fees: Fees {
+            fee_bps: 100,
+            creator_share_bps: 2_500,
+        },
+        supply_mode: SupplyMode::MintOnBuy,
+        token_vault_bump: 0,
+        migration_target: MigrationTarget::RaydiumCpmm,
+        lp_policy: LpPolicy::Lock,
+        lp_unlock_timestamp: 1_700_000_000,
+        unsold_token_policy: UnsoldTokenPolicy::Vest,
+        vesting_duration: 86_400,
+    };
+    let curve = Curve::initialize_under(
+        &args,
+        &ConfigArgs {
+            migration_fee: RAYDIUM_CPMM_MIGRATION_FEE,
+            ..Default::default()
+        },
+    );
+    let state = curve.state();
+
+    assert_eq!(state.vtoken_reserve_amount(), 2_000_000);
+    assert_eq!(state.initial_vtoken_reserve(), 2_000_000);
+    assert_eq!(state.vsol_reserve_amount(), 5_000_000_000);
+    assert_eq!(state.initial_vsol_reserve(), 5_000_000_000);
+    assert_eq!(state.total_supply(), 1_500_000);
+    assert_eq!(state.allocation_at_migration(), 1_000_000);
+    assert_eq!(state.curve_type(), Ok(CurveType::Linear));
+    assert_eq!(state.growth_scale(), 500_000);
+    assert_eq!(state.quote_mode(), Ok(QuoteMode::SplToken));
+    assert_eq!(state.vsol_mint(), &curve.quote_account);
+    assert_eq!(state.fees(), args.fees);
+    assert_eq!(state.creator(), &curve.creator);
+    assert_eq!(state.migration_target(), Ok(MigrationTarget::RaydiumCpmm));
+    assert_eq!(state.lp_policy(), Ok(LpPolicy::Lock));
+    assert_eq!(state.lp_unlock_timestamp(), 1_700_000_000);
+    assert_eq!(state.unsold_token_policy(), Ok(UnsoldTokenPolicy::Vest));
+    assert_eq!(state.vesting_duration(), 86_400);
+}
+
+#[test]
+fn test_initialize_rejects_invalid_parameters() {
+    let invalid = [
+        InitializeArgs {
+            initial_vsol_reserve: 0,
+            ..Default::default()
+        },
+        InitializeArgs {
+            allocation_at_migration: TOTAL_SUPPLY + 1,
+            ..Default::default()
+        },
+        InitializeArgs {
+            allocation_at_migration: 10,
+            initial_vtoken_reserve: 10,
+            ..Default::default()
+        },
+        InitializeArgs {
+            curve_type: CurveType::Linear,
+            growth_scale: 0,
+            ..Default::default()
+        },
+        InitializeArgs {
+            curve_type: CurveType::Exponential,
+            growth_scale: INITIAL_VTOKEN / MAX_EXPONENT,
+            ..Default::default()
+        },
+        InitializeArgs {
+            fees: Fees {
+                fee_bps: MAX_FEE_BPS + 1,
+                creator_share_bps: 0,
+            },
+            ..Default::default()
+        },
+        InitializeArgs {
+            fees: Fees {
+                fee_bps: 100,
+                creator_share_bps: 10_001,
+            },
+            ..Default::default()
+        },
+        // External pools only take SPL tokens
+        InitializeArgs {
+            quote_mode: QuoteMode::Native,
+            quote_vault_bump: BUMP,
+            migration_target: MigrationTarget::MeteoraDamm,
+            ..Default::default()
+        },
+        // Burned liquidity never unlocks
+        InitializeArgs {
+            lp_unlock_timestamp: 1,
+            ..Default::default()
+        },
+        // Locked liquidity unlocks after the curve is created, and host builds run at
+        // the Unix epoch
+        InitializeArgs {
+            lp_policy: LpPolicy::Lock,
+            lp_unlock_timestamp: 0,
+            ..Default::default()
+        },
+        InitializeArgs {
+            lp_policy: LpPolicy::Lock,
+            lp_unlock_timestamp: -1,
+            ..Default::default()
+        },
+        // Only vested tokens vest over a duration, and they always do
+        InitializeArgs {
+            vesting_duration: 1,
+            ..Default::default()
+        },
+        InitializeArgs {
+            unsold_token_policy: UnsoldTokenPolicy::Vest,
+            vesting_duration: 0,
+            ..Default::default()
+        },
+    ];
+
+    for args in invalid {
+        let mut accounts = initialize_accounts();
+        assert_eq!(
+            process(&mut accounts, &initialize_data(&args)),
+            Err(BondingCurveError::InvalidCurveParameters.into())
+        );
+    }
+
+    let mut data = initialize_data(&InitializeArgs::default());
+    data[4 + 32] = 3;
+    let mut accounts = initialize_accounts();
+    assert_eq!(
+        process(&mut accounts, &data),
+        Err(BondingCurveError::InvalidCurveParameters.into())
+    );
+
+    let mut accounts = initialize_accounts();
+    assert_eq!(
+        process(&mut accounts, &[0, BUMP, VAULT_BUMP, FEE_VAULT_BUMP, 0]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn test_trade_with_lamport_reserves() {
+    // The constant product of these reserves does not fit in a u64
+    let mut curve = Curve::initialize_with(&InitializeArgs {
+        initial_vsol_reserve: 30_000_000_000,
+        ..Default::default()
+    });
+    curve.buy(100_000_000).unwrap();
+    let cost = curve.state().vsol_reserve_amount() - 30_000_000_000;
+    // 30e9 * 1e8 / 973e6 = 3_083_247_687.56..., rounded up
+    assert_eq!(cost, 3_083_247_688);
+
+    curve.sell(100_000_000).unwrap();
+    assert!(curve.state().vsol_reserve_amount() >= 30_000_000_000);
+}
+
+#[test]
+fn test_initialize_rejects_initialized_state() {
+    let mut accounts = initialize_accounts();
+    let data = initialize_data(&InitializeArgs::default());
+
+    process(&mut accounts, &data).unwrap();
+    assert_eq!(
+        process(&mut accounts, &data),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+}
+
+#[test]
+fn test_initialize_requires_admin_signature() {
+    let mut accounts = initialize_accounts();
+    accounts[4].is_signer = false;
+
+    assert_eq!(
+        process(&mut accounts, &initialize_data(&InitializeArgs::default())),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+}
+
+#[test]
+fn test_trades_require_trader_signature() {
+    let mut curve = Curve::initialize();
+    curve.buy(100_000_000).unwrap();
+
+    let mut accounts = curve.trade_accounts();
+    accounts[1].is_signer = false;
+    assert_eq!(
+        curve.process(accounts, &buy_data(1_000, u64::MAX)),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+
+    let mut accounts = curve.trade_accounts();
+    accounts[1].is_signer = false;
+    let data = [&[2][..], &1_000u64.to_le_bytes(), &0u64.to_le_bytes()].concat();
+    assert_eq!(
+        curve.process(accounts, &data),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+}
+
+#[test]
+fn test_buy_rejects_wrong_mint() {
+    let mut curve = Curve::initialize();
+    let mut accounts = curve.trade_accounts();
+    accounts[3].key = unique_key();
+
+    assert_eq!(
+        curve.process(accounts, &buy_data(1_000, u64::MAX)),
+        Err(BondingCurveError::InvalidMint.into())
+    );
+}
+
+#[test]
+fn test_buy_rejects_wrong_quote_vault() {
+    let mut curve = Curve::initialize();
+    let mut accounts = curve.trade_accounts();
+    accounts[8].key = unique_key();
+
+    assert_eq!(
+        curve.process(accounts, &buy_data(1_000, u64::MAX)),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+
+    let mut accounts = curve.trade_accounts();
+    accounts.truncate(9);
+    assert_eq!(
+        curve.process(accounts, &buy_data(1_000, u64::MAX)),
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+}
+
+#[test]
+fn test_trades_check_trader_token_accounts() {
+    let mut curve = Curve::initialize();
+    curve.buy(100_000_000).unwrap();
+    let sell_data = [&[2][..], &1_000u64.to_le_bytes(), &0u64.to_le_bytes()].concat();
+
+    // The token account holds the curve's token and the quote account its quote, not
+    // one account standing in for both
+    let token_account = curve.trade_accounts()[2].data.clone();
+    let quote_account = curve.trade_accounts()[9].data.clone();
+    for (index, account_data) in [(2, quote_account), (9, token_account)] {
+        for data in [&buy_data(1_000, u64::MAX), &sell_data] {
+            let mut accounts = curve.trade_accounts();
+            accounts[index].data = account_data.clone();
+            assert_eq!(
+                curve.process(accounts, data),
+                Err(BondingCurveError::InvalidMint.into())
+            );
+        }
+    }
+
+    let mut accounts = curve.trade_accounts();
+    accounts[2].owner = ID;
+    assert_eq!(
+        curve.process(accounts, &buy_data(1_000, u64::MAX)),
+        Err(ProgramError::InvalidAccountData)
+    );
+}
+
+#[test]
+fn test_last_buy_is_capped_at_allocation() {
+    let mut curve = Curve::initialize();
+    curve.buy(INITIAL_VTOKEN).unwrap();
+
+    assert_eq!(
+        curve.state().vtoken_reserve_amount(),
+        INITIAL_VTOKEN - ALLOCATION_AT_MIGRATION
+    );
+    assert_eq!(curve.state().status(), Ok(CurveStatus::Complete));
+}
+
+#[test]
+fn test_last_buy_exact_in_pays_for_allocation_only() {
+    let mut curve = Curve::initialize_with(&InitializeArgs {
+        initial_vtoken_reserve: 1_000,
+        initial_vsol_reserve: 1_000,
+        total_supply: 900,
+        allocation_at_migration: 500,
+        ..Default::default()
+    });
+    // The 10_000 offered would buy more than the 500 tokens left
+    assert_eq!(
+        curve.buy_exact_in(10_000, 501),
+        Err(BondingCurveError::SlippageExceeded.into())
+    );
+    curve.buy_exact_in(10_000, 500).unwrap();
+
+    // Buying the 500 tokens costs 1_000, not the 10_000 offered
+    assert_eq!(curve.state().vtoken_reserve_amount(), 500);
+    assert_eq!(curve.state().vsol_reserve_amount(), 2_000);
+    assert_eq!(curve.state().status(), Ok(CurveStatus::Complete));
+}
+
+#[test]
+fn test_last_buy_exact_in_is_capped_on_every_curve() {
+    for curve_type in [CurveType::Linear, CurveType::Exponential] {
+        let mut curve = Curve::initialize_with(&InitializeArgs {
+            initial_vtoken_reserve: 1_000,
+            initial_vsol_reserve: 1_000,
+            total_supply: 900,
+            allocation_at_migration: 500,
+            curve_type,
+            growth_scale: 500,
+            ..Default::default()
+        });
+        let cost = BondingCurve::from_state(curve.state())
+            .unwrap()
+            .cost_to_buy(500)
+            .unwrap();
+
+        // Offers more than the whole virtual reserve is worth
+        curve.buy_exact_in(u64::MAX / 2, 500).unwrap();
+        assert_eq!(curve.state().vtoken_reserve_amount(), 500);
+        assert_eq!(curve.state().vsol_reserve_amount(), 1_000 + cost);
+        assert_eq!(curve.state().status(), Ok(CurveStatus::Complete));
+    }
+}
+
+#[test]
+fn test_complete_curve_stops_trading_and_migrates_once() {
+    let mut curve = Curve::initialize_with(&InitializeArgs {
+        initial_vtoken_reserve: 1_000,
+        initial_vsol_reserve: 1_000,
+        total_supply: 900,
+        allocation_at_migration: 500,
+        ..Default::default()
+    });
+    curve.buy(500).unwrap();
+    assert_eq!(curve.state().status(), Ok(CurveStatus::Complete));
+
+    let not_trading = Err(BondingCurveError::CurveNotTrading.into());
+    assert_eq!(curve.buy(1), not_trading);
+    assert_eq!(curve.sell(1), not_trading);
+    assert_eq!(curve.buy_exact_in(1_000, 0), not_trading);
+    assert_eq!(curve.sell_exact_out(1, u64::MAX), not_trading);
+
+    curve.migrate().unwrap();
+    assert_eq!(curve.state().status(), Ok(CurveStatus::Migrated));
+    assert_eq!(
+        curve.migrate(),
+        Err(BondingCurveError::AlreadyMigrated.into())
+    );
+    assert_eq!(curve.buy(1), not_trading);
+}
+
+#[test]
+fn test_buy_rejects_cost_above_limit() {
+    let mut curve = Curve::initialize_with(&InitializeArgs {
+        initial_vsol_reserve: 30_000_000_000,
+        ..Default::default()
+    });
+    // 30e9 * 1e8 / 973e6 = 3_083_247_687.56..., rounded up
+    assert_eq!(
+        curve.buy_with_limit(100_000_000, 3_083_247_687),
+        Err(BondingCurveError::SlippageExceeded.into())
+    );
+
+    curve.buy_with_limit(100_000_000, 3_083_247_688).unwrap();
+}
+
+#[test]
+fn test_sell_rejects_refund_below_limit() {
+    let mut curve = Curve::initialize_with(&InitializeArgs {
+        initial_vsol_reserve: 30_000_000_000,
+        ..Default::default()
+    });
+    curve.buy(100_000_000).unwrap();
+    let refund = BondingCurve::from_state(curve.state())
+        .unwrap()
+        .refund_for_sell(100_000_000)
+        .unwrap();
+
+    assert_eq!(
+        curve.sell_with_limit(100_000_000, refund + 1),
+        Err(BondingCurveError::SlippageExceeded.into())
+    );
+    curve.sell_with_limit(100_000_000, refund).unwrap();
+}
+
+#[test]
+fn test_trade_rejects_missing_limit() {
+    let mut curve = Curve::initialize();
+
+    let data = [&[1][..], &1_000u64.to_le_bytes()].concat();
+    assert_eq!(
+        curve.process(curve.trade_accounts(), &data),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    let data = [&[2][..], &1_000u64.to_le_bytes()].concat();
+    assert_eq!(
+        curve.process(curve.trade_accounts(), &data),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn test_buy_exact_in_spends_exact_quote() {
+    let mut curve = Curve::initialize_with(&InitializeArgs {
+        initial_vsol_reserve: 30_000_000_000,
+        ..Default::default()
+    });
+    let quote_in = 3_083_247_688;
+
+    assert_eq!(
+        curve.buy_exact_in(quote_in, 100_000_001),
+        Err(BondingCurveError::SlippageExceeded.into())
+    );
+    curve.buy_exact_in(quote_in, 100_000_000).unwrap();
+
+    assert_eq!(
+        curve.state().vtoken_reserve_amount(),
+        INITIAL_VTOKEN - 100_000_000
+    );
+    assert_eq!(
+        curve.state().vsol_reserve_amount(),
+        30_000_000_000 + quote_in
+    );
+}
+
+#[test]
+fn test_sell_exact_out_receives_exact_quote() {
+    let mut curve = Curve::initialize_with(&InitializeArgs {
+        curve_type: CurveType::Linear,
+        growth_scale: INITIAL_VTOKEN,
+        ..Default::default()
+    });
+    curve.buy(500_000_000).unwrap();
+    let vsol_reserve = curve.state().vsol_reserve_amount();
+    let quote_out = 10;
+    let amount = BondingCurve::from_state(curve.state())
+        .unwrap()
+        .amount_for_quote_out(quote_out)
+        .unwrap();
+
+    assert_eq!(
+        curve.sell_exact_out(quote_out, amount - 1),
+        Err(BondingCurveError::SlippageExceeded.into())
+    );
+    curve.sell_exact_out(quote_out, amount).unwrap();
+
+    assert_eq!(
+        curve.state().vtoken_reserve_amount(),
+        INITIAL_VTOKEN - 500_000_000 + amount
+    );
+    assert_eq!(
+        curve.state().vsol_reserve_amount(),
+        vsol_reserve - quote_out
+    );
+}
+
+#[test]
+fn test_sell_exact_out_rejects_quote_above_collateral() {
+    let mut curve = Curve::initialize_with(&InitializeArgs {
+        curve_type: CurveType::Exponential,
+        growth_scale: INITIAL_VTOKEN / 10,
+        ..Default::default()
+    });
+    curve.buy(100_000_000).unwrap();
+    let collateral = curve.state().vsol_reserve_amount() - INITIAL_VSOL;
+
+    assert_eq!(
+        curve.sell_exact_out(collateral + 1, u64::MAX),
+        Err(BondingCurveError::InsufficientReserve.into())
+    );
+}
+
+#[test]
+fn test_migrate_rejects_curve_below_threshold() {
+    let mut curve = Curve::initialize();
+    curve.buy(100_000_000).unwrap();
+
+    assert_eq!(
+        curve.migrate(),
+        Err(BondingCurveError::NotMigratable.into())
+    );
+}
+
+#[test]
+fn test_migrate_after_allocation_is_sold() {
+    let mut curve = Curve::initialize_with(&InitializeArgs {
+        initial_vtoken_reserve: 1_000,
+        initial_vsol_reserve: 1_000,
+        total_supply: 900,
+        allocation_at_migration: 500,
+        ..Default::default()
+    });
+    curve.buy(499).unwrap();
+    assert_eq!(
+        curve.migrate(),
+        Err(BondingCurveError::NotMigratable.into())
+    );
+
+    curve.buy(1).unwrap();
+    curve.migrate().unwrap();
+}
+
+#[test]
+fn test_trade_on_exponential_curve() {
+    let mut curve = Curve::initialize_with(&InitializeArgs {
+        curve_type: CurveType::Exponential,
+        growth_scale: INITIAL_VTOKEN / 10,
+        ..Default::default()
+    });
+    curve.buy(100_000_000).unwrap();
+    let vsol_after_buy = curve.state().vsol_reserve_amount();
+    assert!(vsol_after_buy > INITIAL_VSOL);
+
+    curve.sell(100_000_000).unwrap();
+    let vsol_after_sell = curve.state().vsol_reserve_amount();
+    assert_eq!(curve.state().vtoken_reserve_amount(), INITIAL_VTOKEN);
+    assert!(vsol_after_sell >= INITIAL_VSOL && vsol_after_sell < vsol_after_buy);
+}
+
+#[test]
+fn test_initialize_native_curve() {
+    let curve = Curve::initialize_native();
+    let state = curve.state();
+
+    assert_eq!(state.quote_mode(), Ok(QuoteMode::Native));
+    assert_eq!(state.quote_vault_bump(), BUMP);
+    assert_eq!(state.vsol_mint(), &[0; 32]);
+    assert_eq!(
+        curve.quote_account,
+        quote_vault_address(&curve.key, BUMP).unwrap()
+    );
+}
+
+#[test]
+fn test_initialize_native_curve_rejects_wrong_vault() {
+    let args = InitializeArgs {
+        quote_mode: QuoteMode::Native,
+        quote_vault_bump: BUMP,
+        ..Default::default()
+    };
+    let mut accounts = initialize_accounts_for(&args);
+    accounts[3].key = unique_key();
+
+    assert_eq!(
+        process(&mut accounts, &initialize_data(&args)),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+
+    let mut accounts = initialize_accounts_for(&args);
+    accounts.pop();
+    assert_eq!(
+        process(&mut accounts, &initialize_data(&args)),
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+}
+
+#[test]
+fn test_native_buy_requires_system_program_and_vault() {
+    let mut curve = Curve::initialize_native();
+
+    let mut accounts = curve.trade_accounts();
+    accounts.pop();
+    assert_eq!(
+        curve.process(accounts, &buy_data(1_000, u64::MAX)),
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+
+    let mut accounts = curve.trade_accounts();
+    accounts[4].key = unique_key();
+    assert_eq!(
+        curve.process(accounts, &buy_data(1_000, u64::MAX)),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+
+    curve.buy(100_000_000).unwrap();
+    assert_eq!(
+        curve.state().vtoken_reserve_amount(),
+        INITIAL_VTOKEN - 100_000_000
+    );
+}
+
+#[test]
+fn test_native_sell_pays_out_of_vault() {
+    let mut curve = Curve::initialize_with(&InitializeArgs {
+        initial_vsol_reserve: 30_000_000_000,
+        quote_mode: QuoteMode::Native,
+        quote_vault_bump: BUMP,
+        ..Default::default()
+    });
+    curve.buy(100_000_000).unwrap();
+    let cost = curve.state().vsol_reserve_amount() - 30_000_000_000;
+    let rent = sysvars::rent().unwrap().minimum_balance(0);
+    // Stands in for the system transfer made by the buy
+    curve.quote_lamports += cost;
+
+    let refund = BondingCurve::from_state(curve.state())
+        .unwrap()
+        .refund_for_sell(40_000_000)
+        .unwrap();
+    let mut accounts = curve.trade_accounts();
+    let seller_lamports = accounts[1].lamports;
+    let data = [&[2][..], &40_000_000u64.to_le_bytes(), &0u64.to_le_bytes()].concat();
+    process(&mut accounts, &data).unwrap();
+
+    assert_eq!(accounts[1].lamports, seller_lamports + refund);
+    assert_eq!(accounts[4].lamports, rent + cost - refund);
+}
+
+#[test]
+fn test_native_sell_keeps_vault_rent_exempt() {
+    let mut curve = Curve::initialize_with(&InitializeArgs {
+        initial_vsol_reserve: 30_000_000_000,
+        quote_mode: QuoteMode::Native,
+        quote_vault_bump: BUMP,
+        ..Default::default()
+    });
+    curve.buy(100_000_000).unwrap();
+    let refund = BondingCurve::from_state(curve.state())
+        .unwrap()
+        .refund_for_sell(100_000_000)
+        .unwrap();
+    // One lamport short of covering the refund on top of the rent-exempt minimum
+    curve.quote_lamports += refund - 1;
+
+    assert_eq!(
+        curve.sell(100_000_000),
+        Err(BondingCurveError::InsufficientReserve.into())
+    );
+
+    curve.quote_lamports += 1;
+    curve.sell(100_000_000).unwrap();
+    assert_eq!(
+        curve.quote_lamports,
+        sysvars::rent().unwrap().minimum_balance(0)
+    );
+}
+
+#[test]
+fn test_buy_charges_fee_on_top_of_cost() {
+    let mut curve = Curve::initialize_with_fees(QuoteMode::SplToken);
+    // 3_083_247_688 * 1% = 30_832_476.88, rounded up
+    let (cost, fee) = (3_083_247_688, 30_832_477);
+
+    assert_eq!(
+        curve.buy_with_limit(100_000_000, cost + fee - 1),
+        Err(BondingCurveError::SlippageExceeded.into())
+    );
+    curve.buy_with_limit(100_000_000, cost + fee).unwrap();
+
+    let state = curve.state();
+    assert_eq!(state.vsol_reserve_amount(), 30_000_000_000 + cost);
+    assert_eq!(state.creator_fees(), fee / 4);
+    assert_eq!(state.protocol_fees(), fee - fee / 4);
+}
+
+#[test]
+fn test_sell_deducts_fee_from_refund() {
+    let mut curve = Curve::initialize_with_fees(QuoteMode::SplToken);
+    curve.buy(100_000_000).unwrap();
+    let accrued = curve.state().accrued_fees().unwrap();
+    let refund = BondingCurve::from_state(curve.state())
+        .unwrap()
+        .refund_for_sell(100_000_000)
+        .unwrap();
+    let fee = curve.state().fees().fee_on(refund).unwrap();
+
+    assert_eq!(
+        curve.sell_with_limit(100_000_000, refund - fee + 1),
+        Err(BondingCurveError::SlippageExceeded.into())
+    );
+    curve.sell_with_limit(100_000_000, refund - fee).unwrap();
+
+    assert_eq!(curve.state().accrued_fees(), Ok(accrued + fee));
+}
+
+#[test]
+fn test_exact_trades_include_fee() {
+    let mut curve = Curve::initialize_with_fees(QuoteMode::SplToken);
+    let quote_in = 3_000_000_000;
+    curve.buy_exact_in(quote_in, 0).unwrap();
+
+    let fee = curve.state().accrued_fees().unwrap();
+    assert_eq!(
+        curve.state().vsol_reserve_amount() - 30_000_000_000 + fee,
+        quote_in
+    );
+    assert_eq!(fee, curve.state().fees().fee_on(quote_in - fee).unwrap());
+
+    let vsol_reserve = curve.state().vsol_reserve_amount();
+    let quote_out = 1_000_000_000;
+    curve.sell_exact_out(quote_out, u64::MAX).unwrap();
+
+    let sell_fee = curve.state().accrued_fees().unwrap() - fee;
+    assert_eq!(
+        vsol_reserve - curve.state().vsol_reserve_amount(),
+        quote_out + sell_fee
+    );
+    assert!(sell_fee >= curve.state().fees().fee_on(quote_out + sell_fee).unwrap());
+}
+
+#[test]
+fn test_native_sell_moves_fee_to_fee_vault() {
+    let mut curve = Curve::initialize_with_fees(QuoteMode::Native);
+    curve.buy(100_000_000).unwrap();
+    let rent = sysvars::rent().unwrap().minimum_balance(0);
+    // Stands in for the system transfers made by the buy
+    curve.quote_lamports += curve.state().vsol_reserve_amount() - 30_000_000_000;
+    curve.fee_vault_lamports += curve.state().accrued_fees().unwrap();
+
+    curve.sell(100_000_000).unwrap();
+
+    let collateral = curve.state().vsol_reserve_amount() - 30_000_000_000;
+    assert_eq!(curve.quote_lamports, rent + collateral);
+    assert_eq!(
+        curve.fee_vault_lamports,
+        rent + curve.state().accrued_fees().unwrap()
+    );
+}
+
+#[test]
+fn test_initialize_config_stores_parameters() {
+    let args = config_args();
+    let mut accounts = initialize_config_accounts();
+    process(&mut accounts, &initialize_config_data(&args)).unwrap();
+
+    let config = unsafe { GlobalConfig::from_bytes(&accounts[0].data) };
+    assert_eq!(config.bump(), CONFIG_BUMP);
+    assert_eq!(config.admin(), &accounts[1].key);
+    assert_eq!(config.fee_recipient(), &args.fee_recipient);
+    assert_eq!(config.migration_fee(), 300_000_000);
+    assert!(!config.paused());
+    assert_eq!(config.default_args(), Ok(args.default_args));
+    assert_eq!(config.referral_fee_share_bps(), 2_000);
+    assert_eq!(config.migration_bounty(), 50_000_000);
+    assert_eq!(config.min_protocol_fee_bps(), 50);
+
+    // The config is a singleton
+    assert_eq!(
+        process(&mut accounts, &initialize_config_data(&args)),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+}
+
+#[test]
+fn test_initialize_config_rejects_invalid_parameters() {
+    let mut accounts = initialize_config_accounts();
+    accounts[0].key = unique_key();
+    assert_eq!(
+        process(
+            &mut accounts,
+            &initialize_config_data(&ConfigArgs::default())
+        ),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+
+    let mut accounts = initialize_config_accounts();
+    accounts[1].is_signer = false;
+    assert_eq!(
+        process(
+            &mut accounts,
+            &initialize_config_data(&ConfigArgs::default())
+        ),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+
+    // Only the canonical bump, which clients find first
+    let mut data = initialize_config_data(&ConfigArgs::default());
+    data[1] = CONFIG_BUMP - 1;
+    let mut accounts = initialize_config_accounts();
+    accounts[0].key = config_address(CONFIG_BUMP - 1).unwrap();
+    assert_eq!(
+        process(&mut accounts, &data),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+
+    for default_args in [
+        InitializeArgs {
+            initial_vsol_reserve: 0,
+            ..Default::default()
+        },
+        InitializeArgs {
+            quote_mode: QuoteMode::Native,
+            ..Default::default()
+        },
+        pre_minted_args(),
+        InitializeArgs {
+            lp_policy: LpPolicy::Lock,
+            ..Default::default()
+        },
+        InitializeArgs {
+            unsold_token_policy: UnsoldTokenPolicy::Burn,
+            ..Default::default()
+        },
+    ] {
+        let args = ConfigArgs {
+            default_args,
+            ..Default::default()
+        };
+        let mut accounts = initialize_config_accounts();
+        assert_eq!(
+            process(&mut accounts, &initialize_config_data(&args)),
+            Err(BondingCurveError::InvalidCurveParameters.into())
+        );
+    }
+
+    for args in [
+        ConfigArgs {
+            referral_fee_share_bps: 10_001,
+            ..Default::default()
+        },
+        ConfigArgs {
+            min_protocol_fee_bps: MAX_FEE_BPS + 1,
+            ..Default::default()
+        },
+        // The defaults leave the protocol 100 * 75% = 75 bps
+        ConfigArgs {
+            min_protocol_fee_bps: 76,
+            ..config_args()
+        },
+        // The migration fee pays the bounty and refunds the caller what the default
+        // target costs
+        ConfigArgs {
+            migration_fee: 100,
+            migration_bounty: 101,
+            ..Default::default()
+        },
+        ConfigArgs {
+            migration_fee: RAYDIUM_CPMM_MIGRATION_FEE,
+            migration_bounty: 1,
+            default_args: InitializeArgs {
+                migration_target: MigrationTarget::RaydiumCpmm,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    ] {
+        let mut accounts = initialize_config_accounts();
+        assert_eq!(
+            process(&mut accounts, &initialize_config_data(&args)),
+            Err(BondingCurveError::InvalidCurveParameters.into())
+        );
+    }
+
+    let mut data = initialize_config_data(&ConfigArgs::default());
+    data[2 + 40] = 2;
+    let mut accounts = initialize_config_accounts();
+    assert_eq!(
+        process(&mut accounts, &data),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn test_initialize_config_requires_upgrade_authority() {
+    let data = initialize_config_data(&ConfigArgs::default());
+
+    for program_data in [
+        program_data_account(Some(unique_key())),
+        // Immutable programs have no upgrade authority
+        program_data_account(None),
+    ] {
+        let mut accounts = initialize_config_accounts();
+        accounts[3] = program_data;
+        assert_eq!(
+            process(&mut accounts, &data),
+            Err(BondingCurveError::Unauthorized.into())
+        );
+    }
+
+    let mut accounts = initialize_config_accounts();
+    accounts[3].key = unique_key();
+    assert_eq!(
+        process(&mut accounts, &data),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+
+    let mut accounts = initialize_config_accounts();
+    accounts[3].owner = ID;
+    assert_eq!(
+        process(&mut accounts, &data),
+        Err(ProgramError::InvalidAccountOwner)
+    );
+
+    let mut accounts = initialize_config_accounts();
+    accounts[3].data.truncate(13);
+    assert_eq!(
+        process(&mut accounts, &data),
+        Err(BondingCurveError::Unauthorized.into())
+    );
+
+    let mut accounts = initialize_config_accounts();
+    accounts.pop();
+    assert_eq!(
+        process(&mut accounts, &data),
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+}
+
+#[test]
+fn test_config_is_only_accepted_at_its_canonical_address() {
+    let (data, admin) = initialize_config(&ConfigArgs::default());
+    let mut accounts = [
+        TestAccount::new(config_address(CONFIG_BUMP - 1).unwrap(), ID, data),
+        TestAccount::new(admin, ID, vec![]).signer(),
+    ];
+    assert_eq!(
+        process(&mut accounts, &[11, 1]),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+
+    let mut curve = Curve::initialize();
+    let mut accounts = curve.trade_accounts();
+    accounts[6].key = config_address(CONFIG_BUMP - 1).unwrap();
+    assert_eq!(
+        curve.process(accounts, &buy_data(1_000, u64::MAX)),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+}
+
+#[test]
+fn test_update_config_requires_admin() {
+    let mut accounts = initialize_config_accounts();
+    process(
+        &mut accounts,
+        &initialize_config_data(&ConfigArgs::default()),
+    )
+    .unwrap();
+    let admin = accounts[1].key;
+    let args = config_args();
+    let data = [&[7][..], &args.to_bytes()].concat();
+
+    let mut update = |signer: Pubkey, is_signer: bool| {
+        let mut signer = TestAccount::new(signer, ID, vec![]);
+        signer.is_signer = is_signer;
+        let mut update_accounts = [
+            TestAccount::new(accounts[0].key, ID, accounts[0].data.clone()),
+            signer,
+        ];
+        let result = process(&mut update_accounts, &data);
+        accounts[0].data = update_accounts[0].data.clone();
+        result
+    };
+
+    assert_eq!(
+        update(unique_key(), true),
+        Err(BondingCurveError::Unauthorized.into())
+    );
+    assert_eq!(
+        update(admin, false),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+    update(admin, true).unwrap();
+
+    let config = unsafe { GlobalConfig::from_bytes(&accounts[0].data) };
+    assert_eq!(config.admin(), &admin);
+    assert_eq!(config.fee_recipient(), &args.fee_recipient);
+    assert_eq!(config.migration_fee(), args.migration_fee);
+    assert_eq!(config.default_args(), Ok(args.default_args));
+}
+
+#[test]
+fn test_initialize_uses_config_defaults() {
+    let args = config_args();
+    let mut accounts = initialize_accounts();
+    accounts[3].key = NATIVE_MINT;
+    accounts[5] = config_account(&args);
+    process(&mut accounts, &[0, BUMP, VAULT_BUMP, FEE_VAULT_BUMP]).unwrap();
+
+    let state = unsafe { BondingCurveState::from_bytes(&accounts[0].data) };
+    assert_eq!(state.initial_vtoken_reserve(), 2_000_000);
+    assert_eq!(state.initial_vsol_reserve(), INITIAL_VSOL);
+    assert_eq!(state.total_supply(), 1_500_000);
+    assert_eq!(state.allocation_at_migration(), 1_000_000);
+    assert_eq!(state.curve_type(), Ok(CurveType::Linear));
+    assert_eq!(state.growth_scale(), 500_000);
+    assert_eq!(state.quote_mode(), Ok(QuoteMode::SplToken));
+    assert_eq!(state.fees(), args.default_args.fees);
+    assert_eq!(state.migration_fee(), args.migration_fee);
+    assert_eq!(state.migration_bounty(), args.migration_bounty);
+}
+
+#[test]
+fn test_initialize_requires_lamports_for_migration_fee() {
+    // The migration fee is paid in lamports, which a curve quoted in any other token
+    // than native or wrapped SOL never collects
+    let mut accounts = initialize_accounts();
+    accounts[5] = config_account(&config_args());
+    assert_eq!(
+        process(&mut accounts, &[0, BUMP, VAULT_BUMP, FEE_VAULT_BUMP]),
+        Err(BondingCurveError::InvalidCurveParameters.into())
+    );
+
+    let args = InitializeArgs {
+        quote_mode: QuoteMode::Native,
+        quote_vault_bump: BUMP,
+        fees: config_args().default_args.fees,
+        ..Default::default()
+    };
+    let mut accounts = initialize_accounts_for(&args);
+    accounts[5] = config_account(&config_args());
+    process(&mut accounts, &initialize_data(&args)).unwrap();
+    let state = unsafe { BondingCurveState::from_bytes(&accounts[0].data) };
+    assert_eq!(state.migration_fee(), 300_000_000);
+
+    // Without a fee, any token will do
+    let mut accounts = initialize_accounts();
+    accounts[5] = config_account(&ConfigArgs {
+        migration_fee: 0,
+        migration_bounty: 0,
+        ..config_args()
+    });
+    process(&mut accounts, &[0, BUMP, VAULT_BUMP, FEE_VAULT_BUMP]).unwrap();
+}
+
+#[test]
+fn test_initialize_requires_migration_fee_to_cover_caller() {
+    let initialize = |args: InitializeArgs, migration_fee, migration_bounty| {
+        let mut accounts = initialize_accounts_for(&args);
+        if args.quote_mode == QuoteMode::SplToken {
+            accounts[3].key = NATIVE_MINT;
+        }
+        accounts[5] = config_account(&ConfigArgs {
+            migration_fee,
+            migration_bounty,
+            ..Default::default()
+        });
+        process(&mut accounts, &initialize_data(&args))
+    };
+    let native = InitializeArgs {
+        quote_mode: QuoteMode::Native,
+        quote_vault_bump: BUMP,
+        ..Default::default()
+    };
+    let raydium = InitializeArgs {
+        migration_target: MigrationTarget::RaydiumCpmm,
+        ..Default::default()
+    };
+
+    // The in-house pool costs the caller nothing, so any bounty the fee covers will do
+    initialize(native, 300, 50).unwrap();
+    initialize(native, 300, 300).unwrap();
+
+    // An external pool is refunded to the caller on top of the bounty
+    initialize(raydium, RAYDIUM_CPMM_MIGRATION_FEE, 0).unwrap();
+    initialize(raydium, RAYDIUM_CPMM_MIGRATION_FEE + 50, 50).unwrap();
+    for (migration_fee, migration_bounty) in [
+        (0, 0),
+        (RAYDIUM_CPMM_MIGRATION_FEE - 1, 0),
+        (RAYDIUM_CPMM_MIGRATION_FEE + 50, 51),
+    ] {
+        assert_eq!(
+            initialize(raydium, migration_fee, migration_bounty),
+            Err(BondingCurveError::InvalidCurveParameters.into())
+        );
+    }
+}
+
+#[test]
+fn test_initialize_requires_min_protocol_fee() {
+    // The config asks for 50 bps of every trade
+    let config = config_account(&ConfigArgs {
+        migration_fee: 0,
+        migration_bounty: 0,
+        ..config_args()
+    });
+    let fees = |fee_bps, creator_share_bps| InitializeArgs {
+        fees: Fees {
+            fee_bps,
+            creator_share_bps,
+        },
+        ..Default::default()
+    };
+
+    for args in [fees(0, 0), fees(49, 0), fees(MAX_FEE_BPS, 9_501)] {
+        let mut accounts = initialize_accounts_for(&args);
+        accounts[5] = config.clone();
+        assert_eq!(
+            process(&mut accounts, &initialize_data(&args)),
+            Err(BondingCurveError::InvalidCurveParameters.into())
+        );
+    }
+
+    for args in [fees(50, 0), fees(100, 5_000), fees(MAX_FEE_BPS, 9_500)] {
+        let mut accounts = initialize_accounts_for(&args);
+        accounts[5] = config.clone();
+        process(&mut accounts, &initialize_data(&args)).unwrap();
+    }
+}
+
+#[test]
+fn test_curve_instructions_require_config() {
+    let mut accounts = initialize_accounts();
+    accounts[5].key = unique_key();
+    assert_eq!(
+        process(&mut accounts, &[0, BUMP, VAULT_BUMP, FEE_VAULT_BUMP]),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+
+    let mut curve = Curve::initialize();
+    let mut accounts = curve.trade_accounts();
+    accounts[6] = TestAccount::new(config_address(CONFIG_BUMP).unwrap(), ID, vec![]);
+    assert_eq!(
+        curve.process(accounts, &buy_data(1_000, u64::MAX)),
+        Err(ProgramError::InvalidAccountData)
+    );
+}
+
+#[test]
+fn test_paused_config_halts_trading_and_migration() {
+    let mut curve = Curve::initialize_with(&InitializeArgs {
+        initial_vtoken_reserve: 1_000,
+        initial_vsol_reserve: 1_000,
+        total_supply: 900,
+        allocation_at_migration: 500,
+        ..Default::default()
+    });
+    curve.buy(400).unwrap();
+    curve.config = config_account(&ConfigArgs {
+        paused: true,
+        ..Default::default()
+    })
+    .data;
+
+    let paused = Err(BondingCurveError::ProgramPaused.into());
+    assert_eq!(curve.buy(1), paused);
+    assert_eq!(curve.sell(1), paused);
+    assert_eq!(curve.buy_exact_in(1_000, 0), paused);
+    assert_eq!(curve.sell_exact_out(1, u64::MAX), paused);
+    assert_eq!(curve.migrate(), paused);
+
+    curve.config = config_account(&ConfigArgs::default()).data;
+    curve.sell(1).unwrap();
+}
+
+#[test]
+fn test_migrate_pays_migration_fee_to_recipient() {
+    let fee_recipient = unique_key();
+    let mut curve = Curve::complete(InitializeArgs {
+        quote_mode: QuoteMode::Native,
+        quote_vault_bump: BUMP,
+        ..Default::default()
+    });
+    curve.quote_lamports += 1_000;
+    let collateral = curve.state().vsol_reserve_amount() - 1_000;
+    assert_eq!(collateral, 1_000);
+
+    curve.charge_migration(fee_recipient, collateral + 1, 0);
+    assert_eq!(
+        curve.migrate(),
+        Err(BondingCurveError::InsufficientCollateral.into())
+    );
+
+    // Only the config fee recipient is paid
+    curve.charge_migration(fee_recipient, 300, 0);
+    let mut accounts = curve.migrate_accounts();
+    accounts[8].key = unique_key();
+    assert_eq!(
+        curve.process(accounts, &migrate_data()),
+        Err(ProgramError::InvalidArgument)
+    );
+
+    let mut accounts = curve.migrate_accounts();
+    assert_eq!(accounts[8].key, fee_recipient);
+    process(&mut accounts, &migrate_data()).unwrap();
+    assert_eq!(accounts[8].lamports, 1_000_000_000 + 300);
+    assert_eq!(accounts[12].lamports, 1_000_000_000 + 700);
+    let pool = unsafe { PoolState::from_bytes(&accounts[10].data) };
+    assert_eq!(pool.pool().quote_reserve, 700);
+}
+
+#[test]
+fn test_migrate_pays_bounty_to_caller() {
+    let mut curve = Curve::complete(InitializeArgs {
+        quote_mode: QuoteMode::Native,
+        quote_vault_bump: BUMP,
+        ..Default::default()
+    });
+    curve.quote_lamports += 1_000;
+    curve.charge_migration(unique_key(), 300, 50);
+
+    // The bounty goes to whoever migrates the curve
+    let mut accounts = curve.migrate_accounts();
+    accounts[9].key = unique_key();
+    assert_eq!(
+        curve.process(accounts, &migrate_data()),
+        Err(ProgramError::InvalidArgument)
+    );
+
+    // A native curve pays the caller itself, which comes first in the accounts
+    let mut accounts = curve.migrate_accounts();
+    assert_eq!(accounts[9].key, accounts[4].key);
+    process(&mut accounts, &migrate_data()).unwrap();
+    assert_eq!(accounts[8].lamports, 1_000_000_000 + 250);
+    assert_eq!(accounts[4].lamports, 1_000_000_000 + 50);
+    assert_eq!(accounts[12].lamports, 1_000_000_000 + 700);
+}
+
+#[test]
+fn test_migration_fee_is_paid_in_wrapped_sol() {
+    let mut curve = Curve::complete(Default::default());
+    curve.charge_migration(unique_key(), 300, 50);
+    unsafe { BondingCurveState::from_bytes_mut(&mut curve.data) }.set_vsol_mint(&NATIVE_MINT);
+    curve.quote_account = NATIVE_MINT;
+
+    // The fee is paid to a wrapped SOL account of the fee recipient
+    let mut accounts = curve.migrate_accounts();
+    accounts[8].data = token_account_data(&NATIVE_MINT, &unique_key());
+    assert_eq!(
+        curve.process(accounts, &migrate_data()),
+        Err(ProgramError::InvalidArgument)
+    );
+    let mut accounts = curve.migrate_accounts();
+    accounts[8].owner = ID;
+    assert_eq!(
+        curve.process(accounts, &migrate_data()),
+        Err(ProgramError::InvalidAccountData)
+    );
+
+    let pool = curve.graduate();
+    assert_eq!(pool.state().pool().quote_reserve, 700);
+}
+
+#[test]
+fn test_admin_transfer_takes_two_steps() {
+    let (mut config, admin) = initialize_config(&ConfigArgs::default());
+    let new_admin = unique_key();
+    let propose = [&[8][..], &new_admin].concat();
+
+    assert_eq!(
+        process_config(&mut config, new_admin, &propose),
+        Err(BondingCurveError::Unauthorized.into())
+    );
+    process_config(&mut config, admin, &propose).unwrap();
+
+    // The admin does not change until the proposed key accepts
+    let state = unsafe { GlobalConfig::from_bytes(&config) };
+    assert_eq!(state.admin(), &admin);
+    assert_eq!(state.pending_admin(), Some(&new_admin));
+    assert_eq!(
+        process_config(&mut config, unique_key(), &[9]),
+        Err(BondingCurveError::Unauthorized.into())
+    );
+
+    process_config(&mut config, new_admin, &[9]).unwrap();
+    let state = unsafe { GlobalConfig::from_bytes(&config) };
+    assert_eq!(state.admin(), &new_admin);
+    assert_eq!(state.pending_admin(), None);
+
+    // The previous admin lost its authority, and the proposal cannot be replayed
+    let update = [&[7][..], &ConfigArgs::default().to_bytes()].concat();
+    assert_eq!(
+        process_config(&mut config, admin, &update),
+        Err(BondingCurveError::Unauthorized.into())
+    );
+    assert_eq!(
+        process_config(&mut config, new_admin, &[9]),
+        Err(BondingCurveError::Unauthorized.into())
+    );
+    process_config(&mut config, new_admin, &update).unwrap();
+}
+
+#[test]
+fn test_admin_proposal_can_be_cancelled() {
+    let (mut config, admin) = initialize_config(&ConfigArgs::default());
+    let new_admin = unique_key();
+
+    process_config(&mut config, admin, &[&[8][..], &new_admin].concat()).unwrap();
+    assert_eq!(
+        process_config(&mut config, new_admin, &[10]),
+        Err(BondingCurveError::Unauthorized.into())
+    );
+    process_config(&mut config, admin, &[10]).unwrap();
+
+    assert_eq!(
+        unsafe { GlobalConfig::from_bytes(&config) }.pending_admin(),
+        None
+    );
+    assert_eq!(
+        process_config(&mut config, new_admin, &[9]),
+        Err(BondingCurveError::Unauthorized.into())
+    );
+
+    // Nobody can be proposed by accident through a truncated or empty key
+    assert_eq!(
+        process_config(&mut config, admin, &[&[8][..], &new_admin[..31]].concat()),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    assert_eq!(
+        process_config(&mut config, admin, &[&[8][..], &[0; 32]].concat()),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn test_admin_pauses_program() {
+    let (mut config, admin) = initialize_config(&ConfigArgs::default());
+    let mut curve = Curve::initialize();
+    curve.buy(100_000_000).unwrap();
+
+    assert_eq!(
+        process_config(&mut config, unique_key(), &[11, 1]),
+        Err(BondingCurveError::Unauthorized.into())
+    );
+    assert_eq!(
+        process_config(&mut config, admin, &[11, 2]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    process_config(&mut config, admin, &[11, 1]).unwrap();
+    assert!(unsafe { GlobalConfig::from_bytes(&config) }.paused());
+
+    curve.config = config.clone();
+    assert_eq!(
+        curve.sell(1_000),
+        Err(BondingCurveError::ProgramPaused.into())
+    );
+    // Quotes only read the curve state
+    BondingCurve::from_state(curve.state())
+        .unwrap()
+        .refund_for_sell(1_000)
+        .unwrap();
+
+    process_config(&mut config, admin, &[11, 0]).unwrap();
+    curve.config = config;
+    curve.sell(1_000).unwrap();
+}
+
+#[test]
+fn test_admin_pauses_single_curve() {
+    let (config, admin) = initialize_config(&ConfigArgs::default());
+    let mut curve = Curve::initialize_with(&InitializeArgs {
+        initial_vtoken_reserve: 1_000,
+        initial_vsol_reserve: 1_000,
+        total_supply: 900,
+        allocation_at_migration: 500,
+        ..Default::default()
+    });
+    let mut other = Curve::initialize();
+    curve.config = config.clone();
+    other.config = config.clone();
+    curve.buy(500).unwrap();
+
+    let set_curve_paused = |curve: &mut Curve, signer: Pubkey, paused: u8| {
+        let mut accounts = [
+            curve.config_account(),
+            TestAccount::new(signer, ID, vec![]).signer(),
+            TestAccount::new(curve.key, ID, curve.data.clone()),
+        ];
+        process(&mut accounts, &[12, paused])?;
+        curve.data = accounts[2].data.clone();
+        Ok::<_, ProgramError>(())
+    };
+
+    assert_eq!(
+        set_curve_paused(&mut curve, unique_key(), 1),
+        Err(BondingCurveError::Unauthorized.into())
+    );
+    set_curve_paused(&mut curve, admin, 1).unwrap();
+    assert!(curve.state().paused());
+
+    let paused = Err(BondingCurveError::CurvePaused.into());
+    assert_eq!(curve.buy(1), paused);
+    assert_eq!(curve.sell(1), paused);
+    assert_eq!(curve.buy_exact_in(1_000, 0), paused);
+    assert_eq!(curve.sell_exact_out(1, u64::MAX), paused);
+    assert_eq!(curve.migrate(), paused);
+
+    // Other curves keep trading
+    other.buy(1_000).unwrap();
+
+    set_curve_paused(&mut curve, admin, 0).unwrap();
+    curve.migrate().unwrap();
+}
+
+#[test]
+fn test_claim_fees_pays_each_recipient_once() {
+    let fee_recipient = unique_key();
+    let mut curve = Curve::initialize_with_fees(QuoteMode::Native);
+    curve.config = config_account(&ConfigArgs {
+        fee_recipient,
+        ..Default::default()
+    })
+    .data;
+    curve.buy(100_000_000).unwrap();
+    let (protocol, creator) = (curve.state().protocol_fees(), curve.state().creator_fees());
+    // Stands in for the system transfer of the fee made by the buy
+    curve.fee_vault_lamports += protocol + creator;
+    let rent = sysvars::rent().unwrap().minimum_balance(0);
+
+    assert_eq!(
+        curve.claim(13, curve.creator).0,
+        Err(BondingCurveError::Unauthorized.into())
+    );
+    assert_eq!(
+        curve.claim(14, fee_recipient).0,
+        Err(BondingCurveError::Unauthorized.into())
+    );
+
+    assert_eq!(curve.claim(13, fee_recipient), (Ok(()), protocol));
+    assert_eq!(curve.state().protocol_fees(), 0);
+    assert_eq!(curve.state().creator_fees(), creator);
+    assert_eq!(curve.fee_vault_lamports, rent + creator);
+
+    assert_eq!(curve.claim(14, curve.creator), (Ok(()), creator));
+    assert_eq!(curve.state().creator_fees(), 0);
+    assert_eq!(curve.fee_vault_lamports, rent);
+
+    // Nothing is left to claim
+    assert_eq!(curve.claim(13, fee_recipient), (Ok(()), 0));
+}
+
+#[test]
+fn test_claim_fees_requires_recipient_signature_and_vault() {
+    let mut curve = Curve::initialize_with_fees(QuoteMode::SplToken);
+    curve.buy(100_000_000).unwrap();
+
+    let mut accounts = vec![
+        TestAccount::new(curve.key, ID, curve.data.clone()),
+        curve.config_account(),
+        TestAccount::new(curve.creator, ID, vec![]),
+        curve.fee_vault(),
+        TestAccount::new(unique_key(), TOKEN_PROGRAM, vec![]),
+        TestAccount::new(TOKEN_PROGRAM, ID, vec![]).readonly(),
+    ];
+    assert_eq!(
+        process(&mut accounts, &[14]),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+
+    accounts[2].is_signer = true;
+    accounts[3].key = unique_key();
+    assert_eq!(
+        process(&mut accounts, &[14]),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+
+    let creator_fees = curve.state().creator_fees();
+    assert!(creator_fees > 0);
+    // Token fees are moved by the token program, not out of the vault's lamports
+    let fee_vault_lamports = curve.fee_vault_lamports;
+    assert_eq!(curve.claim(14, curve.creator), (Ok(()), 0));
+    assert_eq!(curve.fee_vault_lamports, fee_vault_lamports);
+    assert_eq!(curve.state().creator_fees(), 0);
+    assert!(curve.state().protocol_fees() > 0);
+}
+
+#[test]
+fn test_trades_require_fee_vault() {
+    let mut curve = Curve::initialize_with_fees(QuoteMode::SplToken);
+
+    let mut accounts = curve.trade_accounts();
+    accounts[7].key = unique_key();
+    assert_eq!(
+        curve.process(accounts, &buy_data(1_000, u64::MAX)),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+
+    let mut accounts = initialize_accounts();
+    accounts[6].key = unique_key();
+    assert_eq!(
+        process(&mut accounts, &initialize_data(&InitializeArgs::default())),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+}
+
+#[test]
+fn test_initialize_referrer_stats() {
+    let referrer = unique_key();
+    let stats = referrer_stats(referrer);
+    let stats = unsafe { ReferrerStats::from_bytes(&stats.data) };
+    assert_eq!(stats.bump(), REFERRER_STATS_BUMP);
+    assert_eq!(stats.referrer(), &referrer);
+    assert_eq!(stats.total_fees(), 0);
+
+    let mut accounts = referrer_stats_accounts(referrer);
+    accounts[0].key = unique_key();
+    assert_eq!(
+        process(&mut accounts, &[15, REFERRER_STATS_BUMP]),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+
+    // Only the admin approves referrers
+    let mut accounts = referrer_stats_accounts(referrer);
+    accounts[3].key = unique_key();
+    assert_eq!(
+        process(&mut accounts, &[15, REFERRER_STATS_BUMP]),
+        Err(BondingCurveError::Unauthorized.into())
+    );
+    let mut accounts = referrer_stats_accounts(referrer);
+    accounts[3].is_signer = false;
+    assert_eq!(
+        process(&mut accounts, &[15, REFERRER_STATS_BUMP]),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+}
+
+#[test]
+fn test_referrer_is_paid_share_of_fee() {
+    let mut curve = Curve::initialize_with_fees(QuoteMode::Native);
+    curve.config = config_account(&config_args()).data;
+    let mut referrer = TestAccount::new(unique_key(), SYSTEM_PROGRAM, vec![]);
+    referrer.lamports = 0;
+    let mut stats = referrer_stats(referrer.key);
+
+    let mut trade = |curve: &mut Curve, data: &[u8]| {
+        let mut accounts = curve.trade_accounts();
+        accounts.push(referrer.clone());
+        accounts.push(stats.clone());
+        process(&mut accounts, data).unwrap();
+        curve.data = accounts[0].data.clone();
+        curve.quote_lamports = accounts[4].lamports;
+        curve.fee_vault_lamports = accounts[7].lamports;
+        referrer = accounts[9].clone();
+        stats = accounts[10].clone();
+    };
+
+    trade(&mut curve, &buy_data(100_000_000, u64::MAX));
+    // 3_083_247_688 * 1% rounded up, a fifth of it to the referrer and a quarter to the
+    // creator
+    let buy_fee = 30_832_477;
+    assert_eq!(curve.state().creator_fees(), buy_fee / 4);
+    assert_eq!(
+        curve.state().protocol_fees(),
+        buy_fee - buy_fee / 4 - buy_fee / 5
+    );
+    // Stands in for the system transfer made by the buy
+    curve.quote_lamports += curve.state().vsol_reserve_amount() - 30_000_000_000;
+
+    let refund = BondingCurve::from_state(curve.state())
+        .unwrap()
+        .refund_for_sell(100_000_000)
+        .unwrap();
+    let sell_fee = curve.state().fees().fee_on(refund).unwrap();
+    let sell_data = [&[2][..], &100_000_000u64.to_le_bytes(), &0u64.to_le_bytes()].concat();
+    let fee_vault_lamports = curve.fee_vault_lamports;
+    trade(&mut curve, &sell_data);
+
+    // Only the sell pays out of the program's vaults off-chain
+    assert_eq!(referrer.lamports, sell_fee / 5);
+    assert_eq!(
+        curve.fee_vault_lamports,
+        fee_vault_lamports + sell_fee - sell_fee / 5
+    );
+
+    let stats = unsafe { ReferrerStats::from_bytes(&stats.data) };
+    assert_eq!(stats.total_fees(), buy_fee / 5 + sell_fee / 5);
+    assert_eq!(stats.trade_count(), 2);
+}
+
+#[test]
+fn test_referrer_requires_its_stats() {
+    let curve = Curve::initialize_with_fees(QuoteMode::SplToken);
+    let referrer = TestAccount::new(unique_key(), TOKEN_PROGRAM, vec![]);
+
+    let mut accounts = curve.trade_accounts();
+    accounts.push(referrer.clone());
+    assert_eq!(
+        process(&mut accounts, &buy_data(1_000, u64::MAX)),
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+
+    // Stats of another referrer
+    let mut accounts = curve.trade_accounts();
+    accounts.push(referrer);
+    accounts.push(referrer_stats(unique_key()));
+    assert_eq!(
+        process(&mut accounts, &buy_data(1_000, u64::MAX)),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+}
+
+#[test]
+fn test_trader_cannot_refer_itself() {
+    let curve = Curve::initialize_with_fees(QuoteMode::Native);
+    let mut accounts = curve.trade_accounts();
+    let trader = accounts[1].clone();
+    accounts.push(TestAccount::new(trader.key, SYSTEM_PROGRAM, vec![]));
+    accounts.push(referrer_stats(trader.key));
+    assert_eq!(
+        process(&mut accounts, &buy_data(1_000, u64::MAX)),
+        Err(BondingCurveError::InvalidReferrer.into())
+    );
+
+    // Nor through a token account it owns
+    let curve = Curve::initialize_with_fees(QuoteMode::SplToken);
+    let mut accounts = curve.trade_accounts();
+    let referrer = TestAccount::new(
+        unique_key(),
+        TOKEN_PROGRAM,
+        token_account_data(&curve.quote_account, &accounts[1].key),
+    );
+    accounts.push(referrer.clone());
+    accounts.push(referrer_stats(referrer.key));
+    assert_eq!(
+        process(&mut accounts, &buy_data(1_000, u64::MAX)),
+        Err(BondingCurveError::InvalidReferrer.into())
+    );
+}
+
+#[test]
+fn test_instructions_reject_state_off_its_address() {
+    let mut curve = Curve::initialize();
+    let state = curve.key;
+    curve.key = unique_key();
+    assert_eq!(curve.buy(1_000), Err(BondingCurveError::InvalidPda.into()));
+    assert_eq!(curve.sell(1_000), Err(BondingCurveError::InvalidPda.into()));
+    assert_eq!(curve.migrate(), Err(BondingCurveError::InvalidPda.into()));
+
+    let mut accounts = initialize_accounts();
+    accounts[0].key = state;
+    assert_eq!(
+        process(&mut accounts, &initialize_data(&InitializeArgs::default())),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+}
+
+#[test]
+fn test_initialize_requires_mint_signature() {
+    let mut accounts = initialize_accounts();
+    accounts[1].is_signer = false;
+    assert_eq!(
+        process(&mut accounts, &initialize_data(&InitializeArgs::default())),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+}
+
+#[test]
+fn test_buy_requires_curve_controlled_mint() {
+    let mut curve = Curve::initialize();
+    let key = curve.key;
+
+    let mut wrong_decimals = mint_data(Some(&key), None);
+    wrong_decimals[44] = TOKEN_DECIMALS + 1;
+
+    for data in [
+        mint_data(Some(&unique_key()), None),
+        mint_data(Some(&key), Some(&unique_key())),
+        wrong_decimals,
+        mint_data(None, None),
+    ] {
+        let mut accounts = curve.trade_accounts();
+        accounts[3].data = data;
+        assert_eq!(
+            curve.process(accounts, &buy_data(1_000, u64::MAX)),
+            Err(BondingCurveError::InvalidMintAuthority.into())
+        );
+    }
+
+    let mut accounts = curve.trade_accounts();
+    accounts[3].owner = unique_key();
+    assert_eq!(
+        curve.process(accounts, &buy_data(1_000, u64::MAX)),
+        Err(ProgramError::InvalidAccountOwner)
+    );
+}
+
+#[test]
+fn test_pre_minted_curve_trades_out_of_token_vault() {
+    let mut curve = Curve::initialize_pre_minted();
+    assert_eq!(curve.state().supply_mode(), Ok(SupplyMode::PreMinted));
+    assert_eq!(curve.state().token_vault_bump(), TOKEN_VAULT_BUMP);
+
+    curve.buy(100_000_000).unwrap();
+    curve.sell(40_000_000).unwrap();
+    assert_eq!(
+        curve.state().vtoken_reserve_amount(),
+        INITIAL_VTOKEN - 60_000_000
+    );
+}
+
+#[test]
+fn test_initialize_pre_minted_requires_token_vault() {
+    let mut accounts = initialize_accounts_for(&pre_minted_args());
+    accounts.pop();
+    assert_eq!(
+        process(&mut accounts, &initialize_data(&pre_minted_args())),
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+
+    let mut accounts = initialize_accounts_for(&pre_minted_args());
+    accounts[8].key = unique_key();
+    assert_eq!(
+        process(&mut accounts, &initialize_data(&pre_minted_args())),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+}
+
+#[test]
+fn test_pre_minted_trades_require_token_vault() {
+    let mut curve = Curve::initialize_pre_minted();
+
+    let mut accounts = curve.trade_accounts();
+    accounts.pop();
+    assert_eq!(
+        curve.process(accounts, &buy_data(1_000, u64::MAX)),
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+
+    let mut accounts = curve.trade_accounts();
+    accounts[10].key = unique_key();
+    assert_eq!(
+        curve.process(accounts, &buy_data(1_000, u64::MAX)),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+}
+
+#[test]
+fn test_pre_minted_supply_is_capped() {
+    let mut curve = Curve::initialize_pre_minted();
+    let key = curve.key;
+
+    // Nobody may mint once the supply is minted, nor may it exceed the total supply
+    let mut over_supply = mint_data(None, None);
+    over_supply[36..44].copy_from_slice(&(TOTAL_SUPPLY + 1).to_le_bytes());
+
+    for data in [mint_data(Some(&key), None), over_supply] {
+        let mut accounts = curve.trade_accounts();
+        accounts[3].data = data;
+        assert_eq!(
+            curve.process(accounts, &buy_data(1_000, u64::MAX)),
+            Err(BondingCurveError::InvalidMintAuthority.into())
+        );
+    }
+
+    let mut full_supply = mint_data(None, None);
+    full_supply[36..44].copy_from_slice(&TOTAL_SUPPLY.to_le_bytes());
+    let mut accounts = curve.trade_accounts();
+    accounts[3].data = full_supply;
+    curve.process(accounts, &buy_data(1_000, u64::MAX)).unwrap();
+}
+
+#[test]
+fn test_migrate_seeds_pool() {
+    let mut curve = Curve::complete(Default::default());
+    let pool = curve.graduate();
+
+    assert_eq!(curve.state().status(), Ok(CurveStatus::Migrated));
+    let state = pool.state();
+    assert_eq!(state.curve(), &curve.key);
+    assert_eq!(state.token_mint(), &curve.vtoken_mint);
+    assert_eq!(state.quote_mint(), &curve.quote_account);
+    assert_eq!(state.quote_mode(), Ok(QuoteMode::SplToken));
+    assert_eq!(state.lp_mint_bump(), LP_MINT_BUMP);
+    assert_eq!(state.lp_vault_bump(), LP_VAULT_BUMP);
+    assert_eq!(state.pool(), Pool::seed(400, 1_000).unwrap());
+}
+
+#[test]
+fn test_migrate_native_curve_moves_collateral_to_pool() {
+    let mut curve = Curve::complete(InitializeArgs {
+        quote_mode: QuoteMode::Native,
+        quote_vault_bump: BUMP,
+        ..Default::default()
+    });
+    curve.quote_lamports += 1_000;
+    let quote_lamports = curve.quote_lamports;
+    let pool = curve.graduate();
+
+    assert_eq!(pool.state().quote_mode(), Ok(QuoteMode::Native));
+    assert_eq!(curve.quote_lamports, quote_lamports - 1_000);
+    assert_eq!(pool.quote_vault.lamports, 1_000_000_000 + 1_000);
+}
+
+#[test]
+fn test_migrate_pre_minted_curve() {
+    let mut curve = Curve::complete(pre_minted_args());
+    let pool = curve.graduate();
+    assert_eq!(pool.state().pool().token_reserve, 400);
+
+    let mut curve = Curve::complete(pre_minted_args());
+    let mut accounts = curve.migrate_accounts();
+    accounts.pop();
+    assert_eq!(
+        curve.process(accounts, &migrate_data()),
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+}
+
+#[test]
+fn test_migrate_checks_pool_accounts() {
+    let mut curve = Curve::complete(Default::default());
+
+    for index in 10..15 {
+        let mut accounts = curve.migrate_accounts();
+        accounts[index].key = unique_key();
+        assert_eq!(
+            curve.process(accounts, &migrate_data()),
+            Err(BondingCurveError::InvalidPda.into())
+        );
+    }
+
+    let mut accounts = curve.migrate_accounts();
+    accounts[4].is_signer = false;
+    assert_eq!(
+        curve.process(accounts, &migrate_data()),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+
+    assert_eq!(
+        curve.process(curve.migrate_accounts(), &migrate_data()[..5]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    assert_eq!(curve.state().status(), Ok(CurveStatus::Complete));
+}
+
+#[test]
+fn test_swap_trades_against_pool_reserves() {
+    let mut pool = Curve::complete(Default::default()).graduate();
+    let seeded = pool.state().pool();
+
+    let tokens_out = seeded.swap_out(SwapDirection::QuoteToToken, 100).unwrap();
+    pool.swap(SwapDirection::QuoteToToken, 100, tokens_out)
+        .unwrap();
+    let after_buy = pool.state().pool();
+    assert_eq!(after_buy.quote_reserve, seeded.quote_reserve + 100);
+    assert_eq!(after_buy.token_reserve, seeded.token_reserve - tokens_out);
+
+    let quote_out = after_buy
+        .swap_out(SwapDirection::TokenToQuote, tokens_out)
+        .unwrap();
+    pool.swap(SwapDirection::TokenToQuote, tokens_out, quote_out)
+        .unwrap();
+    let after_sell = pool.state().pool();
+    assert_eq!(after_sell.token_reserve, seeded.token_reserve);
+    assert!(after_sell.quote_reserve > seeded.quote_reserve);
+    assert_eq!(after_sell.lp_supply, seeded.lp_supply);
+}
+
+#[test]
+fn test_swap_rejects_output_below_limit() {
+    let mut pool = Curve::complete(Default::default()).graduate();
+    let tokens_out = pool
+        .state()
+        .pool()
+        .swap_out(SwapDirection::QuoteToToken, 100)
+        .unwrap();
+
+    assert_eq!(
+        pool.swap(SwapDirection::QuoteToToken, 100, tokens_out + 1),
+        Err(BondingCurveError::SlippageExceeded.into())
+    );
+
+    let data = [&[16, 2][..], &100u64.to_le_bytes(), &0u64.to_le_bytes()].concat();
+    assert_eq!(
+        pool.process(pool.swap_accounts(), &data),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn test_native_swap_pays_out_of_quote_vault() {
+    let mut curve = Curve::complete(InitializeArgs {
+        quote_mode: QuoteMode::Native,
+        quote_vault_bump: BUMP,
+        ..Default::default()
+    });
+    curve.quote_lamports += 1_000;
+    let mut pool = curve.graduate();
+    let vault_lamports = pool.quote_vault.lamports;
+
+    let quote_out = pool
+        .state()
+        .pool()
+        .swap_out(SwapDirection::TokenToQuote, 100)
+        .unwrap();
+    pool.swap(SwapDirection::TokenToQuote, 100, quote_out)
+        .unwrap();
+    assert_eq!(pool.quote_vault.lamports, vault_lamports - quote_out);
+}
+
+#[test]
+fn test_liquidity_round_trip() {
+    let mut pool = Curve::complete(Default::default()).graduate();
+    let seeded = pool.state().pool();
+
+    // Deposits are taken at the pool ratio, 2.5 quote per token
+    pool.add_liquidity(100, 1_000, 0).unwrap();
+    let deposited = pool.state().pool();
+    let lp = deposited.lp_supply - seeded.lp_supply;
+    assert_eq!(lp, 158);
+    assert!(deposited.token_reserve - seeded.token_reserve <= 100);
+    assert!(deposited.quote_reserve - seeded.quote_reserve <= 250);
+
+    assert_eq!(
+        pool.remove_liquidity(lp, 101, 0),
+        Err(BondingCurveError::SlippageExceeded.into())
+    );
+    pool.remove_liquidity(lp, 0, 0).unwrap();
+    let withdrawn = pool.state().pool();
+    assert_eq!(withdrawn.lp_supply, seeded.lp_supply);
+    assert!(withdrawn.token_reserve >= seeded.token_reserve);
+    assert!(withdrawn.quote_reserve >= seeded.quote_reserve);
+
+    assert_eq!(
+        pool.add_liquidity(100, 1_000, 159),
+        Err(BondingCurveError::SlippageExceeded.into())
+    );
+
+    let mut accounts = pool.liquidity_accounts();
+    accounts[7].key = unique_key();
+    assert_eq!(
+        pool.process(accounts, &[&[17][..], &[0; 24]].concat()),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+}
+
+#[test]
+fn test_migrate_to_external_targets() {
+    for (target, program, dex_len) in [
+        (
+            MigrationTarget::RaydiumCpmm,
+            RAYDIUM_CPMM_ID,
+            RAYDIUM_CPMM_ACCOUNTS_LEN,
+        ),
+        (
+            MigrationTarget::MeteoraDamm,
+            METEORA_DAMM_ID,
+            METEORA_DAMM_ACCOUNTS_LEN,
+        ),
+    ] {
+        let locking = InitializeArgs {
+            lp_policy: LpPolicy::Lock,
+            lp_unlock_timestamp: 1,
+            ..pre_minted_args()
+        };
+        let vesting = InitializeArgs {
+            unsold_token_policy: UnsoldTokenPolicy::Vest,
+            vesting_duration: 100,
+            ..locking
+        };
+        for args in [
+            InitializeArgs::default(),
+            pre_minted_args(),
+            locking,
+            vesting,
+        ] {
+            let mut curve = Curve::complete_external(target, args);
+
+            // The in-house pool accounts are not enough for an external target
+            assert_eq!(
+                curve.process(curve.migrate_accounts(), &migrate_data()),
+                Err(ProgramError::NotEnoughAccountKeys)
+            );
+
+            let accounts = curve.external_migrate_accounts(program, dex_len);
+            let data = curve.with_trailing_bumps(external_migrate_data());
+            curve.process(accounts, &data).unwrap();
+            assert_eq!(curve.state().status(), Ok(CurveStatus::Migrated));
+            // The seed liquidity is locked and the unsold tokens beyond the pool price
+            // vest, as for the in-house pool
+            let state = curve.state();
+            let locked = state.lp_policy() == Ok(LpPolicy::Lock);
+            assert_eq!(state.lp_lock_bump(), if locked { LP_LOCK_BUMP } else { 0 });
+            let vested = state.unsold_token_policy() == Ok(UnsoldTokenPolicy::Vest);
+            // The collateral left after the migration fee buys a quarter as many tokens
+            // at the final price of 4
+            let pool_tokens = (1_000_000_000 - target.fee()) / 4;
+            let vesting_amount = if vested { 400_000_000 - pool_tokens } else { 0 };
+            assert_eq!(state.vesting_amount(), vesting_amount);
+        }
+    }
+}
+
+#[test]
+fn test_external_migration_checks_accounts() {
+    let mut curve = Curve::complete_external(MigrationTarget::RaydiumCpmm, Default::default());
+    let accounts = curve.external_migrate_accounts(RAYDIUM_CPMM_ID, RAYDIUM_CPMM_ACCOUNTS_LEN);
+
+    // Even without a bounty, the caller is refunded what the pool costs it
+    let mut wrong_refund = accounts.clone();
+    wrong_refund[9].data = token_account_data(&NATIVE_MINT, &unique_key());
+    assert_eq!(
+        curve.process(wrong_refund, &external_migrate_data()),
+        Err(ProgramError::InvalidArgument)
+    );
+
+    // The migration authority and its token accounts are derived by the program
+    for index in 10..13 {
+        let mut accounts = accounts.clone();
+        accounts[index].key = unique_key();
+        assert_eq!(
+            curve.process(accounts, &external_migrate_data()),
+            Err(BondingCurveError::InvalidPda.into())
+        );
+    }
+
+    let mut wrong_program = accounts.clone();
+    wrong_program[13].key = METEORA_DAMM_ID;
+    assert_eq!(
+        curve.process(wrong_program, &external_migrate_data()),
+        Err(ProgramError::IncorrectProgramId)
+    );
+
+    // The creator of the Raydium pool must be the migration authority
+    let mut wrong_creator = accounts.clone();
+    wrong_creator[14].key = unique_key();
+    assert_eq!(
+        curve.process(wrong_creator, &external_migrate_data()),
+        Err(BondingCurveError::InvalidMigrationAccount.into())
+    );
+
+    // The seed liquidity is only taken from the authority's liquidity account
+    let mut wrong_lp_account = accounts.clone();
+    wrong_lp_account[23].data = token_account_data(&accounts[20].key, &unique_key());
+    assert_eq!(
+        curve.process(wrong_lp_account, &external_migrate_data()),
+        Err(BondingCurveError::InvalidMigrationAccount.into())
+    );
+
+    assert_eq!(
+        curve.process(accounts, &external_migrate_data()[..3]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    assert_eq!(curve.state().status(), Ok(CurveStatus::Complete));
+}
+
+#[test]
+fn test_migrate_locks_lp_for_creator() {
+    let mut curve = Curve::locking();
+
+    let mut accounts = curve.migrate_accounts();
+    accounts.pop();
+    assert_eq!(
+        curve.process(accounts, &curve.with_trailing_bumps(migrate_data())),
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+
+    // The lock is derived from the curve, with the bump that comes last
+    let mut accounts = curve.migrate_accounts();
+    accounts[15].key = unique_key();
+    assert_eq!(
+        curve.process(accounts, &curve.with_trailing_bumps(migrate_data())),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+    assert_eq!(
+        curve.process(curve.migrate_accounts(), &migrate_data()),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+
+    let pool = curve.graduate();
+    assert_eq!(curve.state().status(), Ok(CurveStatus::Migrated));
+    assert_eq!(curve.state().lp_lock_bump(), LP_LOCK_BUMP);
+
+    curve.unlock_lp();
+    let accounts = curve.claim_locked_lp_accounts(&pool.lp_mint);
+    curve.process(accounts, &[19]).unwrap();
+}
+
+#[test]
+fn test_claim_locked_lp_waits_for_unlock() {
+    let mut curve = Curve::locking();
+    let pool = curve.graduate();
+
+    assert_eq!(
+        curve.process(curve.claim_locked_lp_accounts(&pool.lp_mint), &[19]),
+        Err(BondingCurveError::LpLocked.into())
+    );
+
+    curve.unlock_lp();
+    curve
+        .process(curve.claim_locked_lp_accounts(&pool.lp_mint), &[19])
+        .unwrap();
+}
+
+#[test]
+fn test_claim_locked_lp_checks_accounts() {
+    let mut curve = Curve::locking();
+    let lp_mint = unique_key();
+
+    // Nothing is locked before the curve migrates
+    assert_eq!(
+        curve.process(curve.claim_locked_lp_accounts(&lp_mint), &[19]),
+        Err(BondingCurveError::NotMigrated.into())
+    );
+    curve.graduate();
+
+    let mut accounts = curve.claim_locked_lp_accounts(&lp_mint);
+    accounts[1].key = unique_key();
+    assert_eq!(
+        curve.process(accounts, &[19]),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+
+    // Only the creator can claim
+    let mut accounts = curve.claim_locked_lp_accounts(&lp_mint);
+    accounts[2].key = unique_key();
+    assert_eq!(
+        curve.process(accounts, &[19]),
+        Err(BondingCurveError::Unauthorized.into())
+    );
+    let mut accounts = curve.claim_locked_lp_accounts(&lp_mint);
+    accounts[2].is_signer = false;
+    assert_eq!(
+        curve.process(accounts, &[19]),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+
+    // Burned liquidity has no lock
+    let mut curve = Curve::complete(Default::default());
+    curve.graduate();
+    assert_eq!(
+        curve.process(curve.claim_locked_lp_accounts(&lp_mint), &[19]),
+        Err(BondingCurveError::NothingToClaim.into())
+    );
+}
+
+#[test]
+fn test_migrate_burns_unsold_tokens_beyond_pool_price() {
+    // The pool takes the 250 tokens 1_000 of collateral buys at the final price of 4,
+    // and the other 150 unsold tokens are burned
+    for args in [InitializeArgs::default(), pre_minted_args()] {
+        let mut curve = Curve::complete(InitializeArgs {
+            unsold_token_policy: UnsoldTokenPolicy::Burn,
+            ..args
+        });
+        let pool = curve.graduate();
+        assert_eq!(pool.state().pool(), Pool::seed(250, 1_000).unwrap());
+        assert_eq!(curve.state().vesting_amount(), 0);
+    }
+
+    // By default the pool takes every unsold token
+    let mut curve = Curve::complete(pre_minted_args());
+    let pool = curve.graduate();
+    assert_eq!(pool.state().pool(), Pool::seed(400, 1_000).unwrap());
+}
+
+#[test]
+fn test_migrate_vests_unsold_tokens_for_creator() {
+    let mut curve = Curve::vesting();
+
+    let mut accounts = curve.migrate_accounts();
+    accounts.pop();
+    assert_eq!(
+        curve.process(accounts, &curve.with_trailing_bumps(migrate_data())),
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+
+    // The vesting account is derived from the curve, with the bump that comes last
+    let mut accounts = curve.migrate_accounts();
+    accounts[15].key = unique_key();
+    assert_eq!(
+        curve.process(accounts, &curve.with_trailing_bumps(migrate_data())),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+    assert_eq!(
+        curve.process(curve.migrate_accounts(), &migrate_data()),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+
+    let pool = curve.graduate();
+    assert_eq!(pool.state().pool(), Pool::seed(250, 1_000).unwrap());
+    let state = curve.state();
+    assert_eq!(state.vesting_bump(), VESTING_BUMP);
+    assert_eq!(state.vesting_amount(), 150);
+    // Host builds run at the Unix epoch
+    assert_eq!(state.vesting_start(), 0);
+
+    // Everything has vested once the duration passed
+    curve.elapse_vesting(100);
+    curve
+        .process(curve.claim_vested_tokens_accounts(), &[20])
+        .unwrap();
+    assert_eq!(curve.state().vesting_claimed(), 150);
+    curve
+        .process(curve.claim_vested_tokens_accounts(), &[20])
+        .unwrap();
+    assert_eq!(curve.state().vesting_claimed(), 150);
+}
+
+#[test]
+fn test_claim_vested_tokens_releases_over_duration() {
+    let mut curve = Curve::vesting();
+    curve.graduate();
+
+    curve
+        .process(curve.claim_vested_tokens_accounts(), &[20])
+        .unwrap();
+    assert_eq!(curve.state().vesting_amount(), 150);
+    assert_eq!(curve.state().vesting_claimed(), 0);
+
+    curve.elapse_vesting(50);
+    curve
+        .process(curve.claim_vested_tokens_accounts(), &[20])
+        .unwrap();
+    assert_eq!(curve.state().vesting_claimed(), 75);
+}
+
+#[test]
+fn test_claim_vested_tokens_checks_accounts() {
+    let mut curve = Curve::vesting();
+
+    // Nothing vests before the curve migrates
+    assert_eq!(
+        curve.process(curve.claim_vested_tokens_accounts(), &[20]),
+        Err(BondingCurveError::NotMigrated.into())
+    );
+    curve.graduate();
+
+    let mut accounts = curve.claim_vested_tokens_accounts();
+    accounts[1].key = unique_key();
+    assert_eq!(
+        curve.process(accounts, &[20]),
+        Err(BondingCurveError::InvalidPda.into())
+    );
+
+    // Only the creator can claim
+    let mut accounts = curve.claim_vested_tokens_accounts();
+    accounts[2].key = unique_key();
+    assert_eq!(
+        curve.process(accounts, &[20]),
+        Err(BondingCurveError::Unauthorized.into())
+    );
+    let mut accounts = curve.claim_vested_tokens_accounts();
+    accounts[2].is_signer = false;
+    assert_eq!(
+        curve.process(accounts, &[20]),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+
+    // Burned or pooled tokens have no vesting account
+    for unsold_token_policy in [UnsoldTokenPolicy::SeedPool, UnsoldTokenPolicy::Burn] {
+        let mut curve = Curve::complete(InitializeArgs {
+            unsold_token_policy,
+            ..Default::default()
+        });
+        curve.graduate();
+        assert_eq!(
+            curve.process(curve.claim_vested_tokens_accounts(), &[20]),
+            Err(BondingCurveError::NothingToClaim.into())
+        );
+    }
+}
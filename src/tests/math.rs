@@ -0,0 +1,296 @@
+use num_bigint::BigUint;
+
+use crate::{
+    curve::{ConstantProduct, Curve, Exponential, Linear},
+    error::BondingCurveError,
+    math::{exp, ln, mul_div, sqrt, Rounding, ONE},
+};
+
+/// Deterministic xorshift generator, so failures reproduce.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A value of random bit width, so small and large operands are both common.
+    fn next_u128(&mut self) -> u128 {
+        let value = ((self.next_u64() as u128) << 64) | self.next_u64() as u128;
+        value >> (self.next_u64() % 128)
+    }
+
+    /// A non-zero `u64` of random bit width.
+    fn next_sized(&mut self) -> u64 {
+        let shift = self.next_u64() % 64;
+        (self.next_u64() >> shift).max(1)
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+}
+
+fn big(value: u128) -> BigUint {
+    BigUint::from(value)
+}
+
+/// `numerator / denominator` rounded as requested, or `None` past `u128::MAX`.
+fn reference_div(numerator: BigUint, denominator: BigUint, rounding: Rounding) -> Option<u128> {
+    let mut quotient = &numerator / &denominator;
+    if rounding == Rounding::Up && quotient.clone() * &denominator != numerator {
+        quotient += 1u32;
+    }
+    u128::try_from(quotient).ok()
+}
+
+fn overflow<T>() -> Result<T, pinocchio::program_error::ProgramError> {
+    Err(BondingCurveError::MathOverflow.into())
+}
+
+const EDGES: [u128; 10] = [
+    0,
+    1,
+    2,
+    3,
+    u64::MAX as u128,
+    1 << 64,
+    (1 << 64) + 1,
+    1 << 127,
+    u128::MAX - 1,
+    u128::MAX,
+];
+
+fn check_mul_div(a: u128, b: u128, denominator: u128) {
+    for rounding in [Rounding::Down, Rounding::Up] {
+        let expected = if denominator == 0 {
+            overflow()
+        } else {
+            reference_div(big(a) * big(b), big(denominator), rounding).map_or(overflow(), Ok)
+        };
+        assert_eq!(
+            mul_div(a, b, denominator, rounding),
+            expected,
+            "{a} * {b} / {denominator} rounded {rounding:?}"
+        );
+    }
+}
+
+#[test]
+fn test_mul_div_edge_cases() {
+    for a in EDGES {
+        for b in EDGES {
+            for denominator in EDGES {
+                check_mul_div(a, b, denominator);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_mul_div_matches_reference() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    for _ in 0..100_000 {
+        check_mul_div(rng.next_u128(), rng.next_u128(), rng.next_u128());
+    }
+}
+
+/// `e^(numerator / denominator)` scaled by `2^192` and rounded down, with the bound
+/// on how far the truncated series may sit below the exact value.
+fn reference_exp(numerator: u128, denominator: u128) -> (BigUint, BigUint) {
+    let mut term = BigUint::from(1u32) << 192u32;
+    let mut sum = term.clone();
+    let mut terms = 1u32;
+    while term != BigUint::ZERO {
+        term = term * big(numerator) / (big(denominator) * terms);
+        sum += &term;
+        terms += 1;
+    }
+    (sum, BigUint::from(terms))
+}
+
+#[test]
+fn test_exp_brackets_reference() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    let mut inputs = vec![0, 1, ONE / 2, ONE, 0xb17217f7d1cf79ab, 40 * ONE];
+    inputs.extend((0..2_000).map(|_| rng.next_u128() % (40 * ONE)));
+
+    for x in inputs {
+        let (reference, error) = reference_exp(x, ONE);
+        let down = exp(x, Rounding::Down).unwrap();
+        let up = exp(x, Rounding::Up).unwrap();
+
+        assert!(big(down) << 128u32 <= reference, "e^{x} rounded down");
+        assert!(big(up) << 128u32 >= reference + error, "e^{x} rounded up");
+        // Both bounds stay within 2^-50 of each other
+        assert!(up - down <= (down >> 50) + 64, "e^{x} bounds are loose");
+    }
+
+    assert_eq!(exp(63 * ONE, Rounding::Down), overflow());
+}
+
+#[test]
+fn test_constant_product_never_loses_to_rounding() {
+    let mut rng = Rng(0xdead_beef_cafe_f00d);
+    for _ in 0..20_000 {
+        let vtoken_reserve = rng.next_u64().max(2);
+        let vsol_reserve = rng.next_sized();
+        let amount = rng.below(vtoken_reserve - 1) + 1;
+        let curve = ConstantProduct {
+            vtoken_reserve,
+            vsol_reserve,
+        };
+        let k = big(vtoken_reserve as u128) * big(vsol_reserve as u128);
+
+        let remaining = big((vtoken_reserve - amount) as u128);
+        let expected = reference_div(
+            big(vsol_reserve as u128) * big(amount as u128),
+            remaining.clone(),
+            Rounding::Up,
+        )
+        .and_then(|cost| u64::try_from(cost).ok());
+        match curve.cost_to_buy(amount) {
+            Ok(cost) => {
+                assert_eq!(Some(cost), expected);
+                assert!((big(vsol_reserve as u128) + big(cost as u128)) * remaining >= k);
+            }
+            Err(error) => {
+                assert_eq!(expected, None);
+                assert_eq!(error, BondingCurveError::MathOverflow.into());
+            }
+        }
+
+        let refund = curve.refund_for_sell(amount).unwrap();
+        let grown = big(vtoken_reserve as u128) + big(amount as u128);
+        assert_eq!(
+            Some(refund as u128),
+            reference_div(
+                big(vsol_reserve as u128) * big(amount as u128),
+                grown.clone(),
+                Rounding::Down,
+            )
+        );
+        assert!(big((vsol_reserve - refund) as u128) * grown >= k);
+    }
+}
+
+#[test]
+fn test_linear_matches_reference() {
+    let mut rng = Rng(0x0123_4567_89ab_cdef);
+    for _ in 0..20_000 {
+        let initial_vtoken_reserve = rng.next_sized();
+        let initial_vsol_reserve = rng.next_sized();
+        let growth_scale = rng.next_sized();
+        let sold = rng.below(initial_vtoken_reserve);
+        let amount = rng.below(initial_vtoken_reserve - sold) + 1;
+        let curve = Linear {
+            initial_vtoken_reserve,
+            initial_vsol_reserve,
+            sold,
+            growth_scale,
+        };
+
+        let denominator = big(2) * big(initial_vtoken_reserve as u128) * big(growth_scale as u128);
+        let area = |from: u64, to: u64, rounding| {
+            let width = big(2) * big(growth_scale as u128) + big(from as u128) + big(to as u128);
+            reference_div(
+                big(initial_vsol_reserve as u128) * big((to - from) as u128) * width,
+                denominator.clone(),
+                rounding,
+            )
+            .and_then(|value| u64::try_from(value).ok())
+            .map_or(overflow(), Ok)
+        };
+
+        assert_eq!(
+            curve.cost_to_buy(amount),
+            area(sold, sold + amount, Rounding::Up)
+        );
+        if sold > 0 {
+            let amount = rng.below(sold) + 1;
+            assert_eq!(
+                curve.refund_for_sell(amount),
+                area(sold - amount, sold, Rounding::Down)
+            );
+        }
+    }
+}
+
+#[test]
+fn test_exponential_brackets_reference() {
+    let mut rng = Rng(0x0f1e_2d3c_4b5a_6978);
+    for _ in 0..2_000 {
+        // Keep `e^(T0 / scale) * V0` within a u64
+        let initial_vtoken_reserve = rng.below(1 << 40) + 2;
+        let initial_vsol_reserve = rng.below(1 << 30) + 1;
+        let growth_scale = initial_vtoken_reserve / (rng.below(20) + 1) + 1;
+        let sold = rng.below(initial_vtoken_reserve);
+        let amount = rng.below(initial_vtoken_reserve - sold) + 1;
+        let curve = Exponential {
+            initial_vtoken_reserve,
+            initial_vsol_reserve,
+            sold,
+            growth_scale,
+        };
+
+        // Bounds on `V0 * scale * (e^(to / scale) - e^(from / scale)) / T0`
+        let growth = |tokens: u64| {
+            let (low, error) = reference_exp(tokens as u128, growth_scale as u128);
+            (low.clone(), low + error)
+        };
+        let area = |difference: BigUint| {
+            difference * big(initial_vsol_reserve as u128) * big(growth_scale as u128)
+                / (big(initial_vtoken_reserve as u128) << 192u32)
+        };
+        let (from_low, from_high) = growth(sold);
+        let (to_low, to_high) = growth(sold + amount);
+        let lower = area(to_low - from_high);
+        let upper = area(to_high - from_low);
+
+        let cost = big(curve.cost_to_buy(amount).unwrap() as u128);
+        assert!(cost >= lower);
+        assert!(cost <= &upper + 2u32 + (&cost >> 48));
+
+        let seller = Exponential {
+            sold: sold + amount,
+            ..curve
+        };
+        let refund = big(seller.refund_for_sell(amount).unwrap() as u128);
+        assert!(refund <= upper);
+        assert!(&refund + 2u32 + (&refund >> 48) >= lower);
+    }
+}
+
+#[test]
+fn test_sqrt_rounds_down() {
+    let mut rng = Rng(0x5851_f42d_4c95_7f2d);
+    let mut inputs = EDGES.to_vec();
+    inputs.extend((0..100_000).map(|_| rng.next_u128()));
+
+    for value in inputs {
+        let root = big(sqrt(value));
+        assert!(&root * &root <= big(value), "sqrt({value})");
+        assert!(
+            (&root + 1u32) * (&root + 1u32) > big(value),
+            "sqrt({value})"
+        );
+    }
+}
+
+#[test]
+fn test_ln_inverts_exp() {
+    let mut rng = Rng(0x1405_7b7e_f767_814f);
+    assert_eq!(ln(ONE), Ok(0));
+    assert_eq!(ln(ONE - 1), overflow());
+
+    for _ in 0..10_000 {
+        let x = rng.next_u128() % (40 * ONE);
+        let growth = exp(x, Rounding::Down).unwrap();
+        let log = ln(growth).unwrap();
+        // `exp` is exact to 2^-50 relative, so its logarithm is within 2^-50 of `x`
+        assert!(log.abs_diff(x) <= (ONE >> 50) + 16, "ln(e^{x}) = {log}");
+    }
+}
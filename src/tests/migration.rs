@@ -0,0 +1,287 @@
+//! The adapters are tested on the instructions they build. Migrations invoking them
+//! against the stub DEX run under mollusk, see `sbf.rs`.
+
+use pinocchio::{program_error::ProgramError, pubkey::Pubkey};
+
+use super::harness::{unique_key, with_account_infos, TestAccount};
+use crate::{
+    error::BondingCurveError,
+    migration::{
+        self, Liquidity, MigrationTarget, METEORA_DAMM_ACCOUNTS_LEN, METEORA_DAMM_ID,
+        METEORA_DAMM_INITIALIZE_PERMISSIONLESS_POOL, RAYDIUM_CPMM_ACCOUNTS_LEN, RAYDIUM_CPMM_ID,
+        RAYDIUM_CPMM_INITIALIZE,
+    },
+};
+
+/// Keys of the liquidity a migration authority hands to a DEX.
+struct Keys {
+    authority: Pubkey,
+    token_mint: Pubkey,
+    quote_mint: Pubkey,
+    token_account: Pubkey,
+    quote_account: Pubkey,
+}
+
+impl Keys {
+    /// Keys whose token mint sorts before the quote mint when `token_first`.
+    fn new(token_first: bool) -> Self {
+        let (mut token_mint, mut quote_mint) = (unique_key(), unique_key());
+        if (token_mint < quote_mint) != token_first {
+            core::mem::swap(&mut token_mint, &mut quote_mint);
+        }
+        Self {
+            authority: unique_key(),
+            token_mint,
+            quote_mint,
+            token_account: unique_key(),
+            quote_account: unique_key(),
+        }
+    }
+
+    fn liquidity(&self) -> Liquidity<'_> {
+        Liquidity {
+            authority: &self.authority,
+            token_mint: &self.token_mint,
+            quote_mint: &self.quote_mint,
+            token_account: &self.token_account,
+            quote_account: &self.quote_account,
+            token_amount: 1_000,
+            quote_amount: 2_000,
+        }
+    }
+}
+
+/// The DEX program followed by `len` accounts, with `keys` at the given positions.
+fn dex_accounts(program: Pubkey, len: usize, keys: &[(usize, Pubkey)]) -> Vec<TestAccount> {
+    let mut accounts = vec![TestAccount::new(program, [0; 32], vec![]).readonly()];
+    accounts.extend((0..len).map(|_| TestAccount::new(unique_key(), [0; 32], vec![])));
+    for (index, key) in keys {
+        accounts[1 + index].key = *key;
+    }
+    accounts
+}
+
+fn raydium_accounts(keys: &Keys, token_first: bool) -> Vec<TestAccount> {
+    let (mint_0, account_0, mint_1, account_1) = if token_first {
+        (
+            keys.token_mint,
+            keys.token_account,
+            keys.quote_mint,
+            keys.quote_account,
+        )
+    } else {
+        (
+            keys.quote_mint,
+            keys.quote_account,
+            keys.token_mint,
+            keys.token_account,
+        )
+    };
+    dex_accounts(
+        RAYDIUM_CPMM_ID,
+        RAYDIUM_CPMM_ACCOUNTS_LEN,
+        &[
+            (0, keys.authority),
+            (4, mint_0),
+            (5, mint_1),
+            (7, account_0),
+            (8, account_1),
+        ],
+    )
+}
+
+fn meteora_accounts(keys: &Keys) -> Vec<TestAccount> {
+    dex_accounts(
+        METEORA_DAMM_ID,
+        METEORA_DAMM_ACCOUNTS_LEN,
+        &[
+            (2, keys.token_mint),
+            (3, keys.quote_mint),
+            (10, keys.token_account),
+            (11, keys.quote_account),
+            (15, keys.authority),
+        ],
+    )
+}
+
+#[test]
+fn test_migration_target_from_byte() {
+    assert_eq!(MigrationTarget::try_from(0), Ok(MigrationTarget::InHouse));
+    assert_eq!(
+        MigrationTarget::try_from(1),
+        Ok(MigrationTarget::RaydiumCpmm)
+    );
+    assert_eq!(
+        MigrationTarget::try_from(2),
+        Ok(MigrationTarget::MeteoraDamm)
+    );
+    assert_eq!(
+        MigrationTarget::try_from(3),
+        Err(BondingCurveError::InvalidCurveParameters.into())
+    );
+
+    assert!(!MigrationTarget::InHouse.is_external());
+    assert_eq!(MigrationTarget::InHouse.fee(), 0);
+    assert_eq!(MigrationTarget::InHouse.accounts_len(), 5);
+    // The migration authority, its token accounts and the DEX program come first
+    assert_eq!(
+        MigrationTarget::RaydiumCpmm.accounts_len(),
+        4 + RAYDIUM_CPMM_ACCOUNTS_LEN
+    );
+    assert_eq!(
+        MigrationTarget::MeteoraDamm.accounts_len(),
+        4 + METEORA_DAMM_ACCOUNTS_LEN
+    );
+}
+
+#[test]
+fn test_discriminators_are_anchor_sighashes() {
+    for (name, discriminator) in [
+        ("global:initialize", RAYDIUM_CPMM_INITIALIZE),
+        (
+            "global:initialize_permissionless_pool",
+            METEORA_DAMM_INITIALIZE_PERMISSIONLESS_POOL,
+        ),
+    ] {
+        let hash = solana_nostd_sha256::hash(name.as_bytes());
+        assert_eq!(hash[..8], discriminator);
+    }
+}
+
+#[test]
+fn test_raydium_cpmm_orders_mints_by_address() {
+    for token_first in [true, false] {
+        let keys = Keys::new(token_first);
+        let mut accounts = raydium_accounts(&keys, token_first);
+        let expected: Vec<Pubkey> = accounts[1..].iter().map(|account| account.key).collect();
+
+        with_account_infos(&mut accounts, |accounts| {
+            let creation = migration::raydium_cpmm(accounts, &keys.liquidity()).unwrap();
+            let instruction = creation.instruction();
+            assert_eq!(instruction.program_id, &RAYDIUM_CPMM_ID);
+
+            let keys: Vec<Pubkey> = instruction
+                .accounts
+                .iter()
+                .map(|meta| *meta.pubkey)
+                .collect();
+            assert_eq!(keys, expected);
+            for (index, account) in creation.account_infos.iter().enumerate() {
+                assert_eq!(account.key(), &expected[index]);
+            }
+            assert_eq!(creation.lp_mint().key(), &expected[6]);
+            assert_eq!(creation.lp_account().key(), &expected[9]);
+
+            // Only the creator signs; it pays for the pool along with the accounts the
+            // pool creates or moves tokens through
+            let writable = [0, 3, 6, 7, 8, 9, 10, 11, 12, 13];
+            for (index, meta) in instruction.accounts.iter().enumerate() {
+                assert_eq!(meta.is_signer, index == 0, "account {index}");
+                assert_eq!(
+                    meta.is_writable,
+                    writable.contains(&index),
+                    "account {index}"
+                );
+            }
+
+            let (amount_0, amount_1) = if token_first {
+                (1_000u64, 2_000u64)
+            } else {
+                (2_000, 1_000)
+            };
+            assert_eq!(instruction.data[..8], RAYDIUM_CPMM_INITIALIZE);
+            assert_eq!(instruction.data[8..16], amount_0.to_le_bytes());
+            assert_eq!(instruction.data[16..24], amount_1.to_le_bytes());
+            assert_eq!(instruction.data[24..32], 0u64.to_le_bytes());
+        });
+    }
+}
+
+#[test]
+fn test_meteora_damm_builds_permissionless_pool() {
+    let keys = Keys::new(true);
+    let mut accounts = meteora_accounts(&keys);
+    let expected: Vec<Pubkey> = accounts[1..].iter().map(|account| account.key).collect();
+
+    with_account_infos(&mut accounts, |accounts| {
+        let creation = migration::meteora_damm(accounts, &keys.liquidity()).unwrap();
+        let instruction = creation.instruction();
+        assert_eq!(instruction.program_id, &METEORA_DAMM_ID);
+
+        let keys: Vec<Pubkey> = instruction
+            .accounts
+            .iter()
+            .map(|meta| *meta.pubkey)
+            .collect();
+        assert_eq!(keys, expected);
+        assert_eq!(creation.lp_mint().key(), &expected[1]);
+        assert_eq!(creation.lp_account().key(), &expected[12]);
+
+        let readonly = [2, 3, 16, 17, 19, 20, 21, 22, 23];
+        for (index, meta) in instruction.accounts.iter().enumerate() {
+            assert_eq!(meta.is_signer, index == 15, "account {index}");
+            assert_eq!(
+                meta.is_writable,
+                !readonly.contains(&index),
+                "account {index}"
+            );
+        }
+
+        assert_eq!(
+            instruction.data[..8],
+            METEORA_DAMM_INITIALIZE_PERMISSIONLESS_POOL
+        );
+        // Constant product curve, then the token and quote amounts
+        assert_eq!(instruction.data[8], 0);
+        assert_eq!(instruction.data[9..17], 1_000u64.to_le_bytes());
+        assert_eq!(instruction.data[17..25], 2_000u64.to_le_bytes());
+    });
+}
+
+#[test]
+fn test_adapters_check_the_liquidity_accounts() {
+    let keys = Keys::new(true);
+
+    // The pool is created by the DEX the curve migrates to
+    let mut accounts = raydium_accounts(&keys, true);
+    accounts[0].key = METEORA_DAMM_ID;
+    with_account_infos(&mut accounts, |accounts| {
+        assert_eq!(
+            migration::raydium_cpmm(accounts, &keys.liquidity()).err(),
+            Some(ProgramError::IncorrectProgramId)
+        );
+    });
+
+    // Every account the liquidity passes through is the authority's
+    for index in [1, 5, 6, 8, 9] {
+        let mut accounts = raydium_accounts(&keys, true);
+        accounts[index].key = unique_key();
+        with_account_infos(&mut accounts, |accounts| {
+            assert_eq!(
+                migration::raydium_cpmm(accounts, &keys.liquidity()).err(),
+                Some(BondingCurveError::InvalidMigrationAccount.into()),
+                "account {index}"
+            );
+        });
+    }
+    for index in [3, 4, 11, 12, 16] {
+        let mut accounts = meteora_accounts(&keys);
+        accounts[index].key = unique_key();
+        with_account_infos(&mut accounts, |accounts| {
+            assert_eq!(
+                migration::meteora_damm(accounts, &keys.liquidity()).err(),
+                Some(BondingCurveError::InvalidMigrationAccount.into()),
+                "account {index}"
+            );
+        });
+    }
+
+    let mut accounts = meteora_accounts(&keys);
+    accounts.pop();
+    with_account_infos(&mut accounts, |accounts| {
+        assert_eq!(
+            migration::meteora_damm(accounts, &keys.liquidity()).err(),
+            Some(ProgramError::NotEnoughAccountKeys)
+        );
+    });
+}
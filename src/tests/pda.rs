@@ -0,0 +1,110 @@
+use pinocchio::program_error::ProgramError;
+use solana_sdk::pubkey::Pubkey;
+
+use super::harness::unique_key;
+use crate::{
+    pda::{
+        self, CONFIG_SEED, CURVE_SEED, FEE_VAULT_SEED, LP_LOCK_SEED, LP_MINT_SEED, LP_VAULT_SEED,
+        MIGRATION_AUTHORITY_SEED, MIGRATION_QUOTE_SEED, MIGRATION_TOKEN_SEED,
+        POOL_QUOTE_VAULT_SEED, POOL_SEED, POOL_TOKEN_VAULT_SEED, QUOTE_VAULT_SEED, VAULT_SEED,
+        VESTING_SEED,
+    },
+    ID,
+};
+
+/// Finds the canonical bump of `seeds` the way clients do.
+fn find(seeds: &[&[u8]]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(seeds, &Pubkey::new_from_array(ID))
+}
+
+#[test]
+fn test_addresses_match_create_program_address() {
+    let mint = unique_key();
+    let (state, bump) = find(&[CURVE_SEED, &mint]);
+    assert_eq!(pda::curve_address(&mint, bump), Ok(state.to_bytes()));
+
+    let state = state.to_bytes();
+    let (vault, bump) = find(&[VAULT_SEED, &state]);
+    assert_eq!(pda::vault_address(&state, bump), Ok(vault.to_bytes()));
+
+    let (quote_vault, bump) = find(&[QUOTE_VAULT_SEED, &state]);
+    assert_eq!(
+        pda::quote_vault_address(&state, bump),
+        Ok(quote_vault.to_bytes())
+    );
+
+    let (fee_vault, bump) = find(&[FEE_VAULT_SEED, &state]);
+    assert_eq!(
+        pda::fee_vault_address(&state, bump),
+        Ok(fee_vault.to_bytes())
+    );
+
+    let (config, bump) = find(&[CONFIG_SEED]);
+    assert_eq!(pda::config_address(bump), Ok(config.to_bytes()));
+
+    let (pool, bump) = find(&[POOL_SEED, &state]);
+    assert_eq!(pda::pool_address(&state, bump), Ok(pool.to_bytes()));
+
+    let pool = pool.to_bytes();
+    for (seed, address) in [
+        (
+            POOL_TOKEN_VAULT_SEED,
+            pda::pool_token_vault_address as fn(&_, _) -> _,
+        ),
+        (POOL_QUOTE_VAULT_SEED, pda::pool_quote_vault_address),
+        (LP_MINT_SEED, pda::lp_mint_address),
+        (LP_VAULT_SEED, pda::lp_vault_address),
+    ] {
+        let (expected, bump) = find(&[seed, &pool]);
+        assert_eq!(address(&pool, bump), Ok(expected.to_bytes()));
+    }
+
+    let (lp_lock, bump) = find(&[LP_LOCK_SEED, &state]);
+    assert_eq!(pda::lp_lock_address(&state, bump), Ok(lp_lock.to_bytes()));
+
+    let (vesting, bump) = find(&[VESTING_SEED, &state]);
+    assert_eq!(pda::vesting_address(&state, bump), Ok(vesting.to_bytes()));
+
+    let (authority, bump) = find(&[MIGRATION_AUTHORITY_SEED, &state]);
+    assert_eq!(
+        pda::migration_authority_address(&state, bump),
+        Ok(authority.to_bytes())
+    );
+
+    let authority = authority.to_bytes();
+    for (seed, address) in [
+        (
+            MIGRATION_TOKEN_SEED,
+            pda::migration_token_address as fn(&_, _) -> _,
+        ),
+        (MIGRATION_QUOTE_SEED, pda::migration_quote_address),
+    ] {
+        let (expected, bump) = find(&[seed, &authority]);
+        assert_eq!(address(&authority, bump), Ok(expected.to_bytes()));
+    }
+}
+
+#[test]
+fn test_find_config_address_takes_a_derivable_bump() {
+    let (config, bump) = pda::find_config_address().unwrap();
+    assert_eq!(pda::config_address(bump), Ok(config));
+    assert_ne!(pda::program_data_address(), Ok(config));
+}
+
+#[test]
+fn test_addresses_depend_on_every_seed() {
+    let mint = unique_key();
+    let state = pda::curve_address(&mint, 1).unwrap();
+    assert_ne!(pda::curve_address(&mint, 2), Ok(state));
+    assert_ne!(pda::curve_address(&unique_key(), 1), Ok(state));
+    // Same seeds under another prefix
+    assert_ne!(pda::vault_address(&mint, 1), Ok(state));
+}
+
+#[test]
+fn test_create_program_address_rejects_too_many_seeds() {
+    assert_eq!(
+        pda::create_program_address(&[&[][..]; 16], 0),
+        Err(ProgramError::MaxSeedLengthExceeded)
+    );
+}
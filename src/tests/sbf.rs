@@ -0,0 +1,691 @@
+//! Runs instructions under mollusk against the SBF builds of the program and of the
+//! SPL Token program, so the cross-program invocations the native harness skips take
+//! effect. Migrations to external targets run against the stub DEX, which records the
+//! pool-creation instruction it receives and mints the seed liquidity. The tests need
+//! those builds and are ignored by default, see the README.
+
+use mollusk_svm::{
+    program::{self, loader_keys},
+    result::ProgramResult,
+    Mollusk,
+};
+use solana_sdk::{
+    account::{AccountSharedData, ReadableAccount, WritableAccount},
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    system_program,
+};
+use spl_token::state::{Account, AccountState, Mint};
+
+use stub_dex::{LP_AMOUNT, LP_MINT_AUTHORITY_SEED, RECORD_ACCOUNT_LEN};
+
+use crate::{
+    error::BondingCurveError,
+    migration::{
+        LpPolicy, MigrationTarget, METEORA_DAMM_ACCOUNTS_LEN, METEORA_DAMM_ID,
+        METEORA_DAMM_INITIALIZE_PERMISSIONLESS_POOL, RAYDIUM_CPMM_ACCOUNTS_LEN, RAYDIUM_CPMM_ID,
+        RAYDIUM_CPMM_INITIALIZE,
+    },
+    pda::{
+        CONFIG_SEED, CURVE_SEED, FEE_VAULT_SEED, LP_LOCK_SEED, MIGRATION_AUTHORITY_SEED,
+        MIGRATION_QUOTE_SEED, MIGRATION_TOKEN_SEED, VAULT_SEED,
+    },
+    state::{BondingCurveState, CurveStatus, GlobalConfig, ProgramAccount},
+    ConfigArgs, InitializeArgs, NATIVE_MINT, TOKEN_DECIMALS,
+};
+
+/// SBF build of the program, as `cargo build-sbf` writes it.
+const PROGRAM: &str = "target/deploy/bonding_curve_pinocchio";
+
+/// SPL Token program dumped from mainnet, see the README.
+const SPL_TOKEN: &str = "src/tests/spl_token-3.5.0";
+
+/// SBF build of the stub DEX, deployed at the program id of every external target.
+const STUB_DEX: &str = "target/deploy/stub_dex";
+
+/// Size of the pool account the stub DEX records the instruction in.
+const RECORD_LEN: usize = 1_024;
+
+fn program_id() -> Pubkey {
+    Pubkey::new_from_array(crate::ID)
+}
+
+/// Finds the canonical bump of `seeds` the way clients do.
+fn find(seeds: &[&[u8]]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(seeds, &program_id())
+}
+
+/// A mollusk instance and the accounts instructions are processed against, kept in
+/// sync with the instructions that succeed.
+struct Svm {
+    mollusk: Mollusk,
+    accounts: Vec<(Pubkey, AccountSharedData)>,
+}
+
+impl Svm {
+    /// The program, the SPL Token program and the system program, with the wrapped
+    /// SOL mint.
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&program_id(), PROGRAM);
+        mollusk.add_program(&spl_token::ID, SPL_TOKEN, &loader_keys::LOADER_V3);
+        let mut svm = Self {
+            mollusk,
+            accounts: vec![
+                program::keyed_account_for_system_program(),
+                (
+                    spl_token::ID,
+                    program::create_program_account_loader_v3(&spl_token::ID),
+                ),
+            ],
+        };
+        svm.add_mint(NATIVE_MINT.into(), None, 9);
+        svm
+    }
+
+    /// Deploys the stub DEX at the program ids of the external targets.
+    fn add_stub_dex(&mut self) {
+        for id in [RAYDIUM_CPMM_ID, METEORA_DAMM_ID].map(Pubkey::from) {
+            self.mollusk
+                .add_program(&id, STUB_DEX, &loader_keys::LOADER_V3);
+            self.set_account(id, program::create_program_account_loader_v3(&id));
+        }
+    }
+
+    fn rent(&self, len: usize) -> u64 {
+        self.mollusk.sysvars.rent.minimum_balance(len)
+    }
+
+    fn account(&self, key: &Pubkey) -> &AccountSharedData {
+        &self.accounts.iter().find(|(k, _)| k == key).unwrap().1
+    }
+
+    fn set_account(&mut self, key: Pubkey, account: AccountSharedData) {
+        match self.accounts.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, existing)) => *existing = account,
+            None => self.accounts.push((key, account)),
+        }
+    }
+
+    /// Processes `instruction`, keeping the accounts it leaves behind if it succeeds.
+    fn process(&mut self, instruction: &Instruction) -> ProgramResult {
+        let result = self
+            .mollusk
+            .process_instruction(instruction, &self.accounts);
+        if !result.program_result.is_err() {
+            for (key, account) in result.resulting_accounts {
+                self.set_account(key, account);
+            }
+        }
+        result.program_result
+    }
+
+    /// A system account holding `lamports`.
+    fn add_wallet(&mut self, lamports: u64) -> Pubkey {
+        let key = Pubkey::new_unique();
+        self.set_account(
+            key,
+            AccountSharedData::new(lamports, 0, &system_program::ID),
+        );
+        key
+    }
+
+    fn add_mint(&mut self, key: Pubkey, mint_authority: Option<Pubkey>, decimals: u8) {
+        let mut account = AccountSharedData::new(self.rent(Mint::LEN), Mint::LEN, &spl_token::ID);
+        Mint {
+            mint_authority: mint_authority.into(),
+            supply: 0,
+            decimals,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        }
+        .pack_into_slice(account.data_as_mut_slice());
+        self.set_account(key, account);
+    }
+
+    fn add_token_account(&mut self, mint: Pubkey, owner: Pubkey, amount: u64) -> Pubkey {
+        let key = Pubkey::new_unique();
+        self.set_account(key, self.token_account(mint, owner, amount));
+        key
+    }
+
+    /// A token account of `mint` owned by `owner` holding `amount`, backed by lamports
+    /// when `mint` is wrapped SOL.
+    fn token_account(&self, mint: Pubkey, owner: Pubkey, amount: u64) -> AccountSharedData {
+        let rent = self.rent(Account::LEN);
+        let is_native = mint == Pubkey::from(NATIVE_MINT);
+        let lamports = if is_native { rent + amount } else { rent };
+        let mut account = AccountSharedData::new(lamports, Account::LEN, &spl_token::ID);
+        Account {
+            mint,
+            owner,
+            amount,
+            state: AccountState::Initialized,
+            is_native: if is_native {
+                COption::Some(rent)
+            } else {
+                COption::None
+            },
+            ..Default::default()
+        }
+        .pack_into_slice(account.data_as_mut_slice());
+        account
+    }
+
+    /// The global config at its canonical bump, set up with `args`.
+    fn add_config(&mut self, args: &ConfigArgs) -> Pubkey {
+        let (key, bump) = find(&[CONFIG_SEED]);
+        let mut account = AccountSharedData::new(
+            self.rent(GlobalConfig::LEN),
+            GlobalConfig::LEN,
+            &program_id(),
+        );
+        let data = account.data_as_mut_slice();
+        data[..8].copy_from_slice(&GlobalConfig::DISCRIMINATOR);
+        data[8] = GlobalConfig::VERSION;
+        let config = unsafe { GlobalConfig::from_bytes_mut(data) };
+        config.set_bump(bump);
+        args.apply(config);
+        self.set_account(key, account);
+        key
+    }
+
+    fn token_amount(&self, key: &Pubkey) -> u64 {
+        Account::unpack(self.account(key).data()).unwrap().amount
+    }
+
+    fn state(&self, curve: &Curve) -> &BondingCurveState {
+        unsafe { BondingCurveState::from_bytes(self.account(&curve.state).data()) }
+    }
+
+    /// Initializes a curve quoted in `quote_mint` under a config set up with
+    /// `config_args`.
+    fn initialize(
+        &mut self,
+        config_args: &ConfigArgs,
+        args: &InitializeArgs,
+        quote_mint: Pubkey,
+    ) -> Curve {
+        let vtoken_mint = Pubkey::new_unique();
+        let (state, bump) = find(&[CURVE_SEED, vtoken_mint.as_ref()]);
+        let (vault, vault_bump) = find(&[VAULT_SEED, state.as_ref()]);
+        let (fee_vault, fee_vault_bump) = find(&[FEE_VAULT_SEED, state.as_ref()]);
+        // The instruction creates them
+        for key in [state, vtoken_mint, vault, fee_vault] {
+            self.set_account(key, AccountSharedData::default());
+        }
+        let creator = self.add_wallet(10_000_000_000);
+        let config = self.add_config(config_args);
+
+        let instruction = Instruction {
+            program_id: program_id(),
+            accounts: vec![
+                AccountMeta::new(state, false),
+                AccountMeta::new(vtoken_mint, true),
+                AccountMeta::new(vault, false),
+                AccountMeta::new_readonly(quote_mint, false),
+                AccountMeta::new(creator, true),
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new(fee_vault, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                // Invoked to set up the mint and the vaults
+                AccountMeta::new_readonly(spl_token::ID, false),
+            ],
+            data: [&[0, bump, vault_bump, fee_vault_bump][..], &args.to_bytes()].concat(),
+        };
+        assert_eq!(self.process(&instruction), ProgramResult::Success);
+
+        Curve {
+            state,
+            vtoken_mint,
+            vault,
+            quote_mint,
+            fee_vault,
+            config,
+        }
+    }
+
+    /// Buys `amount` of tokens of `curve` for `buyer`, paying from its quote account.
+    fn buy(&mut self, curve: &Curve, buyer: &Buyer, amount: u64) -> ProgramResult {
+        let instruction = Instruction {
+            program_id: program_id(),
+            accounts: vec![
+                AccountMeta::new(curve.state, false),
+                AccountMeta::new(buyer.wallet, true),
+                AccountMeta::new(buyer.token_account, false),
+                AccountMeta::new(curve.vtoken_mint, false),
+                AccountMeta::new_readonly(curve.quote_mint, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(curve.config, false),
+                AccountMeta::new(curve.fee_vault, false),
+                AccountMeta::new(curve.vault, false),
+                AccountMeta::new(buyer.quote_account, false),
+            ],
+            data: [&[1][..], &amount.to_le_bytes(), &u64::MAX.to_le_bytes()].concat(),
+        };
+        self.process(&instruction)
+    }
+
+    /// A buyer of `curve` holding `quote` in its quote account.
+    fn add_buyer(&mut self, curve: &Curve, quote: u64) -> Buyer {
+        let wallet = self.add_wallet(1_000_000_000);
+        Buyer {
+            wallet,
+            token_account: self.add_token_account(curve.vtoken_mint, wallet, 0),
+            quote_account: self.add_token_account(curve.quote_mint, wallet, quote),
+        }
+    }
+}
+
+impl Svm {
+    /// A curve quoted in wrapped SOL, migrating to `target` with `lp_policy`, whose
+    /// allocation is sold out. Its config charges what the target costs.
+    fn complete_external(&mut self, target: MigrationTarget, lp_policy: LpPolicy) -> Curve {
+        let config_args = ConfigArgs {
+            migration_fee: target.fee(),
+            ..Default::default()
+        };
+        let args = InitializeArgs {
+            initial_vtoken_reserve: 1_000_000_000,
+            initial_vsol_reserve: 1_000_000_000,
+            total_supply: 900_000_000,
+            allocation_at_migration: 500_000_000,
+            migration_target: target,
+            lp_policy,
+            lp_unlock_timestamp: match lp_policy {
+                LpPolicy::Burn => 0,
+                LpPolicy::Lock => 1,
+            },
+            ..Default::default()
+        };
+        let curve = self.initialize(&config_args, &args, NATIVE_MINT.into());
+        let buyer = self.add_buyer(&curve, 2_000_000_000);
+        assert_eq!(
+            self.buy(&curve, &buyer, 500_000_000),
+            ProgramResult::Success
+        );
+        assert_eq!(self.state(&curve).status(), Ok(CurveStatus::Complete));
+        curve
+    }
+
+    /// Sets up the migration of `curve` to the stub DEX at the program id of its
+    /// external target, paid for by a new payer.
+    fn external_migration(&mut self, curve: &Curve) -> ExternalMigration {
+        let state = self.state(curve);
+        let (target, lp_policy) = (
+            state.migration_target().unwrap(),
+            state.lp_policy().unwrap(),
+        );
+        let payer = self.add_wallet(10_000_000_000);
+        let fee_recipient = self.add_wallet(0);
+        let refund_account = self.add_token_account(curve.quote_mint, payer, 0);
+
+        let (authority, authority_bump) = find(&[MIGRATION_AUTHORITY_SEED, curve.state.as_ref()]);
+        let (token_account, token_bump) = find(&[MIGRATION_TOKEN_SEED, authority.as_ref()]);
+        let (quote_account, quote_bump) = find(&[MIGRATION_QUOTE_SEED, authority.as_ref()]);
+        // The instruction creates them
+        for key in [authority, token_account, quote_account] {
+            self.set_account(key, AccountSharedData::default());
+        }
+
+        let program = match target {
+            MigrationTarget::RaydiumCpmm => Pubkey::from(RAYDIUM_CPMM_ID),
+            MigrationTarget::MeteoraDamm => Pubkey::from(METEORA_DAMM_ID),
+            MigrationTarget::InHouse => unreachable!(),
+        };
+        let (lp_mint_authority, _) =
+            Pubkey::find_program_address(&[LP_MINT_AUTHORITY_SEED], &program);
+        let lp_mint = Pubkey::new_unique();
+        self.add_mint(lp_mint, Some(lp_mint_authority), 9);
+        let lp_account = self.add_token_account(lp_mint, authority, 0);
+
+        // The accounts of the pool-creation instruction the adapter checks, and those
+        // the stub uses; the rest only need to exist
+        let (token_mint, quote_mint) = (curve.vtoken_mint, curve.quote_mint);
+        let (dex_len, pool, positions) = match target {
+            MigrationTarget::RaydiumCpmm => {
+                let ((mint_0, account_0), (mint_1, account_1)) = if token_mint < quote_mint {
+                    ((token_mint, token_account), (quote_mint, quote_account))
+                } else {
+                    ((quote_mint, quote_account), (token_mint, token_account))
+                };
+                let pool = Pubkey::new_unique();
+                let positions = vec![
+                    (0, authority),
+                    (2, lp_mint_authority),
+                    (3, pool),
+                    (4, mint_0),
+                    (5, mint_1),
+                    (6, lp_mint),
+                    (7, account_0),
+                    (8, account_1),
+                    (9, lp_account),
+                    (14, spl_token::ID),
+                    (15, spl_token::ID),
+                    (16, spl_token::ID),
+                    (18, system_program::ID),
+                ];
+                (RAYDIUM_CPMM_ACCOUNTS_LEN, pool, positions)
+            }
+            MigrationTarget::MeteoraDamm => {
+                let positions = vec![
+                    (0, lp_mint_authority),
+                    (1, lp_mint),
+                    (2, token_mint),
+                    (3, quote_mint),
+                    (10, token_account),
+                    (11, quote_account),
+                    (12, lp_account),
+                    (15, authority),
+                    (21, spl_token::ID),
+                    (23, system_program::ID),
+                ];
+                (METEORA_DAMM_ACCOUNTS_LEN, lp_mint_authority, positions)
+            }
+            MigrationTarget::InHouse => unreachable!(),
+        };
+        let mut dex_accounts: Vec<Pubkey> = (0..dex_len).map(|_| Pubkey::new_unique()).collect();
+        for (index, key) in positions {
+            dex_accounts[index] = key;
+        }
+        for key in &dex_accounts {
+            if !self.accounts.iter().any(|(k, _)| k == key) {
+                self.set_account(*key, AccountSharedData::default());
+            }
+        }
+        self.set_account(
+            pool,
+            AccountSharedData::new(self.rent(RECORD_LEN), RECORD_LEN, &program),
+        );
+
+        let mut accounts = vec![
+            AccountMeta::new(curve.state, false),
+            AccountMeta::new(curve.vault, false),
+            AccountMeta::new_readonly(curve.quote_mint, false),
+            AccountMeta::new(curve.vtoken_mint, false),
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(curve.config, false),
+            AccountMeta::new(fee_recipient, false),
+            AccountMeta::new(refund_account, false),
+            AccountMeta::new(authority, false),
+            AccountMeta::new(token_account, false),
+            AccountMeta::new(quote_account, false),
+            AccountMeta::new_readonly(program, false),
+        ];
+        // The authority signs the pool creation with its seeds
+        accounts.extend(dex_accounts.iter().map(|&key| {
+            if key == spl_token::ID || key == system_program::ID {
+                AccountMeta::new_readonly(key, false)
+            } else {
+                AccountMeta::new(key, false)
+            }
+        }));
+        let mut data = vec![3, authority_bump, token_bump, quote_bump];
+        let lp_lock = match lp_policy {
+            LpPolicy::Burn => None,
+            LpPolicy::Lock => {
+                let (lp_lock, lp_lock_bump) = find(&[LP_LOCK_SEED, curve.state.as_ref()]);
+                self.set_account(lp_lock, AccountSharedData::default());
+                accounts.push(AccountMeta::new(lp_lock, false));
+                data.push(lp_lock_bump);
+                Some(lp_lock)
+            }
+        };
+
+        ExternalMigration {
+            instruction: Instruction {
+                program_id: program_id(),
+                accounts,
+                data,
+            },
+            authority,
+            token_account,
+            quote_account,
+            dex_accounts,
+            pool,
+            lp_mint,
+            lp_account,
+            lp_lock,
+        }
+    }
+
+    /// The pool-creation instruction the stub DEX recorded in `pool`: its data, then
+    /// the key, signer flag and writable flag of each of its accounts.
+    fn recorded(&self, pool: &Pubkey) -> (Vec<u8>, Vec<(Pubkey, bool, bool)>) {
+        let record = self.account(pool).data();
+        let data_len = u16::from_le_bytes([record[0], record[1]]) as usize;
+        let (data, record) = record[2..].split_at(data_len);
+        let accounts = record[1..]
+            .chunks_exact(RECORD_ACCOUNT_LEN)
+            .take(record[0] as usize)
+            .map(|account| {
+                let key = Pubkey::try_from(&account[..32]).unwrap();
+                (key, account[32] == 1, account[33] == 1)
+            })
+            .collect();
+        (data.to_vec(), accounts)
+    }
+}
+
+/// Keys of a curve initialized by [`Svm::initialize`].
+struct Curve {
+    state: Pubkey,
+    vtoken_mint: Pubkey,
+    vault: Pubkey,
+    quote_mint: Pubkey,
+    fee_vault: Pubkey,
+    config: Pubkey,
+}
+
+/// `Migrate` instruction set up by [`Svm::external_migration`], and the keys of the
+/// accounts it involves.
+struct ExternalMigration {
+    instruction: Instruction,
+    authority: Pubkey,
+    token_account: Pubkey,
+    quote_account: Pubkey,
+    /// Accounts of the pool-creation instruction, in order.
+    dex_accounts: Vec<Pubkey>,
+    /// Where the stub DEX recorded the pool-creation instruction.
+    pool: Pubkey,
+    lp_mint: Pubkey,
+    lp_account: Pubkey,
+    lp_lock: Option<Pubkey>,
+}
+
+/// A wallet trading on a curve and its token accounts.
+struct Buyer {
+    wallet: Pubkey,
+    token_account: Pubkey,
+    quote_account: Pubkey,
+}
+
+#[test]
+#[ignore = "requires the SBF build of the program (cargo build-sbf)"]
+fn test_initialize() {
+    let mut svm = Svm::new();
+    let quote_mint = Pubkey::new_unique();
+    svm.add_mint(quote_mint, None, 6);
+
+    let curve = svm.initialize(
+        &ConfigArgs::default(),
+        &InitializeArgs::default(),
+        quote_mint,
+    );
+
+    assert_eq!(svm.account(&curve.state).owner(), &program_id());
+    assert_eq!(
+        svm.state(&curve).vtoken_mint(),
+        &curve.vtoken_mint.to_bytes()
+    );
+    let mint = Mint::unpack(svm.account(&curve.vtoken_mint).data()).unwrap();
+    assert_eq!(mint.mint_authority, COption::Some(curve.state));
+    assert_eq!(mint.freeze_authority, COption::None);
+    assert_eq!(mint.decimals, TOKEN_DECIMALS);
+    for vault in [curve.vault, curve.fee_vault] {
+        let vault = Account::unpack(svm.account(&vault).data()).unwrap();
+        assert_eq!(vault.mint, quote_mint);
+    }
+    let vault = Account::unpack(svm.account(&curve.vault).data()).unwrap();
+    assert_eq!(vault.owner, curve.state);
+}
+
+#[test]
+#[ignore = "requires the SBF build of the program (cargo build-sbf)"]
+fn test_buy() {
+    let mut svm = Svm::new();
+    let quote_mint = Pubkey::new_unique();
+    svm.add_mint(quote_mint, None, 6);
+    let curve = svm.initialize(
+        &ConfigArgs::default(),
+        &InitializeArgs::default(),
+        quote_mint,
+    );
+    let buyer = svm.add_buyer(&curve, 1_000_000_000);
+
+    assert_eq!(svm.buy(&curve, &buyer, 1_000), ProgramResult::Success);
+
+    assert_eq!(svm.token_amount(&buyer.token_account), 1_000);
+    let paid = 1_000_000_000 - svm.token_amount(&buyer.quote_account);
+    assert!(paid > 0);
+    assert_eq!(
+        svm.token_amount(&curve.vault) + svm.token_amount(&curve.fee_vault),
+        paid
+    );
+    assert_eq!(
+        svm.state(&curve).vtoken_reserve_amount(),
+        InitializeArgs::default().initial_vtoken_reserve - 1_000
+    );
+}
+
+/// Migrates a complete curve to `target`, checking the pool-creation instruction the
+/// stub DEX received, the liquidity handed to it and what became of the liquidity
+/// tokens it minted.
+fn check_external_migration(svm: &mut Svm, target: MigrationTarget, lp_policy: LpPolicy) {
+    let curve = svm.complete_external(target, lp_policy);
+    let collateral = {
+        let state = svm.state(&curve);
+        state.vsol_reserve_amount() - state.initial_vsol_reserve()
+    };
+    let (tokens, quote) = (900_000_000 - 500_000_000, collateral - target.fee());
+
+    let migration = svm.external_migration(&curve);
+    assert_eq!(svm.process(&migration.instruction), ProgramResult::Success);
+
+    // The authority holds the liquidity it signed the pool creation for; the stub DEX
+    // leaves it where it is
+    assert_eq!(svm.token_amount(&migration.token_account), tokens);
+    assert_eq!(svm.token_amount(&migration.quote_account), quote);
+    let (data, accounts) = svm.recorded(&migration.pool);
+    let authority_index = match target {
+        MigrationTarget::RaydiumCpmm => {
+            let (amount_0, amount_1) = if curve.vtoken_mint < curve.quote_mint {
+                (tokens, quote)
+            } else {
+                (quote, tokens)
+            };
+            let expected = [
+                &RAYDIUM_CPMM_INITIALIZE[..],
+                &amount_0.to_le_bytes(),
+                &amount_1.to_le_bytes(),
+                &0u64.to_le_bytes(),
+            ]
+            .concat();
+            assert_eq!(data, expected);
+            0
+        }
+        MigrationTarget::MeteoraDamm => {
+            let expected = [
+                &METEORA_DAMM_INITIALIZE_PERMISSIONLESS_POOL[..],
+                &[0],
+                &tokens.to_le_bytes(),
+                &quote.to_le_bytes(),
+            ]
+            .concat();
+            assert_eq!(data, expected);
+            15
+        }
+        MigrationTarget::InHouse => unreachable!(),
+    };
+    let keys: Vec<Pubkey> = accounts.iter().map(|(key, _, _)| *key).collect();
+    assert_eq!(keys, migration.dex_accounts);
+    for (index, (key, is_signer, is_writable)) in accounts.into_iter().enumerate() {
+        // Only the authority signs, as the creator of the pool
+        assert_eq!(is_signer, index == authority_index, "signer {key}");
+        if index == authority_index {
+            assert_eq!(key, migration.authority);
+            assert!(is_writable);
+        }
+    }
+
+    // The liquidity the DEX minted to the authority is burned or locked
+    assert_eq!(svm.token_amount(&migration.lp_account), 0);
+    let lp_supply = Mint::unpack(svm.account(&migration.lp_mint).data())
+        .unwrap()
+        .supply;
+    match migration.lp_lock {
+        None => assert_eq!(lp_supply, 0),
+        Some(lp_lock) => {
+            assert_eq!(lp_supply, LP_AMOUNT);
+            let lp_lock = Account::unpack(svm.account(&lp_lock).data()).unwrap();
+            assert_eq!(lp_lock.amount, LP_AMOUNT);
+            assert_eq!(lp_lock.owner, curve.state);
+        }
+    }
+
+    assert_eq!(svm.state(&curve).status(), Ok(CurveStatus::Migrated));
+    let mint = Mint::unpack(svm.account(&curve.vtoken_mint).data()).unwrap();
+    assert_eq!(mint.mint_authority, COption::None);
+}
+
+#[test]
+#[ignore = "requires the SBF builds of the program and the stub DEX (cargo build-sbf)"]
+fn test_migrate_to_raydium_cpmm() {
+    let mut svm = Svm::new();
+    svm.add_stub_dex();
+    check_external_migration(&mut svm, MigrationTarget::RaydiumCpmm, LpPolicy::Burn);
+}
+
+#[test]
+#[ignore = "requires the SBF builds of the program and the stub DEX (cargo build-sbf)"]
+fn test_migrate_to_meteora_damm() {
+    let mut svm = Svm::new();
+    svm.add_stub_dex();
+    check_external_migration(&mut svm, MigrationTarget::MeteoraDamm, LpPolicy::Burn);
+}
+
+#[test]
+#[ignore = "requires the SBF builds of the program and the stub DEX (cargo build-sbf)"]
+fn test_external_migration_locks_lp() {
+    for target in [MigrationTarget::RaydiumCpmm, MigrationTarget::MeteoraDamm] {
+        let mut svm = Svm::new();
+        svm.add_stub_dex();
+        check_external_migration(&mut svm, target, LpPolicy::Lock);
+    }
+}
+
+#[test]
+#[ignore = "requires the SBF builds of the program and the stub DEX (cargo build-sbf)"]
+fn test_external_migration_checks_lp_account() {
+    for target in [MigrationTarget::RaydiumCpmm, MigrationTarget::MeteoraDamm] {
+        let mut svm = Svm::new();
+        svm.add_stub_dex();
+        let curve = svm.complete_external(target, LpPolicy::Burn);
+        let migration = svm.external_migration(&curve);
+
+        // The DEX minted the liquidity to an account the authority does not own
+        let lp_account = svm.token_account(migration.lp_mint, Pubkey::new_unique(), 0);
+        svm.set_account(migration.lp_account, lp_account);
+
+        assert_eq!(
+            svm.process(&migration.instruction),
+            ProgramResult::Failure(ProgramError::Custom(
+                BondingCurveError::InvalidMigrationAccount as u32
+            ))
+        );
+    }
+}
@@ -0,0 +1,450 @@
+use pinocchio::program_error::ProgramError;
+
+use super::harness::{unique_key, with_account_infos, TestAccount};
+use crate::{
+    amm::Pool,
+    curve::CurveType,
+    error::BondingCurveError,
+    fees::Fees,
+    migration::{LpPolicy, MigrationTarget, UnsoldTokenPolicy},
+    state::{
+        BondingCurveState, CurveStatus, GlobalConfig, PoolState, ProgramAccount, QuoteMode,
+        ReferrerStats, SupplyMode,
+    },
+    InitializeArgs, ID,
+};
+
+fn state_account(data: Vec<u8>) -> TestAccount {
+    TestAccount::new(unique_key(), ID, data)
+}
+
+#[test]
+fn test_state_round_trip() {
+    let vtoken_mint = unique_key();
+    let vsol_mint = unique_key();
+    let creator = unique_key();
+    let mut accounts = [state_account(vec![0; BondingCurveState::LEN])];
+
+    with_account_infos(&mut accounts, |accounts| {
+        let mut state = BondingCurveState::init(&accounts[0]).unwrap();
+        state.set_vtoken_reserve_amount(1);
+        state.set_vtoken_mint(&vtoken_mint);
+        state.set_vsol_reserve_amount(2);
+        state.set_vsol_mint(&vsol_mint);
+        state.set_total_supply(3);
+        state.set_allocation_at_migration(4);
+        state.set_initial_vtoken_reserve(5);
+        state.set_initial_vsol_reserve(6);
+        state.set_curve_type(CurveType::Exponential);
+        state.set_growth_scale(7);
+        state.set_quote_mode(QuoteMode::Native);
+        state.set_quote_vault_bump(8);
+        state.set_fees(Fees {
+            fee_bps: 9,
+            creator_share_bps: 10,
+        });
+        state.set_creator(&creator);
+        state.set_protocol_fees(11);
+        state.set_creator_fees(12);
+        state.set_paused(true);
+        state.set_fee_vault_bump(13);
+        state.set_bump(14);
+        state.set_vault_bump(15);
+        state.set_supply_mode(SupplyMode::PreMinted);
+        state.set_token_vault_bump(16);
+        state.set_status(CurveStatus::Complete);
+        state.set_migration_target(MigrationTarget::MeteoraDamm);
+        state.set_lp_policy(LpPolicy::Lock);
+        state.set_lp_unlock_timestamp(-17);
+        state.set_lp_lock_bump(18);
+        state.set_unsold_token_policy(UnsoldTokenPolicy::Vest);
+        state.set_vesting_duration(19);
+        state.set_vesting_start(-20);
+        state.set_vesting_amount(21);
+        state.set_vesting_claimed(22);
+        state.set_vesting_bump(23);
+        state.set_migration_fee(24);
+        state.set_migration_bounty(25);
+    });
+
+    with_account_infos(&mut accounts, |accounts| {
+        let state = BondingCurveState::load(&accounts[0]).unwrap();
+        assert_eq!(state.discriminator(), &BondingCurveState::DISCRIMINATOR);
+        assert_eq!(state.version(), BondingCurveState::VERSION);
+        assert_eq!(state.vtoken_reserve_amount(), 1);
+        assert_eq!(state.vtoken_mint(), &vtoken_mint);
+        assert_eq!(state.vsol_reserve_amount(), 2);
+        assert_eq!(state.vsol_mint(), &vsol_mint);
+        assert_eq!(state.total_supply(), 3);
+        assert_eq!(state.allocation_at_migration(), 4);
+        assert_eq!(state.initial_vtoken_reserve(), 5);
+        assert_eq!(state.initial_vsol_reserve(), 6);
+        assert_eq!(state.curve_type(), Ok(CurveType::Exponential));
+        assert_eq!(state.growth_scale(), 7);
+        assert_eq!(state.quote_mode(), Ok(QuoteMode::Native));
+        assert_eq!(state.quote_vault_bump(), 8);
+        assert_eq!(
+            state.fees(),
+            Fees {
+                fee_bps: 9,
+                creator_share_bps: 10,
+            }
+        );
+        assert_eq!(state.creator(), &creator);
+        assert_eq!(state.protocol_fees(), 11);
+        assert_eq!(state.creator_fees(), 12);
+        assert_eq!(state.accrued_fees(), Ok(23));
+        assert!(state.paused());
+        assert_eq!(state.fee_vault_bump(), 13);
+        assert_eq!(state.bump(), 14);
+        assert_eq!(state.vault_bump(), 15);
+        assert_eq!(state.supply_mode(), Ok(SupplyMode::PreMinted));
+        assert_eq!(state.token_vault_bump(), 16);
+        assert_eq!(state.status(), Ok(CurveStatus::Complete));
+        assert_eq!(state.migration_target(), Ok(MigrationTarget::MeteoraDamm));
+        assert_eq!(state.lp_policy(), Ok(LpPolicy::Lock));
+        assert_eq!(state.lp_unlock_timestamp(), -17);
+        assert_eq!(state.lp_lock_bump(), 18);
+        assert_eq!(state.unsold_token_policy(), Ok(UnsoldTokenPolicy::Vest));
+        assert_eq!(state.vesting_duration(), 19);
+        assert_eq!(state.vesting_start(), -20);
+        assert_eq!(state.vesting_amount(), 21);
+        assert_eq!(state.vesting_claimed(), 22);
+        assert_eq!(state.vesting_bump(), 23);
+        assert_eq!(state.migration_fee(), 24);
+        assert_eq!(state.migration_bounty(), 25);
+    });
+
+    // Pin the serialized layout so clients can rely on the offsets.
+    let data = &accounts[0].data;
+    assert_eq!(BondingCurveState::LEN, 252);
+    assert_eq!(data[0..8], BondingCurveState::DISCRIMINATOR);
+    assert_eq!(data[8], BondingCurveState::VERSION);
+    assert_eq!(data[9..17], 1u64.to_le_bytes());
+    assert_eq!(data[17..49], vtoken_mint);
+    assert_eq!(data[49..57], 2u64.to_le_bytes());
+    assert_eq!(data[57..89], vsol_mint);
+    assert_eq!(data[89..97], 3u64.to_le_bytes());
+    assert_eq!(data[97..105], 4u64.to_le_bytes());
+    assert_eq!(data[105..113], 5u64.to_le_bytes());
+    assert_eq!(data[113..121], 6u64.to_le_bytes());
+    assert_eq!(data[121], CurveType::Exponential as u8);
+    assert_eq!(data[122..130], 7u64.to_le_bytes());
+    assert_eq!(data[130], QuoteMode::Native as u8);
+    assert_eq!(data[131], 8);
+    assert_eq!(data[132..134], 9u16.to_le_bytes());
+    assert_eq!(data[134..136], 10u16.to_le_bytes());
+    assert_eq!(data[136..168], creator);
+    assert_eq!(data[168..176], 11u64.to_le_bytes());
+    assert_eq!(data[176..184], 12u64.to_le_bytes());
+    assert_eq!(data[184], 1);
+    assert_eq!(data[185], 13);
+    assert_eq!(data[186], 14);
+    assert_eq!(data[187], 15);
+    assert_eq!(data[188], SupplyMode::PreMinted as u8);
+    assert_eq!(data[189], 16);
+    assert_eq!(data[190], CurveStatus::Complete as u8);
+    assert_eq!(data[191], MigrationTarget::MeteoraDamm as u8);
+    assert_eq!(data[192], LpPolicy::Lock as u8);
+    assert_eq!(data[193..201], (-17i64).to_le_bytes());
+    assert_eq!(data[201], 18);
+    assert_eq!(data[202], UnsoldTokenPolicy::Vest as u8);
+    assert_eq!(data[203..211], 19u64.to_le_bytes());
+    assert_eq!(data[211..219], (-20i64).to_le_bytes());
+    assert_eq!(data[219..227], 21u64.to_le_bytes());
+    assert_eq!(data[227..235], 22u64.to_le_bytes());
+    assert_eq!(data[235], 23);
+    assert_eq!(data[236..244], 24u64.to_le_bytes());
+    assert_eq!(data[244..252], 25u64.to_le_bytes());
+}
+
+#[test]
+fn test_vested_tokens_release_linearly() {
+    let mut accounts = [state_account(vec![0; BondingCurveState::LEN])];
+
+    with_account_infos(&mut accounts, |accounts| {
+        let mut state = BondingCurveState::init(&accounts[0]).unwrap();
+        state.set_vesting_amount(1_000);
+        state.set_vesting_start(100);
+        state.set_vesting_duration(50);
+        assert_eq!(state.vested_tokens(90), Ok(0));
+        assert_eq!(state.vested_tokens(100), Ok(0));
+        assert_eq!(state.vested_tokens(125), Ok(500));
+        assert_eq!(state.vested_tokens(149), Ok(980));
+        assert_eq!(state.vested_tokens(150), Ok(1_000));
+        assert_eq!(state.vested_tokens(i64::MAX), Ok(1_000));
+
+        // Without a duration everything vests at the start
+        state.set_vesting_duration(0);
+        assert_eq!(state.vested_tokens(100), Ok(1_000));
+    });
+}
+
+#[test]
+fn test_config_round_trip() {
+    let admin = unique_key();
+    let pending_admin = unique_key();
+    let fee_recipient = unique_key();
+    let default_args = InitializeArgs {
+        initial_vtoken_reserve: 3,
+        initial_vsol_reserve: 4,
+        total_supply: 5,
+        allocation_at_migration: 6,
+        curve_type: CurveType::Exponential,
+        growth_scale: 7,
+        quote_mode: QuoteMode::SplToken,
+        quote_vault_bump: 0,
+        fees: Fees {
+            fee_bps: 8,
+            creator_share_bps: 9,
+        },
+        supply_mode: SupplyMode::MintOnBuy,
+        token_vault_bump: 0,
+        migration_target: MigrationTarget::RaydiumCpmm,
+        lp_policy: LpPolicy::Burn,
+        lp_unlock_timestamp: 0,
+        unsold_token_policy: UnsoldTokenPolicy::SeedPool,
+        vesting_duration: 0,
+    };
+    let mut accounts = [state_account(vec![0; GlobalConfig::LEN])];
+
+    with_account_infos(&mut accounts, |accounts| {
+        let mut config = GlobalConfig::init(&accounts[0]).unwrap();
+        config.set_bump(1);
+        config.set_admin(&admin);
+        config.set_pending_admin(Some(&pending_admin));
+        config.set_fee_recipient(&fee_recipient);
+        config.set_migration_fee(2);
+        config.set_paused(true);
+        config.set_default_args(&default_args);
+        config.set_referral_fee_share_bps(10);
+        config.set_migration_bounty(11);
+        config.set_min_protocol_fee_bps(12);
+    });
+
+    with_account_infos(&mut accounts, |accounts| {
+        let config = GlobalConfig::load(&accounts[0]).unwrap();
+        assert_eq!(config.discriminator(), &GlobalConfig::DISCRIMINATOR);
+        assert_eq!(config.version(), GlobalConfig::VERSION);
+        assert_eq!(config.bump(), 1);
+        assert_eq!(config.admin(), &admin);
+        assert_eq!(config.pending_admin(), Some(&pending_admin));
+        assert_eq!(config.fee_recipient(), &fee_recipient);
+        assert_eq!(config.migration_fee(), 2);
+        assert!(config.paused());
+        assert_eq!(config.default_args(), Ok(default_args));
+        assert_eq!(config.referral_fee_share_bps(), 10);
+        assert_eq!(config.migration_bounty(), 11);
+        assert_eq!(config.min_protocol_fee_bps(), 12);
+
+        // A config is not a curve, nor the other way around
+        assert_eq!(
+            BondingCurveState::load(&accounts[0]).err(),
+            Some(ProgramError::InvalidAccountData)
+        );
+    });
+
+    // Pin the serialized layout so clients can rely on the offsets.
+    let data = &accounts[0].data;
+    assert_eq!(GlobalConfig::LEN, 173);
+    assert_eq!(data[0..8], GlobalConfig::DISCRIMINATOR);
+    assert_eq!(data[8], GlobalConfig::VERSION);
+    assert_eq!(data[9], 1);
+    assert_eq!(data[10..42], admin);
+    assert_eq!(data[42..74], fee_recipient);
+    assert_eq!(data[74..82], 2u64.to_le_bytes());
+    assert_eq!(data[82], 1);
+    assert_eq!(data[83..91], 3u64.to_le_bytes());
+    assert_eq!(data[91..99], 4u64.to_le_bytes());
+    assert_eq!(data[99..107], 5u64.to_le_bytes());
+    assert_eq!(data[107..115], 6u64.to_le_bytes());
+    assert_eq!(data[115], CurveType::Exponential as u8);
+    assert_eq!(data[116..124], 7u64.to_le_bytes());
+    assert_eq!(data[124..126], 8u16.to_le_bytes());
+    assert_eq!(data[126..128], 9u16.to_le_bytes());
+    assert_eq!(data[128..160], pending_admin);
+    assert_eq!(data[160..162], 10u16.to_le_bytes());
+    assert_eq!(data[162], MigrationTarget::RaydiumCpmm as u8);
+    assert_eq!(data[163..171], 11u64.to_le_bytes());
+    assert_eq!(data[171..173], 12u16.to_le_bytes());
+}
+
+#[test]
+fn test_referrer_stats_round_trip() {
+    let referrer = unique_key();
+    let mut accounts = [state_account(vec![0; ReferrerStats::LEN])];
+
+    with_account_infos(&mut accounts, |accounts| {
+        let mut stats = ReferrerStats::init(&accounts[0]).unwrap();
+        stats.set_bump(1);
+        stats.set_referrer(&referrer);
+        stats.record(2).unwrap();
+        stats.record(3).unwrap();
+    });
+
+    with_account_infos(&mut accounts, |accounts| {
+        let stats = ReferrerStats::load(&accounts[0]).unwrap();
+        assert_eq!(stats.discriminator(), &ReferrerStats::DISCRIMINATOR);
+        assert_eq!(stats.version(), ReferrerStats::VERSION);
+        assert_eq!(stats.bump(), 1);
+        assert_eq!(stats.referrer(), &referrer);
+        assert_eq!(stats.total_fees(), 5);
+        assert_eq!(stats.trade_count(), 2);
+    });
+
+    // Pin the serialized layout so clients can rely on the offsets.
+    let data = &accounts[0].data;
+    assert_eq!(ReferrerStats::LEN, 58);
+    assert_eq!(data[0..8], ReferrerStats::DISCRIMINATOR);
+    assert_eq!(data[8], ReferrerStats::VERSION);
+    assert_eq!(data[9], 1);
+    assert_eq!(data[10..42], referrer);
+    assert_eq!(data[42..50], 5u64.to_le_bytes());
+    assert_eq!(data[50..58], 2u64.to_le_bytes());
+}
+
+#[test]
+fn test_pool_round_trip() {
+    let curve = unique_key();
+    let token_mint = unique_key();
+    let quote_mint = unique_key();
+    let pool = Pool {
+        token_reserve: 7,
+        quote_reserve: 8,
+        lp_supply: 9,
+    };
+    let mut accounts = [state_account(vec![0; PoolState::LEN])];
+
+    with_account_infos(&mut accounts, |accounts| {
+        let mut state = PoolState::init(&accounts[0]).unwrap();
+        state.set_bump(1);
+        state.set_curve(&curve);
+        state.set_token_mint(&token_mint);
+        state.set_quote_mint(&quote_mint);
+        state.set_quote_mode(QuoteMode::Native);
+        state.set_token_vault_bump(2);
+        state.set_quote_vault_bump(3);
+        state.set_lp_mint_bump(4);
+        state.set_lp_vault_bump(5);
+        state.set_pool(&pool);
+    });
+
+    with_account_infos(&mut accounts, |accounts| {
+        let state = PoolState::load(&accounts[0]).unwrap();
+        assert_eq!(state.discriminator(), &PoolState::DISCRIMINATOR);
+        assert_eq!(state.version(), PoolState::VERSION);
+        assert_eq!(state.bump(), 1);
+        assert_eq!(state.curve(), &curve);
+        assert_eq!(state.token_mint(), &token_mint);
+        assert_eq!(state.quote_mint(), &quote_mint);
+        assert_eq!(state.quote_mode(), Ok(QuoteMode::Native));
+        assert_eq!(state.token_vault_bump(), 2);
+        assert_eq!(state.quote_vault_bump(), 3);
+        assert_eq!(state.lp_mint_bump(), 4);
+        assert_eq!(state.lp_vault_bump(), 5);
+        assert_eq!(state.pool(), pool);
+    });
+
+    // Pin the serialized layout so clients can rely on the offsets.
+    let data = &accounts[0].data;
+    assert_eq!(PoolState::LEN, 135);
+    assert_eq!(data[0..8], PoolState::DISCRIMINATOR);
+    assert_eq!(data[8], PoolState::VERSION);
+    assert_eq!(data[9], 1);
+    assert_eq!(data[10..42], curve);
+    assert_eq!(data[42..74], token_mint);
+    assert_eq!(data[74..106], quote_mint);
+    assert_eq!(data[106], QuoteMode::Native as u8);
+    assert_eq!(data[107..111], [2, 3, 4, 5]);
+    assert_eq!(data[111..119], 7u64.to_le_bytes());
+    assert_eq!(data[119..127], 8u64.to_le_bytes());
+    assert_eq!(data[127..135], 9u64.to_le_bytes());
+}
+
+#[test]
+fn test_load_rejects_uninitialized_state() {
+    let mut accounts = [state_account(vec![0; BondingCurveState::LEN])];
+
+    with_account_infos(&mut accounts, |accounts| {
+        assert_eq!(
+            BondingCurveState::load(&accounts[0]).err(),
+            Some(ProgramError::UninitializedAccount)
+        );
+    });
+}
+
+#[test]
+fn test_load_rejects_wrong_discriminator() {
+    let mut data = vec![0; BondingCurveState::LEN];
+    data[..8].copy_from_slice(b"config\0\0");
+    data[8] = BondingCurveState::VERSION;
+    let mut accounts = [state_account(data)];
+
+    with_account_infos(&mut accounts, |accounts| {
+        assert_eq!(
+            BondingCurveState::load(&accounts[0]).err(),
+            Some(BondingCurveError::InvalidAccountDiscriminator.into())
+        );
+        assert_eq!(
+            BondingCurveState::init(&accounts[0]).err(),
+            Some(ProgramError::AccountAlreadyInitialized)
+        );
+    });
+}
+
+#[test]
+fn test_load_rejects_unsupported_version() {
+    // States written before the last layout change, and by newer programs
+    for version in [
+        BondingCurveState::VERSION - 1,
+        BondingCurveState::VERSION + 1,
+    ] {
+        let mut data = vec![0; BondingCurveState::LEN];
+        data[..8].copy_from_slice(&BondingCurveState::DISCRIMINATOR);
+        data[8] = version;
+        let mut accounts = [state_account(data)];
+
+        with_account_infos(&mut accounts, |accounts| {
+            assert_eq!(
+                BondingCurveState::load_mut(&accounts[0]).err(),
+                Some(BondingCurveError::UnsupportedAccountVersion.into())
+            );
+        });
+    }
+}
+
+#[test]
+fn test_load_rejects_wrong_size() {
+    let mut accounts = [state_account(vec![0; BondingCurveState::LEN - 1])];
+
+    with_account_infos(&mut accounts, |accounts| {
+        assert_eq!(
+            BondingCurveState::load(&accounts[0]).err(),
+            Some(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            BondingCurveState::load_mut(&accounts[0]).err(),
+            Some(ProgramError::InvalidAccountData)
+        );
+    });
+}
+
+#[test]
+fn test_load_rejects_wrong_owner() {
+    let mut accounts = [TestAccount::new(
+        unique_key(),
+        unique_key(),
+        vec![0; BondingCurveState::LEN],
+    )];
+
+    with_account_infos(&mut accounts, |accounts| {
+        assert_eq!(
+            BondingCurveState::load(&accounts[0]).err(),
+            Some(ProgramError::InvalidAccountOwner)
+        );
+        assert_eq!(
+            BondingCurveState::load_mut(&accounts[0]).err(),
+            Some(ProgramError::InvalidAccountOwner)
+        );
+    });
+}
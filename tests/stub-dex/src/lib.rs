@@ -0,0 +1,123 @@
+//! Stand-in for the DEX programs curves migrate to, for the tests of the program
+//! under mollusk. Deployed at the Raydium CPMM and Meteora DAMM program ids, it takes
+//! their pool-creation instruction, records it in the pool account and mints the seed
+//! liquidity to the creator's liquidity account, as the DEX would.
+//!
+//! The record holds the length of the instruction data as a little-endian `u16`, the
+//! data and the number of accounts, then the key, signer flag and writable flag of
+//! each account.
+
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{self, Pubkey},
+    ProgramResult,
+};
+use pinocchio_token::instructions::MintTo;
+
+#[cfg(not(feature = "no-entrypoint"))]
+use pinocchio::entrypoint;
+
+#[cfg(not(feature = "no-entrypoint"))]
+entrypoint!(process_instruction);
+
+/// Raydium constant product AMM (CPMM) program.
+pub const RAYDIUM_CPMM_ID: Pubkey =
+    five8_const::decode_32_const("CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C");
+
+/// Meteora dynamic AMM (DAMM v1) program.
+pub const METEORA_DAMM_ID: Pubkey =
+    five8_const::decode_32_const("Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB");
+
+/// Seed of the PDA of the stub that is the mint authority of the liquidity mints.
+pub const LP_MINT_AUTHORITY_SEED: &[u8] = b"lp_mint_authority";
+
+/// Liquidity minted for every pool.
+pub const LP_AMOUNT: u64 = 1_000_000_000;
+
+/// Length of the record of an account: its key and its two flags.
+pub const RECORD_ACCOUNT_LEN: usize = 34;
+
+/// Positions of the accounts the stub uses in a pool-creation instruction.
+struct Layout {
+    /// The pool, owned by the stub, where the instruction is recorded.
+    pool: usize,
+    /// The PDA at [`LP_MINT_AUTHORITY_SEED`].
+    lp_mint_authority: usize,
+    lp_mint: usize,
+    lp_account: usize,
+}
+
+/// Raydium CPMM `initialize`, whose pool authority mints the liquidity.
+const RAYDIUM_CPMM: Layout = Layout {
+    pool: 3,
+    lp_mint_authority: 2,
+    lp_mint: 6,
+    lp_account: 9,
+};
+
+/// Meteora DAMM `initialize_permissionless_pool`, whose pool mints the liquidity.
+const METEORA_DAMM: Layout = Layout {
+    pool: 0,
+    lp_mint_authority: 0,
+    lp_mint: 1,
+    lp_account: 12,
+};
+
+/// Records the pool-creation instruction, then mints [`LP_AMOUNT`] of liquidity to the
+/// creator's liquidity account.
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let layout = match *program_id {
+        RAYDIUM_CPMM_ID => &RAYDIUM_CPMM,
+        METEORA_DAMM_ID => &METEORA_DAMM,
+        _ => return Err(ProgramError::IncorrectProgramId),
+    };
+    let account = |index: usize| {
+        accounts
+            .get(index)
+            .ok_or(ProgramError::NotEnoughAccountKeys)
+    };
+
+    record(account(layout.pool)?, accounts, instruction_data)?;
+
+    let (_, bump) = pubkey::find_program_address(&[LP_MINT_AUTHORITY_SEED], program_id);
+    let bump = [bump];
+    let seeds = [Seed::from(LP_MINT_AUTHORITY_SEED), Seed::from(&bump)];
+    MintTo {
+        mint: account(layout.lp_mint)?,
+        token: account(layout.lp_account)?,
+        mint_authority: account(layout.lp_mint_authority)?,
+        amount: LP_AMOUNT,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])
+}
+
+/// Writes `data` and the keys and privileges of `accounts` to `pool`.
+fn record(pool: &AccountInfo, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let mut record = pool.try_borrow_mut_data()?;
+    let len = 2 + data.len() + 1 + accounts.len() * RECORD_ACCOUNT_LEN;
+    let record = record
+        .get_mut(..len)
+        .ok_or(ProgramError::AccountDataTooSmall)?;
+
+    let (data_len, rest) = record.split_at_mut(2);
+    data_len.copy_from_slice(&(data.len() as u16).to_le_bytes());
+    let (recorded_data, rest) = rest.split_at_mut(data.len());
+    recorded_data.copy_from_slice(data);
+    rest[0] = accounts.len() as u8;
+    for (account, recorded) in accounts
+        .iter()
+        .zip(rest[1..].chunks_exact_mut(RECORD_ACCOUNT_LEN))
+    {
+        recorded[..32].copy_from_slice(account.key());
+        recorded[32] = account.is_signer() as u8;
+        recorded[33] = account.is_writable() as u8;
+    }
+
+    Ok(())
+}